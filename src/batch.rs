@@ -0,0 +1,181 @@
+use crate::http::HttpClient;
+use crate::storage::Storage;
+use crate::{AppError, Result};
+use futures::stream::{self, StreamExt};
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{info, instrument, warn};
+
+#[derive(Debug, Clone)]
+pub struct FetchManyOptions {
+    pub resume: bool,
+    pub retry_failed: bool,
+    pub concurrency: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct FetchManyReport {
+    pub fetched: usize,
+    pub already_done: usize,
+    pub failed: Vec<(String, String)>,
+}
+
+/// Where `fetch_many` records fetched URLs for `list`: `<list>.manifest`.
+pub fn manifest_path(list: &Path) -> PathBuf {
+    sibling_path(list, ".manifest")
+}
+
+/// Where `fetch_many` records the URLs of `list` that failed, as NDJSON
+/// `{"url": ..., "error": ...}` lines: `<list>.failed`.
+pub fn failed_path(list: &Path) -> PathBuf {
+    sibling_path(list, ".failed")
+}
+
+fn sibling_path(list: &Path, suffix: &str) -> PathBuf {
+    let mut name = list.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// GETs every URL in `list` (one per line; blank lines and `#` comments are
+/// skipped) with up to `concurrency` in flight and stores each response under
+/// its URL as the key. Requests also go through the client's rate limiter
+/// and concurrency limit.
+///
+/// Fetched URLs are appended to the manifest as they finish and failed ones
+/// to the failed file, which is rewritten on every run. With `resume`, URLs in
+/// the manifest are skipped; with `retry_failed`, only the URLs in the failed
+/// file are fetched. Both files are removed once every URL has been fetched.
+#[instrument(skip_all, fields(list = %list.display()))]
+pub async fn fetch_many(
+    client: &HttpClient,
+    storage: &Storage,
+    list: &Path,
+    options: &FetchManyOptions,
+) -> Result<FetchManyReport> {
+    let urls = read_url_list(list)?;
+    let total = urls.len();
+    let manifest = manifest_path(list);
+    let failed = failed_path(list);
+
+    if !options.resume && !options.retry_failed && manifest.exists() {
+        return Err(AppError::Validation {
+            message: format!(
+                "An interrupted fetch-many left {}; pass --resume to continue it or delete the file to start over",
+                manifest.display()
+            ),
+        });
+    }
+    let done: HashSet<String> = if manifest.exists() {
+        fs::read_to_string(&manifest)?.lines().map(str::to_string).collect()
+    } else {
+        HashSet::new()
+    };
+    let retry: Option<HashSet<String>> = if options.retry_failed {
+        if !failed.exists() {
+            return Err(AppError::NotFound {
+                resource: format!("failed URL list {}", failed.display()),
+            });
+        }
+        Some(read_failed(&failed)?.into_iter().collect())
+    } else {
+        None
+    };
+
+    let mut report = FetchManyReport::default();
+    let pending: Vec<String> = urls
+        .into_iter()
+        .filter(|url| {
+            let finished = done.contains(url);
+            if finished {
+                report.already_done += 1;
+            }
+            !finished && retry.as_ref().is_none_or(|retry| retry.contains(url))
+        })
+        .collect();
+
+    let log = Mutex::new(OpenOptions::new().create(true).append(true).open(&manifest)?);
+    let failures = Mutex::new(File::create(&failed)?);
+    let outcomes: Vec<(String, Result<()>)> = stream::iter(pending)
+        .map(|url| fetch_one(client, storage, url, &log, &failures))
+        .buffer_unordered(options.concurrency.max(1))
+        .collect()
+        .await;
+
+    for (url, outcome) in outcomes {
+        match outcome {
+            Ok(()) => report.fetched += 1,
+            Err(e) => report.failed.push((url, e.to_string())),
+        }
+    }
+
+    if report.failed.is_empty() {
+        fs::remove_file(&failed)?;
+        // URLs skipped by `retry_failed` are not done yet, so the manifest stays.
+        if report.fetched + report.already_done == total {
+            fs::remove_file(&manifest)?;
+        }
+    }
+    info!(
+        "Fetched {} URLs ({} already done, {} failed)",
+        report.fetched,
+        report.already_done,
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+async fn fetch_one(
+    client: &HttpClient,
+    storage: &Storage,
+    url: String,
+    log: &Mutex<File>,
+    failures: &Mutex<File>,
+) -> (String, Result<()>) {
+    let outcome: Result<()> = async {
+        let response = client.fetch_json(&url).await?;
+        storage.store(url.clone(), response).await?;
+        writeln!(log.lock().unwrap(), "{}", url)?;
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = &outcome {
+        warn!("Failed to fetch {}: {}", url, e);
+        let record = json!({ "url": url, "error": e.to_string() });
+        if let Err(write_error) = writeln!(failures.lock().unwrap(), "{}", record) {
+            warn!("Failed to record failed URL {}: {}", url, write_error);
+        }
+    }
+    (url, outcome)
+}
+
+/// The distinct URLs of `list`, in order of first appearance.
+fn read_url_list(list: &Path) -> Result<Vec<String>> {
+    let mut seen = HashSet::new();
+    Ok(fs::read_to_string(list)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter(|line| seen.insert(line.to_string()))
+        .map(str::to_string)
+        .collect())
+}
+
+fn read_failed(path: &Path) -> Result<Vec<String>> {
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            let record: serde_json::Value = serde_json::from_str(line)?;
+            record["url"].as_str().map(str::to_string).ok_or_else(|| AppError::Validation {
+                message: format!("{} line {}: expected {{\"url\": ...}}", path.display(), index + 1),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,104 @@
+use crate::http::HttpClient;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Latencies are in milliseconds and only cover requests that got a
+/// response; transport failures count as errors without a latency.
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub requests: usize,
+    pub concurrency: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub error_rate: f64,
+    pub elapsed_ms: f64,
+    pub requests_per_second: f64,
+    pub latency: Option<Latency>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Latency {
+    pub min: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl BenchReport {
+    pub fn summary(&self) -> String {
+        let mut lines = vec![
+            format!("Requests:    {} ({} concurrent)", self.requests, self.concurrency),
+            format!(
+                "Succeeded:   {} ({} failed, {:.1}% errors)",
+                self.succeeded,
+                self.failed,
+                self.error_rate * 100.0
+            ),
+            format!("Duration:    {:.1} ms", self.elapsed_ms),
+            format!("Throughput:  {:.1} req/s", self.requests_per_second),
+        ];
+        if let Some(latency) = &self.latency {
+            lines.push(format!(
+                "Latency ms:  min {:.1}  p50 {:.1}  p90 {:.1}  p99 {:.1}  max {:.1}",
+                latency.min, latency.p50, latency.p90, latency.p99, latency.max
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Sends `requests` GETs to `url`, at most `concurrency` at a time. The
+/// client's rate limit and shared concurrency limit still apply on top.
+/// Non-2xx responses count as errors.
+pub async fn run(client: &HttpClient, url: &str, requests: usize, concurrency: usize) -> BenchReport {
+    let start = Instant::now();
+    let outcomes: Vec<Option<Duration>> = stream::iter(0..requests)
+        .map(|_| async {
+            match client.fetch_with_meta(url).await {
+                Ok(response) if response.status.is_success() => Some(response.elapsed),
+                Ok(response) => {
+                    debug!("Bench request returned HTTP {}", response.status);
+                    None
+                }
+                Err(e) => {
+                    debug!("Bench request failed: {}", e);
+                    None
+                }
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    let elapsed = start.elapsed();
+
+    let mut latencies: Vec<f64> = outcomes.iter().flatten().map(|latency| latency.as_secs_f64() * 1000.0).collect();
+    latencies.sort_by(f64::total_cmp);
+    let succeeded = latencies.len();
+    let failed = requests - succeeded;
+
+    BenchReport {
+        requests,
+        concurrency,
+        succeeded,
+        failed,
+        error_rate: if requests == 0 { 0.0 } else { failed as f64 / requests as f64 },
+        elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+        requests_per_second: if elapsed.is_zero() { 0.0 } else { requests as f64 / elapsed.as_secs_f64() },
+        latency: (!latencies.is_empty()).then(|| Latency {
+            min: latencies[0],
+            p50: percentile(&latencies, 50.0),
+            p90: percentile(&latencies, 90.0),
+            p99: percentile(&latencies, 99.0),
+            max: latencies[latencies.len() - 1],
+        }),
+    }
+}
+
+/// Nearest-rank percentile of an ascending, non-empty slice.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
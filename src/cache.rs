@@ -0,0 +1,161 @@
+use crate::http::{self, HttpClient, RequestOptions};
+use crate::{AppError, Result};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+
+/// A cached GET response together with the validators needed to revalidate it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+    pub body: String,
+    /// Whether `body` is newline-delimited JSON.
+    #[serde(default)]
+    pub ndjson: bool,
+}
+
+/// Stores responses that carry an `ETag` or `Last-Modified` header, one file
+/// per URL, and revalidates them with conditional requests.
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// GETs `url`, sending `If-None-Match`/`If-Modified-Since` when a cached
+    /// copy exists. A 304 answer returns the cached body. NDJSON bodies are
+    /// parsed into an array, as they are without the cache.
+    pub async fn fetch(&self, client: &HttpClient, url: &str, options: &RequestOptions) -> Result<Value> {
+        let full_url = client.request_url(url, &options.query)?;
+        let cached = self.get(&full_url)?;
+
+        let mut headers = HeaderMap::new();
+        if let Some(entry) = &cached {
+            if let Some(value) = entry.etag.as_deref().and_then(|etag| HeaderValue::from_str(etag).ok()) {
+                headers.insert(IF_NONE_MATCH, value);
+            }
+            if let Some(value) = entry.last_modified.as_deref().and_then(|date| HeaderValue::from_str(date).ok()) {
+                headers.insert(IF_MODIFIED_SINCE, value);
+            }
+        }
+
+        let response = client.fetch_with_headers(url, &options.query, headers).await?;
+        if response.status == StatusCode::NOT_MODIFIED {
+            if let Some(mut entry) = cached {
+                info!("Cache revalidated for {} (304 Not Modified)", full_url);
+                entry.fetched_at = Utc::now();
+                self.put(&entry)?;
+                return parse_body(&entry);
+            }
+        }
+        if !response.status.is_success() {
+            return Err(AppError::OperationFailed {
+                reason: format!("HTTP {}: {}", response.status, response.body),
+            });
+        }
+
+        let header = |name| response.headers.get(name).and_then(|value: &HeaderValue| value.to_str().ok()).map(String::from);
+        let entry = CacheEntry {
+            url: full_url,
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            fetched_at: Utc::now(),
+            body: response.body,
+            ndjson: options.ndjson || http::is_ndjson(&response.headers),
+        };
+        let value = parse_body(&entry)?;
+        if entry.etag.is_some() || entry.last_modified.is_some() {
+            self.put(&entry)?;
+        } else {
+            debug!("Not caching {}: no ETag or Last-Modified", entry.url);
+        }
+        Ok(value)
+    }
+
+    /// The cached entry for an absolute URL. Unreadable entries count as misses.
+    pub fn get(&self, url: &str) -> Result<Option<CacheEntry>> {
+        let path = self.entry_path(url);
+        if !path.exists() {
+            return Ok(None);
+        }
+        match serde_json::from_str(&fs::read_to_string(&path)?) {
+            Ok(entry) => Ok(Some(entry)),
+            Err(e) => {
+                warn!("Ignoring unreadable cache entry {}: {}", path.display(), e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Every readable entry, sorted by URL.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                match serde_json::from_str::<CacheEntry>(&fs::read_to_string(&path)?) {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => warn!("Ignoring unreadable cache entry {}: {}", path.display(), e),
+                }
+            }
+        }
+        entries.sort_by(|a, b| a.url.cmp(&b.url));
+        Ok(entries)
+    }
+
+    /// Removes the entry for an absolute URL; returns whether one existed.
+    pub fn remove(&self, url: &str) -> Result<bool> {
+        let path = self.entry_path(url);
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(path)?;
+        Ok(true)
+    }
+
+    /// Removes every entry, readable or not, and returns how many files were deleted.
+    pub fn clear(&self) -> Result<usize> {
+        let mut removed = 0;
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+        info!("Cleared {} cached responses from {}", removed, self.dir.display());
+        Ok(removed)
+    }
+
+    fn put(&self, entry: &CacheEntry) -> Result<()> {
+        fs::write(self.entry_path(&entry.url), serde_json::to_string_pretty(entry)?)?;
+        Ok(())
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:x}.json", Sha256::digest(url.as_bytes())))
+    }
+}
+
+fn parse_body(entry: &CacheEntry) -> Result<Value> {
+    if entry.ndjson {
+        return Ok(Value::Array(http::parse_ndjson(&entry.body)?));
+    }
+    if entry.body.is_empty() {
+        return Ok(Value::Null);
+    }
+    Ok(serde_json::from_str(&entry.body)?)
+}
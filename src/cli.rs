@@ -1,5 +1,11 @@
+use crate::color::ColorChoice;
+use crate::config::ConfigFormat;
+use crate::filter::Condition;
+pub use crate::output::OutputFormat;
+use crate::AppError;
+use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "rcli")]
@@ -9,11 +15,59 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
 
-    #[arg(short, long, global = true)]
+    #[arg(short, long, global = true, help = "Load configuration only from this file, or from stdin with '-'")]
     pub config: Option<PathBuf>,
 
+    #[arg(long, global = true, conflicts_with_all = ["config", "config_dir"], help = "Ignore config files and RCLI_* variables and use built-in defaults")]
+    pub no_config: bool,
+
+    #[arg(long, global = true, value_name = "PATH", help = "Load environment variables from this file [default: ./.env if present]")]
+    pub env_file: Option<PathBuf>,
+
+    #[arg(long, global = true, env = "RCLI_CONFIG_DIR", help = "Directory to read the user config file from")]
+    pub config_dir: Option<PathBuf>,
+
+    #[arg(long, global = true, env = "RCLI_PROFILE", help = "Config profile to apply over the base configuration")]
+    pub profile: Option<String>,
+
+    #[arg(long, global = true, env = "RCLI_NAMESPACE", help = "Keep data in storage.data_dir/<namespace>/")]
+    pub namespace: Option<String>,
+
     #[arg(short, long, global = true)]
     pub verbose: bool,
+
+    #[arg(long, global = true, help = "Print the full error chain on failure (implied by --verbose)")]
+    pub debug_errors: bool,
+
+    #[arg(long, global = true, help = "Report errors as JSON on stdout")]
+    pub output_json: bool,
+
+    #[arg(long, global = true, help = "Print JSON on a single line, like --format json, whatever the command's default format")]
+    pub compact: bool,
+
+    #[arg(long, global = true, help = "Suppress confirmation messages")]
+    pub quiet: bool,
+
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto, help = "Colorize output and errors (NO_COLOR disables color regardless)")]
+    pub color: ColorChoice,
+
+    #[arg(long, global = true, value_parser = parse_url, help = "Override server.base_url for this invocation")]
+    pub base_url: Option<String>,
+
+    #[arg(long, global = true, value_parser = clap::value_parser!(u32).range(1..), help = "Override server.retry_attempts for this invocation")]
+    pub retries: Option<u32>,
+
+    #[arg(long, global = true, value_name = "CODES", value_delimiter = ',', value_parser = clap::value_parser!(u16).range(100..=599), help = "Override server.retry_on_status: response statuses to retry, e.g. 502,503,504")]
+    pub retry_on_status: Vec<u16>,
+
+    #[arg(long, global = true, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..), help = "Override server.connect_timeout_seconds: give up connecting to a host after this long")]
+    pub timeout_connect: Option<u64>,
+
+    #[arg(long, global = true, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..), help = "Abort the whole command if it runs longer than this")]
+    pub deadline: Option<u64>,
+
+    #[arg(long, global = true, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Run at most N storage and HTTP operations at once")]
+    pub max_concurrency: Option<usize>,
 }
 
 #[derive(Subcommand)]
@@ -28,41 +82,475 @@ pub enum Commands {
         
         #[arg(short, long, help = "Save response to file")]
         output: Option<PathBuf>,
+
+        #[arg(long, value_name = "DIR", conflicts_with = "output", help = "Save response to a file in DIR named after the URL path")]
+        output_dir: Option<PathBuf>,
+
+        #[arg(short, long = "query", value_name = "KEY=VALUE", value_parser = parse_key_value, help = "Query parameter to append (repeatable)")]
+        query: Vec<(String, String)>,
+
+        #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Truncate nesting deeper than this many levels")]
+        max_depth: Option<usize>,
+
+        #[arg(long, help = "Validate the response against a JSON Schema file")]
+        schema: Option<PathBuf>,
+
+        #[arg(long, conflicts_with_all = ["output", "output_dir"], help = "Copy the output to the clipboard instead of printing it")]
+        clipboard: bool,
+
+        #[arg(long, conflicts_with = "format", help = "Render the response through a Handlebars template file")]
+        template: Option<PathBuf>,
+
+        #[arg(long, help = "Sort object keys alphabetically at every level")]
+        sort_keys: bool,
+
+        #[arg(long, help = "Flatten nested objects and arrays into one object with dotted keys (a.b.0)")]
+        flatten: bool,
+
+        #[arg(long, help = "Cache the response and revalidate it with ETag/Last-Modified")]
+        cache: bool,
+
+        #[arg(long, help = "Parse the body as newline-delimited JSON into an array")]
+        ndjson: bool,
+
+        #[arg(
+            long,
+            requires = "output",
+            conflicts_with_all = ["format", "max_depth", "schema", "template", "sort_keys", "cache", "ndjson"],
+            help = "Download the raw body to --output, continuing a partial file with a Range request"
+        )]
+        resume: bool,
+    },
+    
+    #[command(about = "Fetch every URL in a list and store each response under its URL")]
+    FetchMany {
+        #[arg(help = "File with one URL per line (blank lines and # comments are skipped)")]
+        file: PathBuf,
+
+        #[arg(long, default_value_t = 4, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Number of URLs to fetch concurrently")]
+        concurrency: usize,
+
+        #[arg(long, help = "Continue an interrupted run, skipping URLs in its manifest")]
+        resume: bool,
+
+        #[arg(long, conflicts_with = "resume", help = "Fetch only the URLs that failed in the previous run")]
+        retry_failed: bool,
+
+        #[arg(long, value_name = "N", help = "Stop retrying failed requests once N retries have been made in this run")]
+        max_retries_total: Option<u32>,
+
+        #[arg(long, value_name = "SECONDS", help = "Stop retrying failed requests once this long has been spent waiting to retry")]
+        max_retry_seconds: Option<u64>,
+    },
+
+    #[command(about = "Print server-sent events from a text/event-stream URL as they arrive")]
+    Stream {
+        #[arg(help = "URL of the event stream")]
+        url: String,
+
+        #[arg(long, help = "Parse each event's data as JSON and print it as one compact line")]
+        json: bool,
+
+        #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Stop after this many events")]
+        max_events: Option<usize>,
+
+        #[arg(long, help = "Give up after this many reconnects in a row without an event")]
+        max_reconnects: Option<u32>,
+    },
+
+    #[command(about = "Upload text fields and files as multipart/form-data")]
+    Upload {
+        #[arg(help = "URL to upload to")]
+        url: String,
+
+        #[arg(long = "field", value_name = "NAME=VALUE", value_parser = parse_key_value, help = "Text field to send (repeatable)")]
+        fields: Vec<(String, String)>,
+
+        #[arg(long = "file", value_name = "NAME=PATH", value_parser = parse_key_value, help = "File to attach under NAME (repeatable)")]
+        files: Vec<(String, String)>,
+
+        #[arg(short, long, help = "Output format")]
+        format: Option<OutputFormat>,
+    },
+
+    #[command(about = "Send an HTTP request with any method")]
+    Request {
+        #[arg(value_enum, help = "HTTP method")]
+        method: HttpMethod,
+
+        #[arg(help = "URL to send the request to")]
+        url: String,
+
+        #[arg(short, long, help = "JSON request body")]
+        data: Option<String>,
+
+        #[arg(long, help = "Retry non-idempotent methods (POST, PUT, PATCH) on failure")]
+        retry: bool,
+
+        #[arg(short, long = "query", value_name = "KEY=VALUE", value_parser = parse_key_value, help = "Query parameter to append (repeatable)")]
+        query: Vec<(String, String)>,
+
+        #[arg(short, long, help = "Output format")]
+        format: Option<OutputFormat>,
+
+        #[arg(short, long, help = "Save response to file")]
+        output: Option<PathBuf>,
+    },
+    
+    #[command(about = "POST JSON to a remote API")]
+    Post {
+        #[arg(help = "URL to post to")]
+        url: String,
+
+        #[arg(help = "JSON request body")]
+        body: String,
+
+        #[arg(short, long, value_name = "KEY", help = "Store the response under this key")]
+        store: Option<String>,
+
+        #[arg(short, long, help = "Output format")]
+        format: Option<OutputFormat>,
     },
     
     #[command(about = "Store data locally")]
     Store {
-        #[arg(help = "Key to store data under")]
+        #[arg(help = "Key to store data under (recorded in the file with --path)")]
         key: String,
         
-        #[arg(help = "Value to store (JSON string or file path)")]
-        value: String,
+        #[arg(required_unless_present_any = ["from_url", "edit"], help = "Value to store (JSON string or file path)")]
+        value: Option<String>,
         
         #[arg(short, long, help = "Treat value as file path")]
         file: bool,
+
+        #[arg(long, requires = "file", help = "Store the file's raw bytes base64-encoded")]
+        binary: bool,
+
+        #[arg(long, value_name = "URL", conflicts_with_all = ["value", "file"], help = "Fetch JSON from a URL and store the response")]
+        from_url: Option<String>,
+
+        #[arg(long, requires = "from_url", help = "Do not record source_url, fetched_at, status and etag metadata for --from-url")]
+        no_provenance: bool,
+
+        #[arg(long, value_enum, conflicts_with = "binary", help = "Format of the value or file [default: from the file extension, else json]")]
+        input_format: Option<InputFormat>,
+
+        #[arg(long, value_name = "MB", value_parser = clap::value_parser!(u64).range(1..), help = "Override storage.max_file_size_mb for this write")]
+        max_size_mb: Option<u64>,
+
+        #[arg(long, value_name = "NAME", conflicts_with = "binary", help = "Validate the value against a schema registered with `schema add`")]
+        schema_name: Option<String>,
+
+        #[arg(long, conflicts_with = "binary", help = "Store the value in canonical form (keys sorted at every level)")]
+        canonical: bool,
+
+        #[arg(long, conflicts_with = "binary", help = "Nest an object with dotted keys (a.b.0) before storing; the inverse of --flatten")]
+        unflatten: bool,
+
+        #[arg(long, value_name = "RFC3339", value_parser = parse_rfc3339, help = "Treat the item as expired from this instant")]
+        expire_at: Option<DateTime<Utc>>,
+
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["binary", "from_url", "expire_at"], help = "Write the item to this file instead of the data directory")]
+        path: Option<PathBuf>,
+
+        #[arg(long, conflicts_with_all = ["value", "file", "from_url", "input_format"], help = "Write the value in $EDITOR, starting from the stored value or {}")]
+        edit: bool,
     },
     
     #[command(about = "Retrieve stored data")]
     Get {
-        #[arg(help = "Key to retrieve")]
-        key: String,
+        #[arg(required_unless_present = "path", help = "Key to retrieve")]
+        key: Option<String>,
         
         #[arg(short, long, help = "Output format")]
         format: Option<OutputFormat>,
+
+        #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Truncate nesting deeper than this many levels")]
+        max_depth: Option<usize>,
+
+        #[arg(long, help = "Copy the output to the clipboard instead of printing it")]
+        clipboard: bool,
+
+        #[arg(long, conflicts_with_all = ["format", "max_depth", "clipboard"], help = "Write the raw bytes of a binary value to stdout")]
+        decode: bool,
+
+        #[arg(long, conflicts_with = "decode", help = "Only output the sub-value at this JSON Pointer (e.g. /items/0/name)")]
+        pointer: Option<String>,
+
+        #[arg(long, conflicts_with_all = ["format", "decode"], help = "Render the value through a Handlebars template file")]
+        template: Option<PathBuf>,
+
+        #[arg(long, help = "Sort object keys alphabetically at every level")]
+        sort_keys: bool,
+
+        #[arg(long, help = "Flatten nested objects and arrays into one object with dotted keys (a.b.0)")]
+        flatten: bool,
+
+        #[arg(long, value_name = "FILE", conflicts_with = "key", help = "Read the item from this file (written by store --path) instead of by key")]
+        path: Option<PathBuf>,
+    },
+
+    #[command(about = "Write a sub-value into a stored item at a JSON Pointer")]
+    Set {
+        #[arg(help = "Key of the stored item")]
+        key: String,
+
+        #[arg(help = "JSON Pointer to write (e.g. /items/0/name)")]
+        pointer: String,
+
+        #[arg(help = "JSON value to write")]
+        value: String,
+
+        #[arg(long, help = "Create the item if the key does not exist")]
+        create: bool,
+    },
+    
+    #[command(about = "Show the full stored item including id, timestamps and metadata")]
+    Inspect {
+        #[arg(help = "Key to inspect")]
+        key: String,
+
+        #[arg(short, long, help = "Output format")]
+        format: Option<OutputFormat>,
     },
     
     #[command(about = "List all stored keys")]
     List {
         #[arg(short, long, help = "Show detailed information")]
         detailed: bool,
+
+        #[arg(short, long, help = "Output format (plain text when omitted)")]
+        format: Option<OutputFormat>,
+
+        #[arg(long, value_delimiter = ',', help = "Columns to show as a table: key, id, size, created, updated, or a metadata tag")]
+        columns: Vec<String>,
+
+        #[arg(long, value_parser = parse_time_bound, help = "Only items updated at or after this time (RFC 3339 or a duration ago, e.g. 2h, 3d)")]
+        since: Option<DateTime<Utc>>,
+
+        #[arg(long, value_parser = parse_time_bound, help = "Only items updated at or before this time (RFC 3339 or a duration ago, e.g. 2h, 3d)")]
+        until: Option<DateTime<Utc>>,
+
+        #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "8", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Read item metadata with up to N concurrent reads (default 8)")]
+        parallel: Option<usize>,
+
+        #[arg(long, value_name = "KEY", conflicts_with_all = ["since", "until", "conditions"], help = "Start after this key (the `next` cursor of the previous page)")]
+        after: Option<String>,
+
+        #[arg(long, value_name = "N", conflicts_with_all = ["since", "until", "conditions"], value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Show at most N keys and a cursor for the next page")]
+        limit: Option<usize>,
+
+        #[arg(long = "where", value_name = "POINTER OP VALUE", value_parser = parse_condition, help = "Only items whose value matches, e.g. '/status==active' or '/age>30' (repeatable, all must match)")]
+        conditions: Vec<Condition>,
     },
     
+    #[command(about = "List the keys whose value has an indexed field equal to a value")]
+    Query {
+        #[arg(help = "Top-level field listed in storage.indexed_fields")]
+        field: String,
+
+        #[arg(help = "Value to match, read as JSON when it parses (e.g. 3, true), else as a string")]
+        value: String,
+
+        #[arg(short, long, help = "Output format (plain text when omitted)")]
+        format: Option<OutputFormat>,
+    },
+
     #[command(about = "Delete stored data")]
     Delete {
         #[arg(help = "Key to delete")]
         key: String,
+
+        #[arg(short, long, help = "Skip the confirmation prompt")]
+        yes: bool,
+    },
+
+    #[command(about = "Delete all keys matching a prefix or glob pattern")]
+    DeleteMany {
+        #[arg(help = "Prefix or glob pattern to match keys against")]
+        pattern: String,
+
+        #[arg(short, long, conflicts_with = "glob", required_unless_present = "glob", help = "Match keys starting with the pattern")]
+        prefix: bool,
+
+        #[arg(short, long, help = "Match keys against the pattern as a glob")]
+        glob: bool,
+
+        #[arg(short, long, help = "Skip the confirmation prompt")]
+        yes: bool,
+    },
+    
+    #[command(about = "Print a JSON Schema inferred from a stored item or a fetched URL")]
+    InferSchema {
+        #[arg(help = "Stored key, or an http(s):// URL to fetch")]
+        key_or_url: String,
+    },
+
+    #[command(about = "Show the canonical JSON size of a value and the bytes taken by each top-level field")]
+    Size {
+        #[arg(help = "Stored key, or the path of a JSON/YAML/TOML/XML file")]
+        key_or_file: String,
+
+        #[arg(short, long, help = "Output format (a table when omitted)")]
+        format: Option<OutputFormat>,
+    },
+
+    #[command(about = "Manage named JSON Schemas for store --schema-name")]
+    Schema {
+        #[command(subcommand)]
+        action: SchemaAction,
+    },
+
+    #[command(about = "Delete the oldest items until at most N remain")]
+    Prune {
+        #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Number of items to keep [default: storage.max_items]")]
+        max_items: Option<usize>,
+
+        #[arg(short, long, help = "Skip the confirmation prompt")]
+        yes: bool,
+    },
+
+    #[command(about = "Mark a stored item as updated without changing its value")]
+    Touch {
+        #[arg(help = "Key to touch")]
+        key: String,
+    },
+
+    #[command(about = "Read or change the metadata of a stored item")]
+    Meta {
+        #[arg(help = "Key of the stored item")]
+        key: String,
+
+        #[command(subcommand)]
+        action: MetaAction,
+    },
+
+    #[command(about = "Find keys that store identical values")]
+    Dedupe {
+        #[arg(long, help = "Delete all but the oldest key in each duplicate group")]
+        delete: bool,
+
+        #[arg(short, long, requires = "delete", help = "Skip the confirmation prompt")]
+        yes: bool,
+    },
+
+    #[command(about = "Send stored payloads to an endpoint")]
+    Replay {
+        #[arg(help = "Key of the stored payload (or key prefix with --prefix)")]
+        key: String,
+
+        #[arg(help = "URL to send the payload to")]
+        url: String,
+
+        #[arg(short, long, value_enum, default_value = "post", help = "HTTP method")]
+        method: HttpMethod,
+
+        #[arg(short, long, help = "Replay every key starting with KEY, in key order")]
+        prefix: bool,
+
+        #[arg(long, default_value_t = 200, help = "Milliseconds to wait between payloads")]
+        delay_ms: u64,
+    },
+
+    #[command(about = "Run the requests described in a JSON spec file")]
+    Run {
+        #[arg(help = "JSON array of {\"method\", \"url\", \"body\", \"store_key\"} objects")]
+        spec_file: PathBuf,
+
+        #[arg(long, default_value_t = 1, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Number of steps to run at once (1 runs them in order)")]
+        concurrency: usize,
+
+        #[arg(short, long, help = "Output format for the step report (one line per step when omitted)")]
+        format: Option<OutputFormat>,
+    },
+
+    #[command(about = "Show storage audit records")]
+    Audit {
+        #[arg(help = "Only show records for this key")]
+        key: Option<String>,
+    },
+
+    #[command(about = "List storage namespaces")]
+    Namespaces,
+
+    #[command(about = "Print the end of the log file")]
+    Logs {
+        #[arg(short = 'n', long, default_value_t = 10, help = "Number of lines to print")]
+        lines: usize,
+
+        #[arg(short, long, help = "Keep printing lines as they are appended")]
+        follow: bool,
+    },
+
+    #[command(about = "Import items from an NDJSON archive of {\"key\", \"value\"} lines")]
+    Import {
+        #[arg(help = "Archive file to import")]
+        file: PathBuf,
+
+        #[arg(long, help = "Replace keys that already exist instead of skipping them")]
+        overwrite: bool,
+
+        #[arg(long, help = "Continue an interrupted import from its manifest")]
+        resume: bool,
+
+        #[arg(long, default_value_t = 8, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Number of items to write concurrently")]
+        concurrency: usize,
+    },
+
+    #[command(about = "Upgrade stored items to the current file format")]
+    Migrate,
+
+    #[command(about = "Check that the configured server is reachable")]
+    Ping {
+        #[arg(help = "Health check path relative to server.base_url")]
+        path: Option<String>,
+    },
+
+    #[command(about = "Send a fixed number of GET requests and report latency percentiles and throughput")]
+    Bench {
+        #[arg(help = "URL to request (relative URLs use server.base_url)")]
+        url: String,
+
+        #[arg(short = 'n', long, default_value_t = 100, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Total number of requests")]
+        requests: usize,
+
+        #[arg(long, default_value_t = 10, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Requests in flight at once")]
+        concurrency: usize,
+
+        #[arg(short, long, help = "Output format (summary text when omitted)")]
+        format: Option<OutputFormat>,
+    },
+
+    #[command(about = "Check configuration, storage, logging and server reachability")]
+    Doctor {
+        #[arg(long, help = "Skip the request to server.base_url")]
+        no_ping: bool,
+
+        #[arg(short, long, help = "Output format (one line per check when omitted)")]
+        format: Option<OutputFormat>,
+    },
+
+    #[command(about = "Show the current version and optionally check for updates")]
+    Version {
+        #[arg(long, help = "Check the release endpoint for a newer version")]
+        check: bool,
     },
     
+    #[command(about = "Inspect or clear cached fetch responses")]
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    #[command(about = "Print or install shell completions")]
+    Completions {
+        #[arg(long, value_enum, help = "Shell to generate completions for (default: detected from $SHELL)")]
+        shell: Option<clap_complete::Shell>,
+
+        #[arg(long, help = "Write the script to the shell's completion directory instead of printing it")]
+        install: bool,
+    },
+
     #[command(about = "Generate configuration file")]
     Config {
         #[command(subcommand)]
@@ -76,15 +564,189 @@ pub enum ConfigAction {
     Init {
         #[arg(short, long, help = "Output path for config file")]
         output: Option<PathBuf>,
+
+        #[arg(long, help = "Print what would be written, as a diff against an existing file, without writing")]
+        dry_run: bool,
     },
-    
+
+    #[command(about = "Set one value in the config file (the --config file, else the user config)")]
+    Set {
+        #[arg(help = "Dotted key, e.g. server.timeout_seconds")]
+        key: String,
+
+        #[arg(help = "Value, parsed as YAML (numbers, booleans and lists keep their type)")]
+        value: String,
+
+        #[arg(long, help = "Print the change without writing the file")]
+        dry_run: bool,
+    },
+
+    #[command(about = "Open the config file (the --config file, else the user config) in $VISUAL or $EDITOR")]
+    Edit {
+        #[arg(long, help = "Print the change without writing the file")]
+        dry_run: bool,
+    },
+
     #[command(about = "Show current configuration")]
-    Show,
+    Show {
+        #[arg(short, long, value_enum, default_value_t = ConfigFormat::Yaml, help = "Output format")]
+        format: ConfigFormat,
+
+        #[arg(long, help = "Mask secrets such as HMAC keys and tokens")]
+        redacted: bool,
+    },
+
+    #[command(about = "Show which configuration sources are loaded")]
+    Path,
+
+    #[command(about = "List the profiles defined in the configuration")]
+    Profiles,
+}
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+    #[command(about = "List cached URLs with their age, size and ETag")]
+    List,
+
+    #[command(about = "Remove one cached response, or all of them")]
+    Clear {
+        #[arg(help = "URL to remove (relative URLs use server.base_url)")]
+        url: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SchemaAction {
+    #[command(about = "Register a JSON Schema file under a name")]
+    Add {
+        #[arg(help = "Schema name")]
+        name: String,
+
+        #[arg(help = "JSON Schema file")]
+        file: PathBuf,
+
+        #[arg(long, help = "Replace a schema already registered under this name")]
+        force: bool,
+    },
+
+    #[command(about = "List registered schema names")]
+    List,
+
+    #[command(about = "Remove a registered schema")]
+    Remove {
+        #[arg(help = "Schema name")]
+        name: String,
+    },
 }
 
-#[derive(clap::ValueEnum, Clone, Debug)]
-pub enum OutputFormat {
+#[derive(Subcommand)]
+pub enum MetaAction {
+    #[command(about = "Set a metadata entry")]
+    Set {
+        #[arg(help = "Metadata name")]
+        name: String,
+
+        #[arg(help = "Metadata value")]
+        value: String,
+    },
+
+    #[command(about = "Print one metadata entry, or all of them as JSON")]
+    Get {
+        #[arg(help = "Metadata name")]
+        name: Option<String>,
+    },
+
+    #[command(about = "Delete a metadata entry")]
+    Delete {
+        #[arg(help = "Metadata name")]
+        name: String,
+    },
+
+    #[command(about = "List metadata names")]
+    List,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default)]
+pub enum InputFormat {
+    #[default]
     Json,
     Yaml,
-    Pretty,
-}
\ No newline at end of file
+    Toml,
+    Xml,
+}
+
+impl InputFormat {
+    /// Guesses the format from a file extension; unknown extensions give `None`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "json" => Some(InputFormat::Json),
+            "yaml" | "yml" => Some(InputFormat::Yaml),
+            "toml" => Some(InputFormat::Toml),
+            "xml" => Some(InputFormat::Xml),
+            _ => None,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum HttpMethod {
+    Get,
+    Head,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+impl From<HttpMethod> for reqwest::Method {
+    fn from(method: HttpMethod) -> Self {
+        match method {
+            HttpMethod::Get => reqwest::Method::GET,
+            HttpMethod::Head => reqwest::Method::HEAD,
+            HttpMethod::Post => reqwest::Method::POST,
+            HttpMethod::Put => reqwest::Method::PUT,
+            HttpMethod::Patch => reqwest::Method::PATCH,
+            HttpMethod::Delete => reqwest::Method::DELETE,
+        }
+    }
+}
+
+fn parse_url(s: &str) -> Result<String, String> {
+    reqwest::Url::parse(s)
+        .map(|_| s.to_string())
+        .map_err(|e| format!("invalid URL '{}': {}", s, e))
+}
+
+fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", s))
+}
+
+fn parse_condition(s: &str) -> Result<Condition, String> {
+    Condition::parse(s).map_err(|e| match e {
+        AppError::Validation { message } => message,
+        other => other.to_string(),
+    })
+}
+
+fn parse_rfc3339(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|e| format!("expected an RFC 3339 timestamp such as 2030-01-31T12:00:00Z, got '{}': {}", s, e))
+}
+
+/// Accepts an RFC 3339 timestamp or a duration such as `90m` or `3d`, which
+/// is taken as that long before now.
+fn parse_time_bound(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(s) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    let ago = humantime::parse_duration(s)
+        .map_err(|_| format!("expected an RFC 3339 timestamp or a duration like 2h, got '{}'", s))?;
+    chrono::Duration::from_std(ago)
+        .ok()
+        .and_then(|ago| Utc::now().checked_sub_signed(ago))
+        .ok_or_else(|| format!("duration '{}' is out of range", s))
+}
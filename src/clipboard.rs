@@ -0,0 +1,25 @@
+use crate::{AppError, Result};
+
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> Result<()> {
+    let mut clipboard = arboard::Clipboard::new().map_err(unavailable)?;
+    clipboard.set_text(text).map_err(unavailable)
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> Result<()> {
+    Err(AppError::OperationFailed {
+        reason: "clipboard support not compiled in; rebuild with --features clipboard".to_string(),
+    })
+}
+
+pub fn is_supported() -> bool {
+    cfg!(feature = "clipboard")
+}
+
+#[cfg(feature = "clipboard")]
+fn unavailable(e: arboard::Error) -> AppError {
+    AppError::OperationFailed {
+        reason: format!("clipboard unavailable: {}", e),
+    }
+}
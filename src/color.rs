@@ -0,0 +1,45 @@
+use std::io::IsTerminal;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Decides whether to emit ANSI colors. A non-empty `NO_COLOR` wins over
+    /// every choice, including `always`; `auto` colors only terminals.
+    pub fn resolve(self, no_color: bool, is_terminal: bool) -> bool {
+        if no_color {
+            return false;
+        }
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal,
+        }
+    }
+
+    pub fn for_stderr(self) -> bool {
+        self.resolve(no_color_env(), std::io::stderr().is_terminal())
+    }
+
+    pub fn for_stdout(self) -> bool {
+        self.resolve(no_color_env(), std::io::stdout().is_terminal())
+    }
+}
+
+/// Whether `NO_COLOR` is set to a non-empty value (see https://no-color.org).
+pub fn no_color_env() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+pub fn red(text: &str, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[31m{}\x1b[0m", text)
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,62 @@
+use crate::cli::Cli;
+use crate::{AppError, Result};
+use clap::CommandFactory;
+use clap_complete::Shell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BIN_NAME: &str = "rcli";
+
+/// `explicit` if given, otherwise the shell named by `$SHELL`.
+pub fn detect_shell(explicit: Option<Shell>) -> Result<Shell> {
+    explicit.or_else(Shell::from_env).ok_or_else(|| AppError::Validation {
+        message: "Could not detect the shell from $SHELL; pass --shell".to_string(),
+    })
+}
+
+pub fn generate(shell: Shell) -> Vec<u8> {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), BIN_NAME, &mut script);
+    script
+}
+
+/// Where each shell picks up per-user completions without extra setup,
+/// except zsh, whose `~/.zfunc` has to be on `fpath`.
+pub fn install_path(shell: Shell, home: &Path) -> Result<PathBuf> {
+    let xdg = |var: &str, fallback: &str| {
+        std::env::var_os(var)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(fallback))
+    };
+
+    match shell {
+        Shell::Bash => Ok(xdg("XDG_DATA_HOME", ".local/share").join("bash-completion/completions").join(BIN_NAME)),
+        Shell::Zsh => Ok(home.join(".zfunc").join(format!("_{}", BIN_NAME))),
+        Shell::Fish => Ok(xdg("XDG_CONFIG_HOME", ".config").join("fish/completions").join(format!("{}.fish", BIN_NAME))),
+        other => Err(AppError::Validation {
+            message: format!(
+                "No conventional completion directory for {}; redirect `rcli completions --shell {}` to a file instead",
+                other, other
+            ),
+        }),
+    }
+}
+
+/// Writes the completion script for `shell` under the home directory and
+/// returns its path.
+pub fn install(shell: Shell) -> Result<PathBuf> {
+    let home = dirs::home_dir().ok_or_else(|| AppError::OperationFailed {
+        reason: "could not determine the home directory".to_string(),
+    })?;
+    let path = install_path(shell, &home)?;
+
+    let unwritable = |e: std::io::Error| AppError::OperationFailed {
+        reason: format!("cannot write completions to {}: {}", path.display(), e),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(unwritable)?;
+    }
+    fs::write(&path, generate(shell)).map_err(unwritable)?;
+    Ok(path)
+}
@@ -1,31 +1,380 @@
-use crate::Result;
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use crate::output::OutputFormat;
+use crate::storage::write_atomic;
+use crate::{AppError, Result};
+use chrono::format::{Item, StrftimeItems};
+use config::builder::{ConfigBuilder, DefaultState};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// File names looked for in the user config directory and the current
+/// directory, in order; the first one found in each directory is loaded.
+/// `.rclirc` has no extension and is read as YAML.
+pub const CONFIG_FILE_NAMES: &[&str] = &[
+    "config.yaml",
+    "config.yml",
+    "config.json",
+    "config.toml",
+    "rcli.yaml",
+    "rcli.yml",
+    "rcli.toml",
+    "rcli.json",
+    ".rclirc",
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub logging: LoggingConfig,
     pub storage: StorageConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default)]
+    pub update: UpdateConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, serde_json::Value>,
+    /// Fields that did not parse and were replaced by their defaults.
+    #[serde(skip)]
+    pub fallbacks: Vec<ConfigFallback>,
+}
+
+/// A config field that kept its default because the configured value was
+/// invalid. `field` is a dotted path such as `server.timeout_seconds`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFallback {
+    pub field: String,
+    pub reason: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub base_url: String,
     pub timeout_seconds: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_seconds: Option<u64>,
     pub retry_attempts: u32,
+    #[serde(default = "default_retry_on_status")]
+    pub retry_on_status: Vec<u16>,
+    #[serde(default)]
+    pub max_requests_per_second: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hmac: Option<HmacConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>,
+    #[serde(default = "default_accept_compression")]
+    pub accept_compression: bool,
+    #[serde(default = "default_circuit_breaker_threshold")]
+    pub circuit_breaker_threshold: u32,
+    #[serde(default = "default_circuit_breaker_cooldown_seconds")]
+    pub circuit_breaker_cooldown_seconds: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub har_output: Option<PathBuf>,
+    #[serde(default = "default_redact_headers")]
+    pub redact_headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolve: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HmacConfig {
+    pub secret: String,
+    #[serde(default = "default_signature_header")]
+    pub header: String,
+    #[serde(default = "default_timestamp_header")]
+    pub timestamp_header: String,
+    #[serde(default)]
+    pub algorithm: HmacAlgorithm,
+}
+
+impl std::fmt::Debug for HmacConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HmacConfig")
+            .field("secret", &"<redacted>")
+            .field("header", &self.header)
+            .field("timestamp_header", &self.timestamp_header)
+            .field("algorithm", &self.algorithm)
+            .finish()
+    }
+}
+
+/// Request authentication, selected by `mode`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum AuthConfig {
+    Sigv4(SigV4Config),
+    Bearer(BearerConfig),
+}
+
+/// A token sent as `Authorization: Bearer <token>`: inline, read from
+/// `token_file`, or printed by `token_command`. Exactly one must be set.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct BearerConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_command: Option<String>,
+}
+
+impl std::fmt::Debug for BearerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BearerConfig")
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .field("token_file", &self.token_file)
+            .field("token_command", &self.token_command)
+            .finish()
+    }
+}
+
+/// AWS Signature Version 4 credentials. Unset fields fall back to the
+/// standard `AWS_*` environment variables when the signer is built.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SigV4Config {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_key_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_access_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default = "default_sigv4_service")]
+    pub service: String,
+}
+
+impl std::fmt::Debug for SigV4Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redacted = |value: &Option<String>| value.as_ref().map(|_| "<redacted>");
+        f.debug_struct("SigV4Config")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &redacted(&self.secret_access_key))
+            .field("session_token", &redacted(&self.session_token))
+            .field("region", &self.region)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+fn default_sigv4_service() -> String {
+    "s3".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HmacAlgorithm {
+    #[default]
+    HmacSha256,
+}
+
+fn default_signature_header() -> String {
+    "X-Signature".to_string()
+}
+
+fn default_timestamp_header() -> String {
+    "X-Timestamp".to_string()
+}
+
+fn default_accept_compression() -> bool {
+    true
+}
+
+fn default_follow_symlinks() -> bool {
+    true
+}
+
+fn default_schema_dir() -> PathBuf {
+    PathBuf::from("./schemas")
+}
+
+fn default_circuit_breaker_threshold() -> u32 {
+    5
+}
+
+fn default_circuit_breaker_cooldown_seconds() -> u64 {
+    30
+}
+
+fn default_retry_on_status() -> Vec<u16> {
+    crate::http::DEFAULT_RETRY_ON_STATUS.to_vec()
+}
+
+fn default_redact_headers() -> Vec<String> {
+    ["authorization", "proxy-authorization", "cookie", "set-cookie", "x-api-key", "x-amz-security-token"]
+        .into_iter()
+        .map(String::from)
+        .collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoggingConfig {
     pub level: String,
     pub file_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otlp_endpoint: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
     pub data_dir: PathBuf,
     pub max_file_size_mb: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_log: Option<PathBuf>,
+    #[serde(default = "default_follow_symlinks")]
+    pub follow_symlinks: bool,
+    #[serde(default = "default_schema_dir")]
+    pub schema_dir: PathBuf,
+    #[serde(default)]
+    pub indexed_fields: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_pattern: Option<String>,
+    #[serde(default)]
+    pub normalize_keys: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    /// Format of commands whose `--format` defaults to pretty JSON, when the
+    /// flag is not given.
+    pub default_format: OutputFormat,
+    pub indent: Indent,
+    pub time_format: String,
+    pub timezone: DisplayTimezone,
+    pub sort_keys: bool,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            default_format: OutputFormat::default(),
+            indent: Indent::default(),
+            time_format: "%Y-%m-%d %H:%M:%S %Z".to_string(),
+            timezone: DisplayTimezone::default(),
+            sort_keys: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayTimezone {
+    #[default]
+    Utc,
+    Local,
+}
+
+/// Indentation used by the pretty formatter: a number of spaces, or `"tab"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tab,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+impl Indent {
+    pub fn as_string(&self) -> String {
+        match self {
+            Indent::Spaces(count) => " ".repeat(*count),
+            Indent::Tab => "\t".to_string(),
+        }
+    }
+}
+
+impl Serialize for Indent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Indent::Spaces(count) => serializer.serialize_u64(*count as u64),
+            Indent::Tab => serializer.serialize_str("tab"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Indent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Spaces(u64),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Spaces(count) => Ok(Indent::Spaces(count as usize)),
+            Raw::Text(text) if text.eq_ignore_ascii_case("tab") => Ok(Indent::Tab),
+            Raw::Text(text) => text.trim().parse().map(Indent::Spaces).map_err(|_| {
+                serde::de::Error::custom(format!(
+                    "invalid indent '{}': expected a number of spaces or \"tab\"",
+                    text
+                ))
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    pub check_url: String,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            check_url: "https://api.github.com/repos/xXT10Xx/test012/releases/latest".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub dir: PathBuf,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            dir: dirs::cache_dir()
+                .map(|dir| dir.join("rcli"))
+                .unwrap_or_else(|| PathBuf::from("./.rcli-cache")),
+        }
+    }
+}
+
+/// The formats `AppConfig::render` can write.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigSource {
+    pub name: &'static str,
+    pub location: String,
+    pub path: Option<PathBuf>,
+}
+
+impl ConfigSource {
+    pub fn is_loaded(&self) -> bool {
+        self.path.is_some()
+    }
 }
 
 impl Default for AppConfig {
@@ -34,25 +383,53 @@ impl Default for AppConfig {
             server: ServerConfig {
                 base_url: "https://api.example.com".to_string(),
                 timeout_seconds: 30,
+                connect_timeout_seconds: None,
                 retry_attempts: 3,
+                retry_on_status: default_retry_on_status(),
+                max_requests_per_second: 0,
+                user_agent: None,
+                hmac: None,
+                auth: None,
+                accept_compression: default_accept_compression(),
+                circuit_breaker_threshold: default_circuit_breaker_threshold(),
+                circuit_breaker_cooldown_seconds: default_circuit_breaker_cooldown_seconds(),
+                har_output: None,
+                redact_headers: default_redact_headers(),
+                resolve: Vec::new(),
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
                 file_path: None,
+                otlp_endpoint: None,
             },
             storage: StorageConfig {
                 data_dir: PathBuf::from("./data"),
                 max_file_size_mb: 100,
+                max_items: None,
+                audit_log: None,
+                follow_symlinks: default_follow_symlinks(),
+                schema_dir: default_schema_dir(),
+                indexed_fields: Vec::new(),
+                key_pattern: None,
+                normalize_keys: false,
             },
+            output: OutputConfig::default(),
+            update: UpdateConfig::default(),
+            cache: CacheConfig::default(),
+            default_profile: None,
+            profiles: HashMap::new(),
+            fallbacks: Vec::new(),
         }
     }
 }
 
 impl AppConfig {
     pub fn load() -> Result<Self> {
+        Self::load_layered(None, None)
+    }
+
+    pub fn load_layered(config_dir: Option<&Path>, profile: Option<&str>) -> Result<Self> {
         let mut settings = config::Config::builder()
-            .add_source(config::File::with_name("config").required(false))
-            .add_source(config::Environment::with_prefix("RCLI"))
             .set_default("server.base_url", "https://api.example.com")?
             .set_default("server.timeout_seconds", 30)?
             .set_default("server.retry_attempts", 3)?
@@ -60,19 +437,150 @@ impl AppConfig {
             .set_default("storage.data_dir", "./data")?
             .set_default("storage.max_file_size_mb", 100)?;
 
-        if let Some(config_dir) = dirs::config_dir() {
-            let app_config = config_dir.join("rcli").join("config");
-            settings = settings.add_source(config::File::from(app_config).required(false));
+        for source in Self::sources(config_dir) {
+            if let Some(path) = source.path {
+                let file = match path.extension() {
+                    Some(_) => config::File::from(path),
+                    None => config::File::from(path).format(config::FileFormat::Yaml),
+                };
+                settings = settings.add_source(file);
+            }
+        }
+
+        let env = config::Environment::with_prefix("RCLI").prefix_separator("_").separator("__");
+        build_with_profile(settings, Some(env), profile)
+    }
+
+    /// Config files considered by `load_layered`, lowest precedence first.
+    /// Built-in defaults sit below these and `RCLI_*` environment variables
+    /// above them.
+    pub fn sources(config_dir: Option<&Path>) -> Vec<ConfigSource> {
+        let user_dir = config_dir
+            .map(Path::to_path_buf)
+            .or_else(|| dirs::config_dir().map(|dir| dir.join("rcli")));
+
+        let mut sources = Vec::new();
+        if let Some(dir) = user_dir {
+            sources.push(ConfigSource {
+                name: "user config",
+                location: search_location(&dir),
+                path: find_config_file(&dir),
+            });
         }
 
-        let config = settings.build()?.try_deserialize()?;
-        Ok(config)
+        sources.push(ConfigSource {
+            name: "project config",
+            location: search_location(Path::new(".")),
+            path: find_config_file(Path::new("")),
+        });
+
+        sources
     }
 
-    pub fn load_from_file(path: &PathBuf) -> Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config: AppConfig = serde_yaml::from_str(&content)?;
-        Ok(config)
+    pub fn load_from_file(path: &Path, profile: Option<&str>) -> Result<Self> {
+        let settings = config::Config::builder()
+            .add_source(config::File::from(path).format(config::FileFormat::Yaml));
+        build_with_profile(settings, None, profile)
+    }
+
+    /// Parses config text, such as piped in with `--config -`. Text starting
+    /// with `{` is read as JSON, anything else as YAML.
+    pub fn load_from_str(text: &str, profile: Option<&str>) -> Result<Self> {
+        let trimmed = text.trim_start();
+        if trimmed.is_empty() {
+            return Err(AppError::Validation {
+                message: "Configuration from stdin is empty".to_string(),
+            });
+        }
+        let format = if trimmed.starts_with('{') {
+            config::FileFormat::Json
+        } else {
+            config::FileFormat::Yaml
+        };
+        let settings = config::Config::builder().add_source(config::File::from_str(text, format));
+        build_with_profile(settings, None, profile)
+    }
+
+    fn with_expanded_paths(mut self) -> Self {
+        self.storage.data_dir = expand_path(&self.storage.data_dir);
+        self.storage.audit_log = self.storage.audit_log.as_deref().map(expand_path);
+        self.storage.schema_dir = expand_path(&self.storage.schema_dir);
+        self.server.har_output = self.server.har_output.as_deref().map(expand_path);
+        if let Some(AuthConfig::Bearer(bearer)) = &mut self.server.auth {
+            bearer.token_file = bearer.token_file.as_deref().map(expand_path);
+        }
+        self.cache.dir = expand_path(&self.cache.dir);
+        self.logging.file_path = self.logging.file_path.as_deref().map(expand_path);
+        self
+    }
+
+    /// Checks values that deserialize fine but cannot work, reporting every
+    /// problem at once.
+    pub fn validate(&self) -> Result<()> {
+        let mut issues = Vec::new();
+
+        match reqwest::Url::parse(&self.server.base_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            Ok(url) => issues.push(format!("server.base_url: unsupported scheme '{}'", url.scheme())),
+            Err(e) => issues.push(format!("server.base_url: invalid URL '{}': {}", self.server.base_url, e)),
+        }
+        if self.server.timeout_seconds == 0 {
+            issues.push("server.timeout_seconds: must be greater than 0".to_string());
+        }
+        if self.server.connect_timeout_seconds == Some(0) {
+            issues.push("server.connect_timeout_seconds: must be greater than 0".to_string());
+        }
+        for status in &self.server.retry_on_status {
+            if !(100..=599).contains(status) {
+                issues.push(format!("server.retry_on_status: {} is not an HTTP status code", status));
+            }
+        }
+        if self.server.hmac.is_some() && matches!(self.server.auth, Some(AuthConfig::Sigv4(_))) {
+            issues.push("server.auth: cannot be combined with server.hmac".to_string());
+        }
+        if let Some(AuthConfig::Bearer(bearer)) = &self.server.auth {
+            let sources = [bearer.token.is_some(), bearer.token_file.is_some(), bearer.token_command.is_some()];
+            if sources.into_iter().filter(|&set| set).count() != 1 {
+                issues.push("server.auth: bearer needs exactly one of token, token_file or token_command".to_string());
+            }
+        }
+        for entry in &self.server.resolve {
+            if let Err(AppError::Validation { message }) = crate::http::parse_resolve(entry) {
+                issues.push(format!("server.resolve: {}", message));
+            }
+        }
+        if self.storage.max_file_size_mb == 0 {
+            issues.push("storage.max_file_size_mb: must be greater than 0".to_string());
+        }
+        if self.storage.max_items == Some(0) {
+            issues.push("storage.max_items: must be greater than 0".to_string());
+        }
+        if self.storage.indexed_fields.iter().any(|field| field.is_empty()) {
+            issues.push("storage.indexed_fields: field names must not be empty".to_string());
+        }
+        if let Some(pattern) = &self.storage.key_pattern {
+            if let Err(e) = regex::Regex::new(pattern) {
+                issues.push(format!("storage.key_pattern: invalid regex '{}': {}", pattern, e));
+            }
+        }
+        if StrftimeItems::new(&self.output.time_format).any(|item| item == Item::Error) {
+            issues.push(format!("output.time_format: invalid format '{}'", self.output.time_format));
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::InvalidFields {
+                context: "Invalid configuration".to_string(),
+                issues,
+            })
+        }
+    }
+
+    pub fn profile_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+        names.sort();
+        names
     }
 
     pub fn save_to_file(&self, path: &PathBuf) -> Result<()> {
@@ -80,4 +588,307 @@ impl AppConfig {
         std::fs::write(path, yaml)?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// The file `config set` and `config edit` change: the `--config` file, else the user
+    /// config file, which defaults to `config.yaml` in the user config
+    /// directory when none exists yet.
+    pub fn edit_target(config_file: Option<&Path>, config_dir: Option<&Path>) -> Result<PathBuf> {
+        if config_file == Some(Path::new("-")) {
+            return Err(AppError::Validation {
+                message: "Cannot edit a configuration read from stdin".to_string(),
+            });
+        }
+        if let Some(path) = config_file {
+            return Ok(path.to_path_buf());
+        }
+        let user_dir = config_dir
+            .map(Path::to_path_buf)
+            .or_else(|| dirs::config_dir().map(|dir| dir.join("rcli")))
+            .ok_or_else(|| AppError::Validation {
+                message: "No user config directory; pass --config".to_string(),
+            })?;
+        Ok(find_config_file(&user_dir).unwrap_or_else(|| user_dir.join("config.yaml")))
+    }
+
+    /// Raw contents of a YAML or JSON config file, `None` when it does not
+    /// exist.
+    pub fn read_file_value(path: &Path) -> Result<Option<Value>> {
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            return Err(AppError::Validation {
+                message: format!("Only YAML and JSON config files can be edited: {}", path.display()),
+            });
+        }
+        match std::fs::read_to_string(path) {
+            Ok(text) if text.trim().is_empty() => Ok(Some(Value::Object(Default::default()))),
+            Ok(text) => Ok(Some(serde_yaml::from_str(&text)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Checks that a possibly partial config file, layered over the
+    /// defaults, still gives a valid configuration and has no unknown keys.
+    pub fn check_file_value(value: &Value) -> Result<()> {
+        let mut merged = serde_json::to_value(Self::default())?;
+        merge_json(&mut merged, value);
+        let config: Self = serde_json::from_value(merged).map_err(|e| AppError::Validation {
+            message: format!("Invalid configuration: {}", e),
+        })?;
+        // Deserializing drops unknown fields, so a misspelt key would
+        // otherwise be written and silently ignored.
+        if let Some(key) = unknown_key(value, &serde_json::to_value(&config)?, "") {
+            return Err(AppError::Validation {
+                message: format!("Unknown config key '{}'", key),
+            });
+        }
+        config.validate()
+    }
+
+    /// Writes a config file value as JSON or YAML, by the file's extension.
+    pub fn write_file_value(path: &Path, value: &Value) -> Result<()> {
+        let text = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::to_string_pretty(value)?
+        } else {
+            serde_yaml::to_string(value)?
+        };
+        Self::write_file_text(path, &text)
+    }
+
+    /// Replaces a config file atomically, creating its directory if needed.
+    pub fn write_file_text(path: &Path, text: &str) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, text.as_bytes())
+    }
+
+    /// Serializes the effective configuration. With `redacted`, string values
+    /// of secret-bearing fields are masked at any depth, profiles included.
+    pub fn render(&self, format: ConfigFormat, redacted: bool) -> Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        if redacted {
+            redact_secrets(&mut value);
+        }
+
+        match format {
+            ConfigFormat::Json => Ok(serde_json::to_string_pretty(&value)?),
+            ConfigFormat::Yaml => Ok(serde_yaml::to_string(&value)?),
+            ConfigFormat::Toml => {
+                // TOML has no null; unset options are left out instead.
+                strip_nulls(&mut value);
+                toml::to_string_pretty(&value).map_err(|e| AppError::Validation {
+                    message: format!("Cannot render configuration as TOML: {}", e),
+                })
+            }
+        }
+    }
+}
+
+const SECRET_FIELDS: &[&str] = &["secret", "token", "password", "api_key", "secret_access_key", "session_token"];
+
+fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if field.is_string() && SECRET_FIELDS.contains(&key.to_ascii_lowercase().as_str()) {
+                    *field = Value::String("<redacted>".to_string());
+                } else {
+                    redact_secrets(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+/// The first dotted key of `value` that `known`, the same file after a
+/// round trip through `AppConfig`, lost. Null values are not checked.
+fn unknown_key(value: &Value, known: &Value, prefix: &str) -> Option<String> {
+    let Value::Object(map) = value else {
+        return None;
+    };
+    for (name, child) in map {
+        let key = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        match known.get(name) {
+            Some(known_child) => {
+                if let Some(unknown) = unknown_key(child, known_child, &key) {
+                    return Some(unknown);
+                }
+            }
+            None if child.is_null() => {}
+            None => return Some(key),
+        }
+    }
+    None
+}
+
+fn merge_json(base: &mut Value, overlay: &Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, field| !field.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+/// Layers the selected profile (explicit, else `default_profile`) above the
+/// config files but below environment variables, so `RCLI_*` still wins.
+fn build_with_profile(
+    settings: ConfigBuilder<DefaultState>,
+    env: Option<config::Environment>,
+    profile: Option<&str>,
+) -> Result<AppConfig> {
+    let mut base = settings.clone();
+    if let Some(env) = &env {
+        base = base.add_source(env.clone());
+    }
+    let base = base.build()?;
+
+    let Some(name) = profile
+        .map(str::to_string)
+        .or_else(|| base.get_string("default_profile").ok())
+    else {
+        return Ok(deserialize_lenient(base)?.with_expanded_paths());
+    };
+
+    let overrides = base
+        .get_table(&format!("profiles.{}", name))
+        .map_err(|_| AppError::Validation {
+            message: format!("Unknown profile '{}'", name),
+        })?;
+
+    let mut profile_layer = config::Config::builder();
+    for (key, value) in overrides {
+        profile_layer = profile_layer.set_override(key, value)?;
+    }
+
+    let mut layered = settings.add_source(profile_layer.build()?);
+    if let Some(env) = env {
+        layered = layered.add_source(env);
+    }
+    Ok(deserialize_lenient(layered.build()?)?.with_expanded_paths())
+}
+
+/// Deserializes the configuration, falling back to the default for each
+/// field that does not parse instead of failing as a whole. Fields are
+/// applied one at a time over the defaults, so every rejected field is
+/// reported in `fallbacks` and missing fields simply keep their defaults.
+fn deserialize_lenient(settings: config::Config) -> Result<AppConfig> {
+    if let Ok(config) = settings.clone().try_deserialize::<AppConfig>() {
+        return Ok(config);
+    }
+
+    let defaults = config::Config::try_from(&AppConfig::default())?;
+    let mut accepted = config::Config::builder().add_source(defaults);
+    let mut fallbacks = Vec::new();
+    let mut sections: Vec<(String, config::Value)> =
+        settings.try_deserialize::<HashMap<String, config::Value>>()?.into_iter().collect();
+    sections.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (section, value) in sections {
+        let mut entries: Vec<(String, config::Value)> = match value.clone().into_table() {
+            Ok(table) if section != "profiles" => table
+                .into_iter()
+                .map(|(field, value)| (format!("{}.{}", section, field), value))
+                .collect(),
+            _ => vec![(section, value)],
+        };
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        for (field, value) in entries {
+            let candidate = accepted.clone().set_override(field.as_str(), value)?;
+            match candidate.build_cloned()?.try_deserialize::<AppConfig>() {
+                Ok(_) => accepted = candidate,
+                Err(e) => fallbacks.push(ConfigFallback { field, reason: e.to_string() }),
+            }
+        }
+    }
+
+    let mut config: AppConfig = accepted.build()?.try_deserialize()?;
+    config.fallbacks = fallbacks;
+    Ok(config)
+}
+
+/// Loads `KEY=value` lines from `path`, or from `./.env` when no path is
+/// given and that file exists, into the process environment. Variables that
+/// are already set keep their values. Returns the file that was loaded.
+pub fn load_env_file(path: Option<&Path>) -> Result<Option<PathBuf>> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None if Path::new(".env").is_file() => PathBuf::from(".env"),
+        None => return Ok(None),
+    };
+
+    dotenvy::from_path(&path).map_err(|e| AppError::Validation {
+        message: format!("Failed to load env file {}: {}", path.display(), e),
+    })?;
+    Ok(Some(path))
+}
+
+/// Expands a leading `~` to the home directory and `$VAR` / `${VAR}` to
+/// environment values. Unset variables are left as written.
+pub fn expand_path(path: &Path) -> PathBuf {
+    let raw = path.to_string_lossy();
+
+    let home_relative = match raw.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => dirs::home_dir()
+            .map(|home| format!("{}{}", home.display(), rest)),
+        _ => None,
+    };
+    let raw = home_relative.unwrap_or_else(|| raw.into_owned());
+
+    let mut expanded = String::with_capacity(raw.len());
+    let mut rest = raw.as_str();
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => expanded.push_str(&value),
+            _ => expanded.push_str(&rest[start..start + 1 + consumed]),
+        }
+        rest = &after[consumed..];
+    }
+    expanded.push_str(rest);
+
+    PathBuf::from(expanded)
+}
+
+/// The first of `CONFIG_FILE_NAMES` that exists in `dir`.
+fn find_config_file(dir: &Path) -> Option<PathBuf> {
+    CONFIG_FILE_NAMES.iter().map(|name| dir.join(name)).find(|path| path.is_file())
+}
+
+fn search_location(dir: &Path) -> String {
+    format!("{}/{{config.*, rcli.*, .rclirc}}", dir.display())
+}
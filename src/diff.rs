@@ -0,0 +1,50 @@
+use serde_json::Value;
+use std::fmt;
+
+/// One difference between two JSON documents. Paths are dotted object keys;
+/// arrays are compared as a whole.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added { path: String, value: Value },
+    Removed { path: String, value: Value },
+    Changed { path: String, from: Value, to: Value },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {}: {}", path, value),
+            Change::Removed { path, value } => write!(f, "- {}: {}", path, value),
+            Change::Changed { path, from, to } => write!(f, "~ {}: {} -> {}", path, from, to),
+        }
+    }
+}
+
+/// Changes that turn `old` into `new`, in key order.
+pub fn json_diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_at("", old, new, &mut changes);
+    changes
+}
+
+fn diff_at(path: &str, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    let (Value::Object(old_map), Value::Object(new_map)) = (old, new) else {
+        if old != new {
+            changes.push(Change::Changed { path: path.to_string(), from: old.clone(), to: new.clone() });
+        }
+        return;
+    };
+
+    let mut keys: Vec<&String> = old_map.keys().chain(new_map.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+        match (old_map.get(key), new_map.get(key)) {
+            (Some(old), Some(new)) => diff_at(&child, old, new, changes),
+            (Some(old), None) => changes.push(Change::Removed { path: child, value: old.clone() }),
+            (None, Some(new)) => changes.push(Change::Added { path: child, value: new.clone() }),
+            (None, None) => unreachable!("key comes from one of the maps"),
+        }
+    }
+}
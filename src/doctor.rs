@@ -0,0 +1,174 @@
+use crate::config::{AppConfig, StorageConfig};
+use crate::http::HttpClient;
+use crate::AppError;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+
+const MB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self { name, status, detail: detail.into() }
+    }
+}
+
+/// Where the configuration came from. `file` is the `--config` argument.
+pub fn config_source(file: Option<&Path>, no_config: bool, config_dir: Option<&Path>) -> Check {
+    const NAME: &str = "config";
+    match file {
+        _ if no_config => Check::new(NAME, Status::Pass, "built-in defaults (--no-config)"),
+        Some(path) if path == Path::new("-") => Check::new(NAME, Status::Pass, "loaded from stdin"),
+        Some(path) => Check::new(NAME, Status::Pass, format!("loaded from {}", path.display())),
+        None => {
+            let loaded: Vec<String> = AppConfig::sources(config_dir)
+                .iter()
+                .filter_map(|source| source.path.as_ref().map(|path| path.display().to_string()))
+                .collect();
+            if loaded.is_empty() {
+                Check::new(NAME, Status::Warn, "no config file found; using defaults and RCLI_* variables")
+            } else {
+                Check::new(NAME, Status::Pass, format!("loaded from {}", loaded.join(", ")))
+            }
+        }
+    }
+}
+
+pub fn config_values(config: &AppConfig) -> Check {
+    const NAME: &str = "config values";
+    if !config.fallbacks.is_empty() {
+        let fields: Vec<String> = config
+            .fallbacks
+            .iter()
+            .map(|fallback| format!("{}: {}", fallback.field, fallback.reason))
+            .collect();
+        return Check::new(NAME, Status::Fail, format!("fell back to defaults for {}", fields.join("; ")));
+    }
+    match config.validate() {
+        Ok(()) => Check::new(NAME, Status::Pass, "valid"),
+        Err(AppError::InvalidFields { issues, .. }) => Check::new(NAME, Status::Fail, issues.join("; ")),
+        Err(e) => Check::new(NAME, Status::Fail, e.to_string()),
+    }
+}
+
+/// A missing directory passes with a warning when it can be created.
+pub fn data_dir(dir: &Path) -> Check {
+    const NAME: &str = "data directory";
+    if dir.is_dir() {
+        return match probe_writable(dir) {
+            Ok(()) => Check::new(NAME, Status::Pass, format!("{} is writable", dir.display())),
+            Err(e) => Check::new(NAME, Status::Fail, format!("{} is not writable: {}", dir.display(), e)),
+        };
+    }
+    if dir.exists() {
+        return Check::new(NAME, Status::Fail, format!("{} is not a directory", dir.display()));
+    }
+    match existing_ancestor(dir).map(probe_writable) {
+        Some(Ok(())) => Check::new(NAME, Status::Warn, format!("{} does not exist yet and will be created", dir.display())),
+        Some(Err(e)) => Check::new(NAME, Status::Fail, format!("{} does not exist and cannot be created: {}", dir.display(), e)),
+        None => Check::new(NAME, Status::Fail, format!("{} does not exist", dir.display())),
+    }
+}
+
+/// Compares free space with what the storage limits allow the data
+/// directory to grow to: `max_file_size_mb` per item, times `max_items` when
+/// set.
+pub fn disk_space(storage: &StorageConfig) -> Check {
+    const NAME: &str = "disk space";
+    let Some(dir) = existing_ancestor(&storage.data_dir) else {
+        return Check::new(NAME, Status::Fail, format!("cannot inspect {}", storage.data_dir.display()));
+    };
+    let available = match fs4::available_space(dir) {
+        Ok(available) => available,
+        Err(e) => return Check::new(NAME, Status::Warn, format!("cannot read free space of {}: {}", dir.display(), e)),
+    };
+
+    let per_item = storage.max_file_size_mb.saturating_mul(MB);
+    let quota = per_item.saturating_mul(storage.max_items.unwrap_or(1) as u64);
+    let detail = format!("{} MB free, quota {} MB", available / MB, quota / MB);
+    if available < per_item {
+        Check::new(NAME, Status::Fail, format!("{}; not enough for one item at storage.max_file_size_mb", detail))
+    } else if available < quota {
+        Check::new(NAME, Status::Warn, format!("{}; storage.max_items items at the size limit would not fit", detail))
+    } else {
+        Check::new(NAME, Status::Pass, detail)
+    }
+}
+
+pub fn log_file(path: Option<&Path>) -> Check {
+    const NAME: &str = "log file";
+    let Some(path) = path else {
+        return Check::new(NAME, Status::Pass, "not configured (logging to stderr only)");
+    };
+    if path.exists() {
+        return match OpenOptions::new().append(true).open(path) {
+            Ok(_) => Check::new(NAME, Status::Pass, format!("{} is writable", path.display())),
+            Err(e) => Check::new(NAME, Status::Fail, format!("{} is not writable: {}", path.display(), e)),
+        };
+    }
+    match existing_ancestor(path).map(probe_writable) {
+        Some(Ok(())) => Check::new(NAME, Status::Pass, format!("{} will be created", path.display())),
+        Some(Err(e)) => Check::new(NAME, Status::Fail, format!("{} cannot be created: {}", path.display(), e)),
+        None => Check::new(NAME, Status::Fail, format!("{} cannot be created", path.display())),
+    }
+}
+
+/// A GET of `server.base_url`. Any HTTP response proves the server is
+/// reachable; a non-2xx one is only a warning, since the base URL itself is
+/// often not a routable resource.
+pub async fn server(client: &HttpClient) -> Check {
+    const NAME: &str = "server";
+    match client.fetch_with_meta("").await {
+        Ok(response) if response.status.is_success() => Check::new(
+            NAME,
+            Status::Pass,
+            format!("HTTP {} in {} ms", response.status, response.elapsed.as_millis()),
+        ),
+        Ok(response) => Check::new(
+            NAME,
+            Status::Warn,
+            format!("reachable, but answered HTTP {}", response.status),
+        ),
+        Err(e) => Check::new(NAME, Status::Fail, format!("unreachable: {}", e)),
+    }
+}
+
+/// The nearest part of `path` that exists. It may be a file, in which case
+/// nothing can be created below it.
+fn existing_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|ancestor| !ancestor.as_os_str().is_empty() && ancestor.exists()).or_else(|| {
+        // A relative path with no existing parent lives in the working directory.
+        path.is_relative().then_some(Path::new("."))
+    })
+}
+
+fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".rcli-doctor-{}", uuid::Uuid::new_v4()));
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)
+}
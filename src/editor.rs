@@ -0,0 +1,56 @@
+use crate::{AppError, Result};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// The editor to open: `$VISUAL`, else `$EDITOR`, else `vi`.
+pub fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Opens `editor` on a temporary file holding `initial`, named with
+/// `extension` so the editor picks the right syntax, and returns the file's
+/// contents once the editor exits. `editor` is run by the shell, so it may
+/// be quoted or carry arguments, as in `code --wait`. The file is readable
+/// only by the owner and is removed afterwards.
+pub fn edit_text(editor: &str, initial: &str, extension: &str) -> Result<String> {
+    if editor.trim().is_empty() {
+        return Err(AppError::Validation {
+            message: "The editor command is empty".to_string(),
+        });
+    }
+
+    // tempfile creates the file with mode 0600.
+    let file = tempfile::Builder::new()
+        .prefix("rcli-edit-")
+        .suffix(&format!(".{}", extension))
+        .tempfile()?;
+    fs::write(file.path(), initial)?;
+    let status = shell(editor, file.path()).status().map_err(|e| AppError::Validation {
+        message: format!("Could not start editor '{}': {}", editor, e),
+    })?;
+    if !status.success() {
+        return Err(AppError::OperationFailed {
+            reason: format!("Editor '{}' exited with {}; nothing was changed", editor, status),
+        });
+    }
+    Ok(fs::read_to_string(file.path())?)
+}
+
+#[cfg(windows)]
+fn shell(editor: &str, path: &Path) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(format!("{} \"{}\"", editor, path.display()));
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(editor: &str, path: &Path) -> Command {
+    let mut shell = Command::new("sh");
+    shell.args(["-c", &format!("{} \"$1\"", editor), "sh"]).arg(path);
+    shell
+}
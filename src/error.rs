@@ -10,6 +10,12 @@ pub enum AppError {
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
 
+    #[error("Request to {url} timed out after {after_seconds}s")]
+    Timeout { url: String, after_seconds: u64 },
+
+    #[error("Could not connect to {url}: {reason}")]
+    Connection { url: String, reason: String },
+
     #[error("JSON parsing error: {0}")]
     Json(#[from] serde_json::Error),
 
@@ -22,9 +28,50 @@ pub enum AppError {
     #[error("Validation error: {message}")]
     Validation { message: String },
 
+    #[error("Validation error: {context}:{}", bulleted(issues))]
+    InvalidFields { context: String, issues: Vec<String> },
+
+    #[error(
+        "Corrupt stored item {}: {reason}. Run `rcli migrate` to check every item, or delete the file",
+        path.display()
+    )]
+    CorruptItem { path: std::path::PathBuf, reason: String },
+
     #[error("Not found: {resource}")]
     NotFound { resource: String },
 
     #[error("Operation failed: {reason}")]
     OperationFailed { reason: String },
-}
\ No newline at end of file
+}
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Io(_) => "IO_ERROR",
+            AppError::Http(_) => "HTTP_ERROR",
+            AppError::Timeout { .. } => "TIMEOUT",
+            AppError::Connection { .. } => "CONNECTION_ERROR",
+            AppError::Json(_) => "JSON_ERROR",
+            AppError::Yaml(_) => "YAML_ERROR",
+            AppError::Config(_) => "CONFIG_ERROR",
+            AppError::Validation { .. } | AppError::InvalidFields { .. } => "VALIDATION_ERROR",
+            AppError::CorruptItem { .. } => "CORRUPT_ITEM",
+            AppError::NotFound { .. } => "NOT_FOUND",
+            AppError::OperationFailed { .. } => "OPERATION_FAILED",
+        }
+    }
+
+    /// Process exit status. Connection failures and timeouts get their own
+    /// codes so scripts can tell them apart; everything else exits with 1.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Connection { .. } => 3,
+            AppError::Timeout { .. } => 4,
+            _ => 1,
+        }
+    }
+}
+
+fn bulleted(issues: &[String]) -> String {
+    issues.iter().map(|issue| format!("\n  - {}", issue)).collect()
+}
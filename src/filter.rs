@@ -0,0 +1,90 @@
+use crate::{AppError, Result};
+use serde_json::Value;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+// Longer spellings first, so `==` is not read as a shorter operator.
+const OPERATORS: &[(&str, Op)] = &[
+    (" contains ", Op::Contains),
+    ("==", Op::Eq),
+    ("!=", Op::Ne),
+    ("<=", Op::Le),
+    (">=", Op::Ge),
+    ("<", Op::Lt),
+    (">", Op::Gt),
+];
+
+/// A `<pointer><op><value>` test against a stored value, e.g.
+/// `/status==active`, `/age>=30` or `/tags contains "urgent"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub pointer: String,
+    pub op: Op,
+    pub value: Value,
+}
+
+impl Condition {
+    /// The value is read as JSON when it parses, otherwise as a plain string.
+    pub fn parse(expression: &str) -> Result<Self> {
+        let invalid = |reason: &str| AppError::Validation {
+            message: format!("Invalid condition '{}': {}", expression, reason),
+        };
+
+        let (index, token, op) = OPERATORS
+            .iter()
+            .filter_map(|(token, op)| expression.find(token).map(|index| (index, *token, *op)))
+            .min_by_key(|(index, _, _)| *index)
+            .ok_or_else(|| invalid("expected one of ==, !=, <, <=, >, >=, contains"))?;
+
+        let pointer = expression[..index].trim();
+        if !pointer.is_empty() && !pointer.starts_with('/') {
+            return Err(invalid("the JSON Pointer must be empty or start with '/'"));
+        }
+        let raw = expression[index + token.len()..].trim();
+        let value = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+
+        Ok(Self { pointer: pointer.to_string(), op, value })
+    }
+
+    /// A pointer that does not resolve only satisfies `!=`.
+    pub fn matches(&self, root: &Value) -> bool {
+        let Some(actual) = root.pointer(&self.pointer) else {
+            return self.op == Op::Ne;
+        };
+
+        match self.op {
+            Op::Eq => *actual == self.value,
+            Op::Ne => *actual != self.value,
+            Op::Lt => compare(actual, &self.value) == Some(Ordering::Less),
+            Op::Le => matches!(compare(actual, &self.value), Some(Ordering::Less | Ordering::Equal)),
+            Op::Gt => compare(actual, &self.value) == Some(Ordering::Greater),
+            Op::Ge => matches!(compare(actual, &self.value), Some(Ordering::Greater | Ordering::Equal)),
+            Op::Contains => match (actual, &self.value) {
+                (Value::Array(items), needle) => items.contains(needle),
+                (Value::String(text), Value::String(needle)) => text.contains(needle.as_str()),
+                (Value::Object(map), Value::String(key)) => map.contains_key(key),
+                _ => false,
+            },
+        }
+    }
+}
+
+/// Numbers compare numerically and strings lexically; anything else is
+/// unordered.
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
@@ -0,0 +1,142 @@
+use crate::Result;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::{Request, StatusCode, Version};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+const REDACTED: &str = "<redacted>";
+
+/// Collects request/response pairs and writes them to a HAR 1.2 file.
+/// The file is rewritten after every entry, so it always holds the
+/// exchanges of the current invocation.
+pub struct HarRecorder {
+    path: PathBuf,
+    redact_headers: Vec<String>,
+    entries: Mutex<Vec<Value>>,
+}
+
+/// The parts of a buffered response needed for a HAR entry.
+pub struct HarResponse<'a> {
+    pub status: StatusCode,
+    pub version: Version,
+    pub headers: &'a HeaderMap,
+    pub body: &'a [u8],
+}
+
+impl HarRecorder {
+    /// Header names in `redact_headers` are matched case-insensitively and
+    /// their values replaced in the recorded requests and responses.
+    pub fn new(path: PathBuf, redact_headers: &[String]) -> Self {
+        Self {
+            path,
+            redact_headers: redact_headers.iter().map(|name| name.to_ascii_lowercase()).collect(),
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Snapshots a request before it is sent (the body is consumed by sending).
+    pub fn capture_request(&self, request: &Request) -> Value {
+        let url = request.url();
+        let body = request.body().and_then(|body| body.as_bytes()).unwrap_or_default();
+
+        let mut entry = json!({
+            "method": request.method().as_str(),
+            "url": url.as_str(),
+            "httpVersion": http_version(request.version()),
+            "cookies": [],
+            "headers": self.headers(request.headers()),
+            "queryString": url
+                .query_pairs()
+                .map(|(name, value)| json!({"name": name, "value": value}))
+                .collect::<Vec<_>>(),
+            "headersSize": -1,
+            "bodySize": body.len(),
+        });
+        if !body.is_empty() {
+            entry["postData"] = json!({
+                "mimeType": mime_type(request.headers()),
+                "text": String::from_utf8_lossy(body),
+            });
+        }
+        entry
+    }
+
+    /// Adds an entry and rewrites the HAR file. Write failures only log a
+    /// warning so that recording never breaks a request.
+    pub fn record(&self, request: Value, started: DateTime<Utc>, elapsed: Duration, response: HarResponse) {
+        let millis = elapsed.as_secs_f64() * 1000.0;
+        let entry = json!({
+            "startedDateTime": started.to_rfc3339(),
+            "time": millis,
+            "request": request,
+            "response": {
+                "status": response.status.as_u16(),
+                "statusText": response.status.canonical_reason().unwrap_or_default(),
+                "httpVersion": http_version(response.version),
+                "cookies": [],
+                "headers": self.headers(response.headers),
+                "content": {
+                    "size": response.body.len(),
+                    "mimeType": mime_type(response.headers),
+                    "text": String::from_utf8_lossy(response.body),
+                },
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": response.body.len(),
+            },
+            "cache": {},
+            "timings": {"send": 0, "wait": millis, "receive": 0},
+        });
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(entry);
+        if let Err(e) = self.write(&entries) {
+            warn!("Failed to write HAR file {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn write(&self, entries: &[Value]) -> Result<()> {
+        let har = json!({
+            "log": {
+                "version": "1.2",
+                "creator": {"name": "rcli", "version": env!("CARGO_PKG_VERSION")},
+                "entries": entries,
+            }
+        });
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&har)?)?;
+        Ok(())
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> Vec<Value> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.redact_headers.iter().any(|redact| redact == name.as_str()) {
+                    REDACTED.into()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes())
+                };
+                json!({"name": name.as_str(), "value": value})
+            })
+            .collect()
+    }
+}
+
+fn mime_type(headers: &HeaderMap) -> String {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn http_version(version: Version) -> String {
+    format!("{:?}", version)
+}
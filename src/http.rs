@@ -1,50 +1,722 @@
+use crate::har::{HarRecorder, HarResponse};
+use crate::signing::{BearerToken, HmacSigner, SigV4Signer};
+use crate::sse::{SseEvent, SseParser};
 use crate::{AppError, Result};
-use reqwest::Client;
+use brotli_decompressor::DecompressorWriter;
+use flate2::write::{GzDecoder, ZlibDecoder};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use reqwest::header::{
+    HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE,
+    CONTENT_TYPE, RANGE, USER_AGENT,
+};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
-use std::time::Duration;
-use tracing::{info, warn};
+use std::io::{self, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{debug, field, info, instrument, warn, Span};
+use uuid::Uuid;
+
+const DEFAULT_USER_AGENT: &str = concat!("rust-advanced-cli/", env!("CARGO_PKG_VERSION"));
+
+/// Response statuses that are retried unless `with_retry_on_status` says
+/// otherwise: timeouts, rate limiting and transient server errors.
+pub const DEFAULT_RETRY_ON_STATUS: [u16; 6] = [408, 429, 500, 502, 503, 504];
+
+/// Longest wait between event stream reconnects.
+const MAX_SSE_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    pub query: Vec<(String, String)>,
+    pub force_retry: bool,
+    /// Read the body as newline-delimited JSON whatever its content type.
+    pub ndjson: bool,
+}
+
+/// A response returned as-is, whatever its status, together with how long
+/// the round trip took.
+#[derive(Debug)]
+pub struct ResponseMeta {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub elapsed: Duration,
+    pub body: String,
+}
+
+/// A parsed response body together with the status and headers of the
+/// response it came from.
+#[derive(Debug)]
+pub struct Fetched<T> {
+    pub value: T,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+}
+
+/// The outcome of `fetch_to_file`: bytes written by this call and the size
+/// of the partial file it continued from (0 for a full download).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Download {
+    pub written: u64,
+    pub resumed_from: u64,
+}
 
 pub struct HttpClient {
     client: Client,
     base_url: String,
+    timeout: Duration,
+    connect_timeout: Option<Duration>,
+    accept_compression: bool,
+    resolve: Vec<(String, SocketAddr)>,
     retry_attempts: u32,
+    retry_on_status: Vec<u16>,
+    rate_limiter: Option<DefaultDirectRateLimiter>,
+    user_agent: Option<String>,
+    circuit: Option<CircuitBreaker>,
+    retry_budget: Option<RetryBudget>,
+    signer: Option<HmacSigner>,
+    sigv4: Option<SigV4Signer>,
+    bearer: Option<BearerToken>,
+    har: Option<HarRecorder>,
+    limiter: Option<Arc<Semaphore>>,
 }
 
 impl HttpClient {
     pub fn new(base_url: String, timeout_seconds: u64, retry_attempts: u32) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(timeout_seconds))
-            .user_agent("rust-advanced-cli/0.1.0")
-            .build()?;
+        let timeout = Duration::from_secs(timeout_seconds);
 
         Ok(Self {
-            client,
+            client: build_client(timeout, None, true, &[])?,
             base_url,
+            timeout,
+            connect_timeout: None,
+            accept_compression: true,
+            resolve: Vec::new(),
             retry_attempts,
+            retry_on_status: DEFAULT_RETRY_ON_STATUS.to_vec(),
+            rate_limiter: None,
+            user_agent: None,
+            circuit: None,
+            retry_budget: None,
+            signer: None,
+            sigv4: None,
+            bearer: None,
+            har: None,
+            limiter: None,
         })
     }
 
+    /// Starts a client with named settings instead of `new`'s positional
+    /// ones. Unset options keep the built-in defaults: a 30 second timeout
+    /// and 3 retry attempts.
+    ///
+    /// ```
+    /// use rust_advanced_cli::http::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .base_url("https://api.example.com")
+    ///     .timeout_seconds(10)
+    ///     .retry_attempts(1)
+    ///     .user_agent("my-tool/1.0")
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(client.request_url("users/1", &[]).unwrap(), "https://api.example.com/users/1");
+    /// ```
+    pub fn builder() -> HttpClientBuilder {
+        HttpClientBuilder::default()
+    }
+
+    /// Controls whether gzip, brotli and deflate are offered in
+    /// `Accept-Encoding`. Compressed responses are decoded transparently.
+    /// Enabled by default.
+    pub fn with_compression(mut self, enabled: bool) -> Result<Self> {
+        if enabled != self.accept_compression {
+            self.client = build_client(self.timeout, self.connect_timeout, enabled, &self.resolve)?;
+            self.accept_compression = enabled;
+        }
+        Ok(self)
+    }
+
+    /// Pins host names to addresses, like curl's `--resolve`. Each entry is
+    /// `HOST:PORT:ADDRESS`; the port of the request URL is used when
+    /// connecting.
+    pub fn with_resolve(mut self, entries: &[String]) -> Result<Self> {
+        if entries.is_empty() {
+            return Ok(self);
+        }
+        self.resolve = entries.iter().map(|entry| parse_resolve(entry)).collect::<Result<_>>()?;
+        self.client = build_client(self.timeout, self.connect_timeout, self.accept_compression, &self.resolve)?;
+        Ok(self)
+    }
+
+    /// Gives up on establishing a connection after `connect_timeout`, so an
+    /// unreachable host fails fast while a slow body still gets the whole
+    /// request timeout. `None` leaves connecting bounded by the request
+    /// timeout only.
+    pub fn with_connect_timeout(mut self, connect_timeout: Option<Duration>) -> Result<Self> {
+        if connect_timeout != self.connect_timeout {
+            self.client = build_client(self.timeout, connect_timeout, self.accept_compression, &self.resolve)?;
+            self.connect_timeout = connect_timeout;
+        }
+        Ok(self)
+    }
+
+    /// Adds an HMAC signature and timestamp header to every request.
+    pub fn with_signer(mut self, signer: Option<HmacSigner>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Signs every request with AWS Signature Version 4.
+    pub fn with_sigv4(mut self, signer: Option<SigV4Signer>) -> Self {
+        self.sigv4 = signer;
+        self
+    }
+
+    /// Sends `Authorization: Bearer <token>` with every request. The token
+    /// is resolved when the first request is prepared.
+    pub fn with_bearer(mut self, bearer: Option<BearerToken>) -> Self {
+        self.bearer = bearer;
+        self
+    }
+
+    /// Records every exchange that receives a response into a HAR file.
+    pub fn with_har(mut self, recorder: Option<HarRecorder>) -> Self {
+        self.har = recorder;
+        self
+    }
+
+    /// Makes every request wait for a permit from `limiter`, which may be
+    /// shared with storage. The permit is held until the response headers
+    /// arrive.
+    pub fn with_concurrency_limit(mut self, limiter: Option<Arc<Semaphore>>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Sets which non-2xx statuses are worth retrying. Any other status
+    /// fails on the first attempt.
+    pub fn with_retry_on_status(mut self, statuses: Vec<u16>) -> Self {
+        self.retry_on_status = statuses;
+        self
+    }
+
+    /// Replaces the default `rust-advanced-cli/<version>` user agent.
+    pub fn with_user_agent(mut self, user_agent: Option<String>) -> Self {
+        self.user_agent = user_agent;
+        self
+    }
+
+    /// Spaces outbound requests (retries included) so that no more than
+    /// `requests_per_second` are sent. Zero disables limiting.
+    pub fn with_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.rate_limiter = NonZeroU32::new(requests_per_second).map(|rate| {
+            RateLimiter::direct(Quota::per_second(rate).allow_burst(NonZeroU32::MIN))
+        });
+        self
+    }
+
+    /// Stops sending requests for `cooldown` after `threshold` consecutive
+    /// failed attempts (transport errors and 5xx responses). Once the
+    /// cooldown elapses a single probe is let through: success closes the
+    /// circuit, failure opens it again. A threshold of zero disables it.
+    pub fn with_circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.circuit = (threshold > 0).then(|| CircuitBreaker::new(threshold, cooldown));
+        self
+    }
+
+    /// Caps the retries of all requests made through this client together:
+    /// at most `max_retries` retries, and at most `max_wait` spent waiting
+    /// between attempts. Once either runs out, failed requests are no longer
+    /// retried. With neither set, only the per-request retry limit applies.
+    pub fn with_retry_budget(mut self, max_retries: Option<u32>, max_wait: Option<Duration>) -> Self {
+        self.retry_budget = (max_retries.is_some() || max_wait.is_some()).then(|| RetryBudget::new(max_retries, max_wait));
+        self
+    }
+
+    #[instrument(skip_all, fields(url = %url))]
     pub async fn fetch_json(&self, url: &str) -> Result<Value> {
-        let full_url = if url.starts_with("http") {
-            url.to_string()
+        self.fetch_as::<Value>(url).await
+    }
+
+    /// GETs `url` and deserializes the body straight into `T`, with the same
+    /// retries as `fetch_json`.
+    #[instrument(skip_all, fields(url = %url))]
+    pub async fn fetch_as<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        self.request_as(Method::GET, url, None, &RequestOptions::default()).await
+    }
+
+    #[instrument(skip_all, fields(url = %url))]
+    pub async fn post_json(&self, url: &str, data: &Value) -> Result<Value> {
+        self.request_json(Method::POST, url, Some(data), &RequestOptions::default()).await
+    }
+
+    /// POSTs a multipart/form-data body of text `fields` and `files`. File
+    /// parts carry the file name and a content type guessed from the
+    /// extension. Sent once, like `post_json`.
+    #[instrument(skip_all, fields(url = %url, request_id = field::Empty))]
+    pub async fn post_multipart(
+        &self,
+        url: &str,
+        fields: &[(String, String)],
+        files: &[(String, PathBuf)],
+    ) -> Result<Value> {
+        let full_url = self.resolve_url(url);
+        let request_id = Uuid::new_v4().to_string();
+        Span::current().record("request_id", request_id.as_str());
+
+        let mut form = Form::new();
+        for (name, value) in fields {
+            form = form.text(name.clone(), value.clone());
+        }
+        for (name, path) in files {
+            let bytes = tokio::fs::read(path).await.map_err(|e| AppError::Validation {
+                message: format!("Cannot read upload file {}: {}", path.display(), e),
+            })?;
+            let file_name = path
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| name.clone());
+            let content_type = mime_guess::from_path(path).first_or_octet_stream();
+            debug!("Attaching {} ({} bytes, {}) as '{}'", path.display(), bytes.len(), content_type, name);
+            let part = Part::bytes(bytes).file_name(file_name).mime_str(content_type.as_ref())?;
+            form = form.part(name.clone(), part);
+        }
+
+        let request = self.prepare(Method::POST, &full_url, &request_id).await?.multipart(form);
+        if let Some(circuit) = &self.circuit {
+            circuit.acquire()?;
+        }
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.until_ready().await;
+        }
+
+        info!("Uploading {} fields and {} files to {}", fields.len(), files.len(), full_url);
+        let outcome = self.send(request).await;
+        self.record_outcome(&outcome);
+
+        let response = outcome.map_err(|e| self.transport_error(&full_url, e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AppError::OperationFailed {
+                reason: format!("HTTP {}: {}", status, error_text),
+            });
+        }
+        let bytes = response.bytes().await?;
+        Ok(serde_json::from_slice(if bytes.is_empty() { b"null" } else { &bytes })?)
+    }
+
+    /// Sends a single GET and returns the status, headers, body and latency
+    /// without treating non-2xx responses as errors. Only transport failures
+    /// (connection refused, timeout, ...) are returned as `Err`.
+    pub async fn fetch_with_meta(&self, url: &str) -> Result<ResponseMeta> {
+        self.fetch_with_headers(url, &[], HeaderMap::new()).await
+    }
+
+    /// `fetch_with_meta` with query parameters and extra request headers,
+    /// such as `If-None-Match` for a conditional request.
+    #[instrument(skip_all, fields(url = %url, request_id = field::Empty))]
+    pub async fn fetch_with_headers(
+        &self,
+        url: &str,
+        query: &[(String, String)],
+        headers: HeaderMap,
+    ) -> Result<ResponseMeta> {
+        let full_url = self.resolve_url(url);
+        let request_id = Uuid::new_v4().to_string();
+        Span::current().record("request_id", request_id.as_str());
+
+        let request = self.prepare(Method::GET, &full_url, &request_id).await?.query(query).headers(headers);
+        if let Some(circuit) = &self.circuit {
+            circuit.acquire()?;
+        }
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.until_ready().await;
+        }
+
+        let start = Instant::now();
+        let outcome = self.send(request).await;
+        self.record_outcome(&outcome);
+
+        let response = outcome.map_err(|e| self.transport_error(&full_url, e))?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.text().await?;
+        let elapsed = start.elapsed();
+        info!("GET {} returned {} in {:?}", full_url, status, elapsed);
+
+        Ok(ResponseMeta { status, headers, elapsed, body })
+    }
+
+    /// The absolute URL a GET of `url` with `query` is sent to.
+    pub fn request_url(&self, url: &str, query: &[(String, String)]) -> Result<String> {
+        let full_url = self.resolve_url(url);
+        if query.is_empty() {
+            return Ok(full_url);
+        }
+        let parsed = reqwest::Url::parse_with_params(&full_url, query).map_err(|e| AppError::Validation {
+            message: format!("Invalid URL '{}': {}", full_url, e),
+        })?;
+        Ok(parsed.to_string())
+    }
+
+    /// Streams the raw response body of a GET to `path`.
+    ///
+    /// With `resume` set and a partial file at `path`, only the missing tail
+    /// is requested with `Range: bytes=<len>-` and appended. A server that
+    /// ignores the range (200) gets the file rewritten from the start; one that
+    /// answers 416 for exactly the length on disk means the file is complete.
+    /// A 206 whose `Content-Range` does not start at that length is an error.
+    /// Compression is not offered, since byte ranges refer to the encoded body.
+    #[instrument(skip_all, fields(url = %url, request_id = field::Empty))]
+    pub async fn fetch_to_file(
+        &self,
+        url: &str,
+        query: &[(String, String)],
+        path: &Path,
+        resume: bool,
+    ) -> Result<Download> {
+        let full_url = self.resolve_url(url);
+        let request_id = Uuid::new_v4().to_string();
+        Span::current().record("request_id", request_id.as_str());
+
+        let existing = match tokio::fs::metadata(path).await {
+            Ok(metadata) if resume && metadata.is_file() => metadata.len(),
+            _ => 0,
+        };
+
+        let mut request = self
+            .prepare(Method::GET, &full_url, &request_id)
+            .await?
+            .query(query)
+            .header(ACCEPT_ENCODING, "identity");
+        if existing > 0 {
+            request = request.header(RANGE, format!("bytes={}-", existing));
+        }
+        if let Some(circuit) = &self.circuit {
+            circuit.acquire()?;
+        }
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.until_ready().await;
+        }
+
+        let outcome = self.send(request).await;
+        self.record_outcome(&outcome);
+        let mut response = outcome.map_err(|e| self.transport_error(&full_url, e))?;
+
+        let status = response.status();
+        let content_range = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        let complete_range = format!("bytes */{}", existing);
+        if existing > 0
+            && status == StatusCode::RANGE_NOT_SATISFIABLE
+            && content_range.as_deref() == Some(complete_range.as_str())
+        {
+            info!("{} is already complete ({} bytes)", path.display(), existing);
+            return Ok(Download { written: 0, resumed_from: existing });
+        }
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AppError::OperationFailed {
+                reason: format!("HTTP {}: {}", status, error_text),
+            });
+        }
+
+        let resumed_from = if status == StatusCode::PARTIAL_CONTENT {
+            // Without a matching Content-Range the body is an unknown slice
+            // of the file, and writing it would silently corrupt the download.
+            let expected = format!("bytes {}-", existing);
+            match content_range {
+                Some(range) if existing > 0 && range.starts_with(&expected) => existing,
+                Some(range) => {
+                    return Err(AppError::OperationFailed {
+                        reason: format!("Server returned range '{}' instead of bytes {}-", range, existing),
+                    })
+                }
+                None => {
+                    return Err(AppError::OperationFailed {
+                        reason: "Server returned 206 Partial Content without a Content-Range header".to_string(),
+                    })
+                }
+            }
+        } else if status == StatusCode::OK {
+            if existing > 0 {
+                warn!("Server did not honor the range request; downloading {} from the start", full_url);
+            }
+            0
         } else {
-            format!("{}/{}", self.base_url.trim_end_matches('/'), url.trim_start_matches('/'))
+            return Err(AppError::OperationFailed {
+                reason: format!("Unexpected HTTP {} for a download", status),
+            });
         };
 
-        info!("Fetching data from: {}", full_url);
+        let mut file = if resumed_from > 0 {
+            tokio::fs::OpenOptions::new().append(true).open(path).await?
+        } else {
+            tokio::fs::File::create(path).await?
+        };
+        let mut written = 0u64;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        file.flush().await?;
+        info!("Downloaded {} bytes from {} to {}", written, full_url, path.display());
+
+        Ok(Download { written, resumed_from })
+    }
+
+    /// Follows a `text/event-stream` GET, calling `on_event` for each event
+    /// as it arrives until it returns `ControlFlow::Break`.
+    ///
+    /// A dropped connection is reopened with `Last-Event-ID` set to the last
+    /// id seen, after a delay that starts at the server's `retry:` value (1
+    /// second by default) and doubles with each reconnect that brings no event,
+    /// up to 30 seconds. With `max_reconnects`, gives up after that many
+    /// reconnects in a row without an event: with the last error, or `Ok` if
+    /// the server had closed the stream cleanly. A 204 ends the stream; other
+    /// error statuses outside `retry_on_status` and responses that are not an
+    /// event stream fail at once.
+    ///
+    /// The client timeout only covers receiving the response headers, and
+    /// streams are not recorded in the HAR file. Each connection waits for a
+    /// `with_concurrency_limit` permit like any other request.
+    #[instrument(skip_all, fields(url = %url))]
+    pub async fn stream_events<F>(&self, url: &str, max_reconnects: Option<u32>, mut on_event: F) -> Result<()>
+    where
+        F: FnMut(SseEvent) -> Result<ControlFlow<()>>,
+    {
+        let full_url = self.resolve_url(url);
+        let mut parser = SseParser::default();
+        let mut reconnects = 0u32;
+
+        loop {
+            let mut received = false;
+            let error = match self.follow_stream(&full_url, &mut parser, &mut on_event, &mut received).await? {
+                StreamEnd::Stopped => return Ok(()),
+                StreamEnd::Closed => None,
+                StreamEnd::Dropped(e) => Some(e),
+            };
+            if received {
+                reconnects = 0;
+            }
+            if max_reconnects.is_some_and(|max| reconnects >= max) {
+                return error.map_or(Ok(()), Err);
+            }
 
-        for attempt in 1..=self.retry_attempts {
-            match self.client.get(&full_url).send().await {
+            let base = parser.retry().unwrap_or(Duration::from_secs(1));
+            let delay = base
+                .checked_mul(2u32.saturating_pow(reconnects))
+                .map_or(MAX_SSE_BACKOFF, |delay| delay.min(MAX_SSE_BACKOFF));
+            reconnects += 1;
+            match error {
+                Some(e) => warn!("Event stream dropped: {}; reconnecting in {:?}", e, delay),
+                None => info!("Event stream closed; reconnecting in {:?}", delay),
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// One connection of `stream_events`. Errors worth reconnecting after
+    /// come back as `StreamEnd::Dropped`; the rest are returned.
+    async fn follow_stream<F>(
+        &self,
+        url: &str,
+        parser: &mut SseParser,
+        on_event: &mut F,
+        received: &mut bool,
+    ) -> Result<StreamEnd>
+    where
+        F: FnMut(SseEvent) -> Result<ControlFlow<()>>,
+    {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.until_ready().await;
+        }
+
+        let request_id = Uuid::new_v4().to_string();
+        let mut request = self
+            .prepare(Method::GET, url, &request_id)
+            .await?
+            .header(ACCEPT, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache")
+            .timeout(Duration::MAX);
+        if let Some(id) = parser.last_event_id() {
+            request = request.header("Last-Event-ID", id);
+        }
+        let mut request = request.build()?;
+        self.sign(&mut request);
+
+        let permit = self.permit().await;
+        let outcome = tokio::time::timeout(self.timeout, self.client.execute(request)).await;
+        drop(permit);
+        let mut response = match outcome {
+            Ok(Ok(response)) => self.decode(response),
+            Ok(Err(e)) => return Ok(StreamEnd::Dropped(self.transport_error(url, e))),
+            Err(_) => {
+                return Ok(StreamEnd::Dropped(AppError::Timeout {
+                    url: url.to_string(),
+                    after_seconds: self.timeout.as_secs(),
+                }))
+            }
+        };
+
+        let status = response.status();
+        if status == StatusCode::NO_CONTENT {
+            info!("Event stream {} ended with 204 No Content", url);
+            return Ok(StreamEnd::Stopped);
+        }
+        if !status.is_success() {
+            let error = AppError::OperationFailed {
+                reason: format!("HTTP {}: {}", status, response.text().await.unwrap_or_default()),
+            };
+            return if self.retry_on_status.contains(&status.as_u16()) {
+                Ok(StreamEnd::Dropped(error))
+            } else {
+                Err(error)
+            };
+        }
+        if !is_event_stream(response.headers()) {
+            return Err(AppError::Validation {
+                message: format!("{} did not answer with a text/event-stream", url),
+            });
+        }
+
+        info!("Connected to event stream {} (request id {})", url, request_id);
+        parser.reset();
+        loop {
+            let chunk = match response.chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => return Ok(StreamEnd::Closed),
+                Err(e) => return Ok(StreamEnd::Dropped(self.transport_error(url, e))),
+            };
+            for event in parser.feed(&chunk) {
+                *received = true;
+                if on_event(event)?.is_break() {
+                    return Ok(StreamEnd::Stopped);
+                }
+            }
+        }
+    }
+
+    /// Sends a request and parses the response body as JSON.
+    pub async fn request_json(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<&Value>,
+        options: &RequestOptions,
+    ) -> Result<Value> {
+        self.request_as(method, url, body, options).await
+    }
+
+    /// Sends a request and deserializes the response body into `T`. An empty
+    /// body is read as JSON `null`.
+    ///
+    /// Only GET, HEAD and DELETE are retried automatically: repeating them
+    /// cannot change server state beyond the first success. Other methods may
+    /// not be idempotent (a retried POST can create a duplicate record when
+    /// only the response was lost), so they are sent once unless the caller
+    /// sets `force_retry`.
+    ///
+    /// Query parameters in `options` are URL-encoded and appended to any
+    /// already present in `url`; repeated keys are sent as repeated pairs.
+    pub async fn request_as<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<&Value>,
+        options: &RequestOptions,
+    ) -> Result<T> {
+        Ok(self.request_with_meta(method, url, body, options).await?.value)
+    }
+
+    /// `request_as` that also returns the status and headers of the
+    /// successful response.
+    #[instrument(skip_all, fields(method = %method, url = %url, request_id = field::Empty, attempt = field::Empty))]
+    pub async fn request_with_meta<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<&Value>,
+        options: &RequestOptions,
+    ) -> Result<Fetched<T>> {
+        let full_url = self.resolve_url(url);
+        let attempts = if options.force_retry || is_idempotent(&method) {
+            self.retry_attempts.max(1)
+        } else {
+            1
+        };
+
+        let request_id = Uuid::new_v4().to_string();
+        Span::current().record("request_id", request_id.as_str());
+        info!("Sending {} request to: {} (request id {})", method, full_url, request_id);
+
+        for attempt in 1..=attempts {
+            Span::current().record("attempt", attempt);
+
+            let mut request = self
+                .prepare(method.clone(), &full_url, &request_id)
+                .await?
+                .query(&options.query);
+            if let Some(data) = body {
+                request = request.json(data);
+            }
+
+            if let Some(circuit) = &self.circuit {
+                circuit.acquire()?;
+            }
+
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.until_ready().await;
+            }
+
+            let outcome = self.send(request).await;
+            self.record_outcome(&outcome);
+
+            match outcome {
                 Ok(response) => {
-                    if response.status().is_success() {
-                        let json: Value = response.json().await?;
-                        info!("Successfully fetched data (attempt {})", attempt);
-                        return Ok(json);
+                    let (status, headers) = (response.status(), response.headers().clone());
+                    if status.is_success() && (options.ndjson || is_ndjson(&headers)) {
+                        let items = read_ndjson(response).await?;
+                        info!("Received {} NDJSON records (attempt {})", items.len(), attempt);
+                        let value = serde_json::from_value(Value::Array(items))?;
+                        return Ok(Fetched { value, status, headers });
+                    } else if status.is_success() {
+                        // Decoded responses lose their Content-Encoding header; one that
+                        // is still present was not decoded.
+                        let undecoded = response.headers().get(CONTENT_ENCODING).cloned();
+                        let bytes = response.bytes().await?;
+                        match undecoded {
+                            Some(encoding) => warn!("Response body left encoded as {:?}", encoding),
+                            None => debug!(
+                                "Received {} bytes (compression {})",
+                                bytes.len(),
+                                if self.accept_compression { "accepted" } else { "disabled" }
+                            ),
+                        }
+                        let value = serde_json::from_slice(if bytes.is_empty() { b"null" } else { &bytes })?;
+                        info!("Successfully completed {} request (attempt {})", method, attempt);
+                        return Ok(Fetched { value, status, headers });
                     } else {
-                        let status = response.status();
                         let error_text = response.text().await.unwrap_or_default();
                         
-                        if attempt == self.retry_attempts {
+                        if attempt == attempts
+                            || !self.retry_on_status.contains(&status.as_u16())
+                            || !self.spend_retry(attempt)
+                        {
                             return Err(AppError::OperationFailed {
                                 reason: format!("HTTP {}: {}", status, error_text),
                             });
@@ -54,46 +726,637 @@ impl HttpClient {
                     }
                 }
                 Err(e) => {
-                    if attempt == self.retry_attempts {
-                        return Err(AppError::Http(e));
+                    if attempt == attempts || !self.spend_retry(attempt) {
+                        return Err(self.transport_error(&full_url, e));
                     } else {
                         warn!("Request failed (attempt {}): {}, retrying...", attempt, e);
                     }
                 }
             }
 
-            tokio::time::sleep(Duration::from_millis(1000 * attempt as u64)).await;
+            tokio::time::sleep(retry_delay(attempt)).await;
         }
 
         unreachable!()
     }
 
-    pub async fn post_json(&self, url: &str, data: &Value) -> Result<Value> {
-        let full_url = if url.starts_with("http") {
+    /// Takes the retry after `attempt` from the retry budget, if there is
+    /// one. `false` means the budget is used up and the request should fail.
+    fn spend_retry(&self, attempt: u32) -> bool {
+        self.retry_budget.as_ref().is_none_or(|budget| budget.try_spend(retry_delay(attempt)))
+    }
+
+    /// The request with the id, user agent and bearer token set. Resolving
+    /// the token can fail, so callers prepare before `circuit.acquire()`: an
+    /// error after it would leave a half-open probe that is never recorded.
+    async fn prepare(&self, method: Method, full_url: &str, request_id: &str) -> Result<RequestBuilder> {
+        let mut request = self
+            .client
+            .request(method, full_url)
+            .header("X-Request-Id", request_id);
+        if let Some(user_agent) = &self.user_agent {
+            request = request.header(USER_AGENT, user_agent);
+        }
+        if let Some(bearer) = &self.bearer {
+            request = request.bearer_auth(bearer.token().await?);
+        }
+        Ok(request)
+    }
+
+    /// A permit from the `--max-concurrency` semaphore, held until the
+    /// response headers have arrived.
+    async fn permit(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.limiter {
+            Some(limiter) => limiter.acquire().await.ok(),
+            None => None,
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let _permit = self.permit().await;
+        let mut request = request.build()?;
+        self.sign(&mut request);
+        let Some(har) = &self.har else {
+            return Ok(self.decode(self.client.execute(request).await?));
+        };
+
+        // The body has to be buffered for the HAR entry, so the response is
+        // rebuilt around it for the caller.
+        let captured = har.capture_request(&request);
+        let started = chrono::Utc::now();
+        let start = Instant::now();
+        let response = self.decode(self.client.execute(request).await?);
+        let status = response.status();
+        let version = response.version();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+        har.record(
+            captured,
+            started,
+            start.elapsed(),
+            HarResponse { status, version, headers: &headers, body: &body },
+        );
+
+        let mut rebuilt = http::Response::new(body);
+        *rebuilt.status_mut() = status;
+        *rebuilt.version_mut() = version;
+        *rebuilt.headers_mut() = headers;
+        Ok(Response::from(rebuilt))
+    }
+
+    fn sign(&self, request: &mut reqwest::Request) {
+        if let Some(signer) = &self.signer {
+            signer.sign(request, chrono::Utc::now().timestamp());
+        }
+        if let Some(signer) = &self.sigv4 {
+            signer.sign(request, chrono::Utc::now());
+        }
+    }
+
+    /// Wraps a gzip, deflate or brotli encoded body so it is decoded as it
+    /// is read. Bodies are left alone when compression is disabled.
+    fn decode(&self, response: Response) -> Response {
+        if !self.accept_compression {
+            return response;
+        }
+        let encoding = response
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let Some((encoding, decoder)) = encoding.and_then(|encoding| {
+            let decoder = BodyDecoder::for_encoding(&encoding)?;
+            Some((encoding, decoder))
+        }) else {
+            return response;
+        };
+        debug!("Response body is {}-encoded; decoding it", encoding);
+
+        let mut headers = response.headers().clone();
+        headers.remove(CONTENT_ENCODING);
+        headers.remove(CONTENT_LENGTH);
+        let (status, version) = (response.status(), response.version());
+        let body = futures::stream::try_unfold(
+            (response, Some(decoder)),
+            |(mut response, decoder)| async move {
+                let Some(mut decoder) = decoder else {
+                    return Ok::<_, Box<dyn std::error::Error + Send + Sync>>(None);
+                };
+                match response.chunk().await? {
+                    Some(chunk) => {
+                        let decoded = decoder.decode(&chunk)?;
+                        Ok(Some((decoded, (response, Some(decoder)))))
+                    }
+                    None => Ok(Some((decoder.finish()?, (response, None)))),
+                }
+            },
+        );
+
+        let mut rebuilt = http::Response::new(reqwest::Body::wrap_stream(body));
+        *rebuilt.status_mut() = status;
+        *rebuilt.version_mut() = version;
+        *rebuilt.headers_mut() = headers;
+        Response::from(rebuilt)
+    }
+
+    /// Splits timeouts and failed connections out of `AppError::Http`.
+    fn transport_error(&self, url: &str, error: reqwest::Error) -> AppError {
+        if let Some(connect_timeout) = self.connect_timeout.filter(|_| error.is_connect() && error.is_timeout()) {
+            AppError::Timeout {
+                url: url.to_string(),
+                after_seconds: connect_timeout.as_secs(),
+            }
+        } else if error.is_timeout() {
+            AppError::Timeout {
+                url: url.to_string(),
+                after_seconds: self.timeout.as_secs(),
+            }
+        } else if error.is_connect() {
+            let mut reason = error.to_string();
+            let mut source = std::error::Error::source(&error);
+            while let Some(cause) = source {
+                reason = cause.to_string();
+                source = cause.source();
+            }
+            AppError::Connection { url: url.to_string(), reason }
+        } else {
+            AppError::Http(error)
+        }
+    }
+
+    fn record_outcome(&self, outcome: &reqwest::Result<Response>) {
+        if let Some(circuit) = &self.circuit {
+            match outcome {
+                Ok(response) if !response.status().is_server_error() => circuit.record_success(),
+                _ => circuit.record_failure(),
+            }
+        }
+    }
+
+    fn resolve_url(&self, url: &str) -> String {
+        if url.starts_with("http") {
             url.to_string()
         } else {
             format!("{}/{}", self.base_url.trim_end_matches('/'), url.trim_start_matches('/'))
+        }
+    }
+}
+
+/// Named settings for an `HttpClient`; see `HttpClient::builder`.
+pub struct HttpClientBuilder {
+    base_url: String,
+    timeout_seconds: u64,
+    connect_timeout_seconds: Option<u64>,
+    retry_attempts: u32,
+    retry_on_status: Vec<u16>,
+    accept_compression: bool,
+    resolve: Vec<String>,
+    requests_per_second: u32,
+    user_agent: Option<String>,
+    circuit_breaker: Option<(u32, Duration)>,
+    max_retries_total: Option<u32>,
+    max_retry_wait: Option<Duration>,
+    signer: Option<HmacSigner>,
+    sigv4: Option<SigV4Signer>,
+    bearer: Option<BearerToken>,
+    har: Option<HarRecorder>,
+    limiter: Option<Arc<Semaphore>>,
+}
+
+impl Default for HttpClientBuilder {
+    fn default() -> Self {
+        Self {
+            base_url: String::new(),
+            timeout_seconds: 30,
+            connect_timeout_seconds: None,
+            retry_attempts: 3,
+            retry_on_status: DEFAULT_RETRY_ON_STATUS.to_vec(),
+            accept_compression: true,
+            resolve: Vec::new(),
+            requests_per_second: 0,
+            user_agent: None,
+            circuit_breaker: None,
+            max_retries_total: None,
+            max_retry_wait: None,
+            signer: None,
+            sigv4: None,
+            bearer: None,
+            har: None,
+            limiter: None,
+        }
+    }
+}
+
+impl HttpClientBuilder {
+    /// Prefix for relative request URLs.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn timeout_seconds(mut self, timeout_seconds: u64) -> Self {
+        self.timeout_seconds = timeout_seconds;
+        self
+    }
+
+    /// See `HttpClient::with_connect_timeout`.
+    pub fn connect_timeout_seconds(mut self, connect_timeout_seconds: u64) -> Self {
+        self.connect_timeout_seconds = Some(connect_timeout_seconds);
+        self
+    }
+
+    pub fn retry_attempts(mut self, retry_attempts: u32) -> Self {
+        self.retry_attempts = retry_attempts;
+        self
+    }
+
+    /// See `HttpClient::with_retry_on_status`.
+    pub fn retry_on_status(mut self, statuses: Vec<u16>) -> Self {
+        self.retry_on_status = statuses;
+        self
+    }
+
+    /// See `HttpClient::with_compression`.
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.accept_compression = enabled;
+        self
+    }
+
+    /// `HOST:PORT:ADDRESS` overrides; see `HttpClient::with_resolve`.
+    pub fn resolve(mut self, entries: Vec<String>) -> Self {
+        self.resolve = entries;
+        self
+    }
+
+    /// See `HttpClient::with_rate_limit`.
+    pub fn rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.requests_per_second = requests_per_second;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// See `HttpClient::with_circuit_breaker`.
+    pub fn circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.circuit_breaker = Some((threshold, cooldown));
+        self
+    }
+
+    /// See `HttpClient::with_retry_budget`.
+    pub fn max_retries_total(mut self, max_retries: u32) -> Self {
+        self.max_retries_total = Some(max_retries);
+        self
+    }
+
+    /// See `HttpClient::with_retry_budget`.
+    pub fn max_retry_wait(mut self, max_wait: Duration) -> Self {
+        self.max_retry_wait = Some(max_wait);
+        self
+    }
+
+    /// HMAC request signing; see `HttpClient::with_signer`.
+    pub fn hmac(mut self, signer: HmacSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// AWS Signature Version 4 signing; see `HttpClient::with_sigv4`.
+    pub fn sigv4(mut self, signer: SigV4Signer) -> Self {
+        self.sigv4 = Some(signer);
+        self
+    }
+
+    /// See `HttpClient::with_bearer`.
+    pub fn bearer(mut self, bearer: BearerToken) -> Self {
+        self.bearer = Some(bearer);
+        self
+    }
+
+    pub fn har(mut self, recorder: HarRecorder) -> Self {
+        self.har = Some(recorder);
+        self
+    }
+
+    /// See `HttpClient::with_concurrency_limit`.
+    pub fn concurrency_limit(mut self, limiter: Arc<Semaphore>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Fails on an invalid `resolve` entry or when the underlying HTTP
+    /// client cannot be created.
+    pub fn build(self) -> Result<HttpClient> {
+        let mut client = HttpClient::new(self.base_url, self.timeout_seconds, self.retry_attempts)?
+            .with_connect_timeout(self.connect_timeout_seconds.map(Duration::from_secs))?
+            .with_compression(self.accept_compression)?
+            .with_resolve(&self.resolve)?
+            .with_retry_on_status(self.retry_on_status)
+            .with_rate_limit(self.requests_per_second)
+            .with_retry_budget(self.max_retries_total, self.max_retry_wait)
+            .with_user_agent(self.user_agent)
+            .with_signer(self.signer)
+            .with_sigv4(self.sigv4)
+            .with_bearer(self.bearer)
+            .with_har(self.har)
+            .with_concurrency_limit(self.limiter);
+        if let Some((threshold, cooldown)) = self.circuit_breaker {
+            client = client.with_circuit_breaker(threshold, cooldown);
+        }
+        Ok(client)
+    }
+}
+
+/// Parses a `HOST:PORT:ADDRESS` override. IPv6 addresses may be bracketed.
+pub fn parse_resolve(entry: &str) -> Result<(String, SocketAddr)> {
+    let invalid = |reason: &str| AppError::Validation {
+        message: format!("Invalid resolve entry '{}': {} (expected HOST:PORT:ADDRESS)", entry, reason),
+    };
+
+    let mut parts = entry.splitn(3, ':');
+    let (Some(host), Some(port), Some(address)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid("missing fields"));
+    };
+    if host.is_empty() {
+        return Err(invalid("empty host"));
+    }
+    let port: u16 = port.parse().map_err(|_| invalid("port is not a number"))?;
+    let address: IpAddr = address
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .map_err(|_| invalid("address is not an IP address"))?;
+
+    Ok((host.to_string(), SocketAddr::new(address, port)))
+}
+
+fn build_client(
+    timeout: Duration,
+    connect_timeout: Option<Duration>,
+    accept_compression: bool,
+    resolve: &[(String, SocketAddr)],
+) -> Result<Client> {
+    let mut builder = Client::builder().timeout(timeout).user_agent(DEFAULT_USER_AGENT);
+    if accept_compression {
+        // Decoded by `HttpClient::decode` rather than reqwest, so the
+        // encoding a response actually used can be logged.
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip, br, deflate"));
+        builder = builder.default_headers(headers);
+    }
+    if let Some(connect_timeout) = connect_timeout {
+        builder = builder.connect_timeout(connect_timeout);
+    }
+    for (host, address) in resolve {
+        builder = builder.resolve(host, *address);
+    }
+    Ok(builder.build()?)
+}
+
+/// Undoes a gzip, deflate or brotli `Content-Encoding` one chunk at a time,
+/// so streamed bodies are never buffered whole.
+enum BodyDecoder {
+    Gzip(GzDecoder<Vec<u8>>),
+    Deflate(ZlibDecoder<Vec<u8>>),
+    Brotli(Box<DecompressorWriter<Vec<u8>>>),
+}
+
+impl BodyDecoder {
+    fn for_encoding(encoding: &str) -> Option<Self> {
+        match encoding.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Self::Gzip(GzDecoder::new(Vec::new()))),
+            "deflate" => Some(Self::Deflate(ZlibDecoder::new(Vec::new()))),
+            "br" => Some(Self::Brotli(Box::new(DecompressorWriter::new(Vec::new(), 4096)))),
+            _ => None,
+        }
+    }
+
+    /// Returns whatever `chunk` completes of the decoded body.
+    fn decode(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        let output = match self {
+            Self::Gzip(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+            Self::Deflate(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+            Self::Brotli(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
         };
+        Ok(std::mem::take(output))
+    }
 
-        info!("Posting data to: {}", full_url);
+    /// Returns the rest of the decoded body, failing if it was truncated.
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(decoder) => decoder.finish(),
+            Self::Deflate(decoder) => decoder.finish(),
+            Self::Brotli(mut decoder) => {
+                decoder.close()?;
+                Ok(decoder.into_inner().unwrap_or_else(|output| output))
+            }
+        }
+    }
+}
 
-        let response = self
-            .client
-            .post(&full_url)
-            .json(data)
-            .send()
-            .await?;
-
-        if response.status().is_success() {
-            let json: Value = response.json().await?;
-            info!("Successfully posted data");
-            Ok(json)
+/// How one connection of `stream_events` ended.
+enum StreamEnd {
+    /// The caller or a 204 asked to stop.
+    Stopped,
+    /// The server closed the stream.
+    Closed,
+    /// The connection failed in a way worth reconnecting after.
+    Dropped(AppError),
+}
+
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    content_type_essence(headers).is_some_and(|essence| essence == "text/event-stream")
+}
+
+pub(crate) fn is_ndjson(headers: &HeaderMap) -> bool {
+    content_type_essence(headers).is_some_and(|essence| {
+        matches!(essence.as_str(), "application/x-ndjson" | "application/ndjson" | "application/jsonl")
+    })
+}
+
+/// The media type of the `Content-Type` header, lowercased and without
+/// parameters.
+fn content_type_essence(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    Some(content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase())
+}
+
+/// Parses newline-delimited JSON as the body arrives, one value per
+/// non-blank line, so only the current partial line is buffered.
+async fn read_ndjson(mut response: Response) -> Result<Vec<Value>> {
+    let mut items = Vec::new();
+    let mut pending = Vec::new();
+    let mut line_number = 0;
+
+    while let Some(chunk) = response.chunk().await? {
+        pending.extend_from_slice(&chunk);
+        let mut start = 0;
+        while let Some(offset) = pending[start..].iter().position(|&byte| byte == b'\n') {
+            line_number += 1;
+            parse_ndjson_line(&pending[start..start + offset], line_number, &mut items)?;
+            start += offset + 1;
+        }
+        pending.drain(..start);
+    }
+    parse_ndjson_line(&pending, line_number + 1, &mut items)?;
+    Ok(items)
+}
+
+/// Parses a newline-delimited JSON body that has already been read.
+pub(crate) fn parse_ndjson(body: &str) -> Result<Vec<Value>> {
+    let mut items = Vec::new();
+    for (index, line) in body.split('\n').enumerate() {
+        parse_ndjson_line(line.as_bytes(), index + 1, &mut items)?;
+    }
+    Ok(items)
+}
+
+fn parse_ndjson_line(line: &[u8], line_number: usize, items: &mut Vec<Value>) -> Result<()> {
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Ok(());
+    }
+    let value = serde_json::from_slice(line).map_err(|e| AppError::Validation {
+        message: format!("Invalid NDJSON on line {}: {}", line_number, e),
+    })?;
+    items.push(value);
+    Ok(())
+}
+
+/// How long to wait before retrying after the `attempt`-th attempt failed.
+fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_millis(1000 * attempt as u64)
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::DELETE)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CircuitState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen,
+}
+
+struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<CircuitState>,
+}
+
+impl CircuitBreaker {
+    fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Mutex::new(CircuitState::Closed { failures: 0 }),
+        }
+    }
+
+    fn acquire(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            CircuitState::Closed { .. } => Ok(()),
+            CircuitState::Open { until } if Instant::now() >= until => {
+                info!("Circuit half-open, sending probe request");
+                *state = CircuitState::HalfOpen;
+                Ok(())
+            }
+            CircuitState::Open { until } => Err(AppError::OperationFailed {
+                reason: format!(
+                    "circuit open after {} consecutive failures, retry in {}s",
+                    self.threshold,
+                    until.saturating_duration_since(Instant::now()).as_secs().max(1)
+                ),
+            }),
+            CircuitState::HalfOpen => Err(AppError::OperationFailed {
+                reason: "circuit open, probe request in flight".to_string(),
+            }),
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if matches!(*state, CircuitState::HalfOpen) {
+            info!("Probe succeeded, circuit closed");
+        }
+        *state = CircuitState::Closed { failures: 0 };
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            CircuitState::Closed { failures } => failures + 1,
+            _ => self.threshold,
+        };
+
+        *state = if failures >= self.threshold {
+            warn!("Circuit opened for {:?} after {} consecutive failures", self.cooldown, failures);
+            CircuitState::Open { until: Instant::now() + self.cooldown }
         } else {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            Err(AppError::OperationFailed {
-                reason: format!("HTTP {}: {}", status, error_text),
-            })
+            CircuitState::Closed { failures }
+        };
+    }
+}
+
+#[derive(Debug, Default)]
+struct RetrySpend {
+    retries: u32,
+    waited: Duration,
+    exhausted: bool,
+}
+
+/// Retries shared by every request of a client; see
+/// `HttpClient::with_retry_budget`.
+struct RetryBudget {
+    max_retries: Option<u32>,
+    max_wait: Option<Duration>,
+    spent: Mutex<RetrySpend>,
+}
+
+impl RetryBudget {
+    fn new(max_retries: Option<u32>, max_wait: Option<Duration>) -> Self {
+        Self {
+            max_retries,
+            max_wait,
+            spent: Mutex::new(RetrySpend::default()),
         }
     }
-}
\ No newline at end of file
+
+    /// Takes one retry followed by `delay` of waiting from the budget. Once
+    /// that would go over either limit, the budget stays exhausted, even if a
+    /// shorter wait would still fit.
+    fn try_spend(&self, delay: Duration) -> bool {
+        let mut spent = self.spent.lock().unwrap();
+        if spent.exhausted {
+            return false;
+        }
+        let retries = spent.retries + 1;
+        let waited = spent.waited + delay;
+        if self.max_retries.is_some_and(|max| retries > max) || self.max_wait.is_some_and(|max| waited > max) {
+            warn!(
+                "Retry budget exhausted after {} retries and {:?} of waiting; failing requests without retrying",
+                spent.retries, spent.waited
+            );
+            spent.exhausted = true;
+            return false;
+        }
+        spent.retries = retries;
+        spent.waited = waited;
+        true
+    }
+}
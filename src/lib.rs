@@ -1,8 +1,26 @@
+pub mod batch;
+pub mod bench;
+pub mod cache;
 pub mod cli;
+pub mod clipboard;
+pub mod color;
+pub mod completions;
 pub mod config;
+pub mod diff;
+pub mod doctor;
+pub mod editor;
 pub mod error;
+pub mod filter;
+pub mod har;
 pub mod http;
 pub mod logging;
+pub mod output;
+pub mod pointer;
+pub mod runner;
+pub mod schema;
+pub mod signing;
+pub mod sse;
 pub mod storage;
+pub mod xml;
 
 pub use error::{AppError, Result};
\ No newline at end of file
@@ -1,19 +1,28 @@
 use crate::config::LoggingConfig;
 use crate::Result;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
-pub fn init_logging(config: &LoggingConfig) -> Result<()> {
+#[cfg(feature = "otlp")]
+static TRACER_PROVIDER: std::sync::OnceLock<opentelemetry_sdk::trace::SdkTracerProvider> =
+    std::sync::OnceLock::new();
+
+pub fn init_logging(config: &LoggingConfig, ansi: bool) -> Result<()> {
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new(&config.level));
 
-    let stdout_layer = tracing_subscriber::fmt::layer()
+    let console_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
         .with_target(false)
         .with_thread_ids(true)
         .with_file(true)
-        .with_line_number(true);
+        .with_line_number(true)
+        .with_ansi(ansi);
 
     let mut layers = Vec::new();
-    layers.push(stdout_layer.boxed());
+    layers.push(console_layer.boxed());
 
     if let Some(file_path) = &config.file_path {
         if let Some(parent) = file_path.parent() {
@@ -36,10 +45,88 @@ pub fn init_logging(config: &LoggingConfig) -> Result<()> {
         layers.push(file_layer.boxed());
     }
 
+    if let Some(endpoint) = &config.otlp_endpoint {
+        #[cfg(feature = "otlp")]
+        layers.push(otlp_layer(endpoint)?);
+
+        #[cfg(not(feature = "otlp"))]
+        eprintln!(
+            "warning: logging.otlp_endpoint is set to {} but rcli was built without the `otlp` feature",
+            endpoint
+        );
+    }
+
     tracing_subscriber::registry()
         .with(env_filter)
         .with(layers)
         .init();
 
     Ok(())
+}
+
+/// Returns the last `count` lines of the file at `path`, reading backwards
+/// from the end in blocks so large logs are neither loaded whole nor
+/// rescanned.
+pub fn tail_lines(path: &Path, count: usize) -> Result<Vec<String>> {
+    const BLOCK: u64 = 8 * 1024;
+
+    let mut file = File::open(path)?;
+    let mut pos = file.metadata()?.len();
+    let mut blocks = Vec::new();
+    let mut newlines = 0;
+
+    // One newline more than `count` guarantees the first kept line is whole.
+    while pos > 0 && newlines <= count {
+        let read = BLOCK.min(pos);
+        pos -= read;
+        file.seek(SeekFrom::Start(pos))?;
+        let mut block = vec![0; read as usize];
+        file.read_exact(&mut block)?;
+        newlines += block.iter().filter(|&&b| b == b'\n').count();
+        blocks.push(block);
+    }
+    let buf: Vec<u8> = blocks.into_iter().rev().flatten().collect();
+
+    let text = String::from_utf8_lossy(&buf);
+    let lines: Vec<&str> = text.lines().collect();
+    Ok(lines[lines.len().saturating_sub(count)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect())
+}
+
+/// Flushes any spans still buffered for OTLP export. A no-op unless an
+/// exporter was installed by `init_logging`.
+pub fn shutdown() {
+    #[cfg(feature = "otlp")]
+    if let Some(provider) = TRACER_PROVIDER.get() {
+        if let Err(e) = provider.shutdown() {
+            eprintln!("warning: failed to flush OTLP spans: {}", e);
+        }
+    }
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_layer<S>(endpoint: &str) -> Result<Box<dyn Layer<S> + Send + Sync>>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span> + Send + Sync,
+{
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| crate::AppError::OperationFailed {
+            reason: format!("Failed to create OTLP exporter: {}", e),
+        })?;
+
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .build();
+    let tracer = provider.tracer("rcli");
+    let _ = TRACER_PROVIDER.set(provider);
+
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer).boxed())
 }
\ No newline at end of file
@@ -1,71 +1,509 @@
 use clap::Parser;
 use rust_advanced_cli::{
-    cli::{Cli, Commands, ConfigAction, OutputFormat},
-    config::AppConfig,
-    http::HttpClient,
+    batch::{self, FetchManyOptions},
+    bench,
+    cache::ResponseCache,
+    cli::{CacheAction, Cli, Commands, ConfigAction, HttpMethod, InputFormat, MetaAction, OutputFormat, SchemaAction},
+    clipboard,
+    color,
+    completions,
+    config::{self, AppConfig, AuthConfig, OutputConfig},
+    diff,
+    doctor,
+    editor,
+    har::HarRecorder,
+    http::{HttpClient, RequestOptions},
     logging,
-    storage::Storage, Result,
+    output::{
+        file_name_for_url, flatten_value, format_output, format_timestamp, render_table, render_template,
+        size_breakdown, sort_keys, truncate_value, unflatten_value, unused_path,
+    },
+    pointer,
+    runner,
+    schema::{self, SchemaRegistry},
+    signing::{BearerToken, HmacSigner, SigV4Signer},
+    storage::{decode_binary, ImportOptions, Storage, StoredItem},
+    xml,
+    AppError, Result,
 };
-use serde_json::Value;
-use std::path::PathBuf;
-use tracing::{error, info};
+use globset::Glob;
+use reqwest::header::ETAG;
+use reqwest::Method;
+use semver::Version;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::sync::Semaphore;
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() {
-    if let Err(e) = run().await {
-        error!("Application error: {}", e);
+    let cli = Cli::parse();
+    if let Err(e) = config::load_env_file(cli.env_file.as_deref()) {
+        eprintln!("Error: {}", e);
         std::process::exit(1);
     }
+    // Parse again so flags backed by RCLI_* variables see the loaded values.
+    let cli = Cli::parse();
+    let show_error_chain = cli.verbose || cli.debug_errors;
+    let output_json = cli.output_json;
+    let color = cli.color.for_stderr();
+
+    let result = match cli.deadline.map(Duration::from_secs) {
+        Some(deadline) => tokio::time::timeout(deadline, run(cli)).await.unwrap_or_else(|_| {
+            Err(AppError::OperationFailed {
+                reason: "deadline exceeded".to_string(),
+            })
+        }),
+        None => run(cli).await,
+    };
+    logging::shutdown();
+
+    if let Err(e) = result {
+        error!("Application error: {}", e);
+        if output_json {
+            let report = json!({
+                "status": "error",
+                "code": e.code(),
+                "message": e.to_string(),
+            });
+            println!("{}", report);
+        }
+        if show_error_chain {
+            print_error_chain(&e, color);
+        }
+        std::process::exit(e.exit_code());
+    }
 }
 
-async fn run() -> Result<()> {
-    let cli = Cli::parse();
-    
-    let config = if let Some(config_path) = &cli.config {
-        AppConfig::load_from_file(config_path)?
+/// Whether `command` can run with `field` at its default after the
+/// configured value failed to parse. Config inspection, completions and
+/// `doctor` never depend on the values, commands that only talk to the
+/// server do not need `storage`, and purely local storage commands do not
+/// need `server`. Everything else is strict.
+fn tolerates_fallback(command: &Commands, field: &str) -> bool {
+    let section = field.split('.').next().unwrap_or(field);
+    match command {
+        Commands::Config { .. } | Commands::Completions { .. } | Commands::Doctor { .. } => true,
+        Commands::Ping { .. } | Commands::Bench { .. } | Commands::Stream { .. } => section == "storage",
+        Commands::Get { .. }
+        | Commands::Set { .. }
+        | Commands::Inspect { .. }
+        | Commands::Size { .. }
+        | Commands::List { .. }
+        | Commands::Query { .. }
+        | Commands::Delete { .. }
+        | Commands::DeleteMany { .. }
+        | Commands::Schema { .. }
+        | Commands::Prune { .. }
+        | Commands::Touch { .. }
+        | Commands::Meta { .. }
+        | Commands::Dedupe { .. }
+        | Commands::Audit { .. }
+        | Commands::Namespaces
+        | Commands::Import { .. }
+        | Commands::Migrate => section == "server",
+        _ => false,
+    }
+}
+
+fn print_error_chain(err: &AppError, color: bool) {
+    eprintln!("{}: {}", color::red("Error", color), err);
+
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        eprintln!("  Caused by: {}", cause);
+        source = cause.source();
+    }
+
+    eprintln!("Debug: {:?}", err);
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let mut config = if cli.no_config {
+        AppConfig::default()
+    } else if cli.config.as_deref() == Some(Path::new("-")) {
+        // Read asynchronously so `--deadline` also bounds waiting on input.
+        let mut text = String::new();
+        tokio::io::stdin().read_to_string(&mut text).await?;
+        AppConfig::load_from_str(&text, cli.profile.as_deref())?
+    } else if let Some(config_path) = &cli.config {
+        AppConfig::load_from_file(config_path, cli.profile.as_deref())?
     } else {
-        AppConfig::load()?
+        AppConfig::load_layered(cli.config_dir.as_deref(), cli.profile.as_deref())?
     };
 
+    if let Some(base_url) = &cli.base_url {
+        config.server.base_url = base_url.clone();
+    }
+    if !cli.retry_on_status.is_empty() {
+        config.server.retry_on_status = cli.retry_on_status.clone();
+    }
+    if let Some(seconds) = cli.timeout_connect {
+        config.server.connect_timeout_seconds = Some(seconds);
+    }
+
+    let mut logging_config = config.logging.clone();
     if cli.verbose {
-        let mut logging_config = config.logging.clone();
         logging_config.level = "debug".to_string();
-        logging::init_logging(&logging_config)?;
-    } else {
-        logging::init_logging(&config.logging)?;
     }
+    if let Commands::Doctor { .. } | Commands::Logs { .. } = cli.command {
+        // `doctor` reports on the log file rather than failing to open it,
+        // and `logs` must not append to the file it is reading.
+        logging_config.file_path = None;
+    }
+    logging::init_logging(&logging_config, cli.color.for_stderr())?;
 
     info!("Starting rust-advanced-cli");
 
+    if !config.fallbacks.is_empty() {
+        let (tolerated, fatal): (Vec<_>, Vec<_>) =
+            config.fallbacks.iter().partition(|fallback| tolerates_fallback(&cli.command, &fallback.field));
+        if !fatal.is_empty() {
+            return Err(AppError::InvalidFields {
+                context: "Invalid configuration".to_string(),
+                issues: fatal.iter().map(|fallback| format!("{}: {}", fallback.field, fallback.reason)).collect(),
+            });
+        }
+        // `config show` and `doctor` report them themselves.
+        let listed = matches!(
+            &cli.command,
+            Commands::Config { action: ConfigAction::Show { .. } } | Commands::Doctor { .. }
+        );
+        for fallback in tolerated.into_iter().filter(|_| !listed) {
+            warn!("Config field {} is invalid, using its default: {}", fallback.field, fallback.reason);
+        }
+    }
+
+    // `doctor` runs before validation and before storage creates the data
+    // directory, so that it can report those problems itself.
+    if let Commands::Doctor { no_ping, format } = &cli.command {
+        let checks = run_doctor(&config, &cli, *no_ping).await;
+        let format = if cli.compact { Some(OutputFormat::Json) } else { format.clone() };
+        return handle_doctor(&checks, format, &config.output);
+    }
+
+    config.validate()?;
+
+    let limiter = cli.max_concurrency.map(|permits| Arc::new(Semaphore::new(permits)));
+
     let http_client = HttpClient::new(
         config.server.base_url.clone(),
         config.server.timeout_seconds,
-        config.server.retry_attempts,
-    )?;
+        cli.retries.unwrap_or(config.server.retry_attempts),
+    )?
+    .with_connect_timeout(config.server.connect_timeout_seconds.map(Duration::from_secs))?
+    .with_compression(config.server.accept_compression)?
+    .with_resolve(&config.server.resolve)?
+    .with_retry_on_status(config.server.retry_on_status.clone())
+    .with_rate_limit(config.server.max_requests_per_second)
+    .with_user_agent(config.server.user_agent.clone())
+    .with_signer(config.server.hmac.as_ref().map(HmacSigner::new).transpose()?)
+    .with_sigv4(match &config.server.auth {
+        Some(AuthConfig::Sigv4(sigv4)) => Some(SigV4Signer::new(sigv4)?),
+        _ => None,
+    })
+    .with_bearer(match &config.server.auth {
+        Some(AuthConfig::Bearer(bearer)) => Some(BearerToken::new(bearer)),
+        _ => None,
+    })
+    .with_har(
+        config
+            .server
+            .har_output
+            .clone()
+            .map(|path| HarRecorder::new(path, &config.server.redact_headers)),
+    )
+    .with_circuit_breaker(
+        config.server.circuit_breaker_threshold,
+        Duration::from_secs(config.server.circuit_breaker_cooldown_seconds),
+    )
+    .with_concurrency_limit(limiter.clone());
 
     let storage = Storage::new(
         config.storage.data_dir.clone(),
+        cli.namespace.as_deref(),
         config.storage.max_file_size_mb,
-    )?;
+    )?
+    .with_audit_log(config.storage.audit_log.clone())
+    .with_max_items(config.storage.max_items)
+    .with_indexed_fields(config.storage.indexed_fields.clone())
+    .with_normalize_keys(config.storage.normalize_keys)
+    .with_concurrency_limit(limiter)
+    .with_key_pattern(config.storage.key_pattern.as_deref())?
+    .with_follow_symlinks(config.storage.follow_symlinks)?;
+
+    // `--compact` wins over `--format` and the per-command default.
+    let compact = cli.compact;
+    let choose_format = |format: Option<OutputFormat>| if compact { Some(OutputFormat::Json) } else { format };
 
     match cli.command {
-        Commands::Fetch { url, format, output } => {
-            handle_fetch(&http_client, &url, format, output).await?;
+        Commands::Fetch { url, output: Some(output), query, resume: true, .. } => {
+            handle_download(&http_client, &url, &query, &output, cli.quiet).await?;
+        }
+        Commands::Fetch { url, format, output, output_dir, query, max_depth, schema, clipboard, template, sort_keys, flatten, cache, ndjson, .. } => {
+            let format = choose_format(format).unwrap_or_else(|| config.output.default_format.clone());
+            let options = RequestOptions { query, ndjson, ..Default::default() };
+            let file = match output_dir {
+                Some(dir) => {
+                    std::fs::create_dir_all(&dir)?;
+                    let extension = if template.is_some() { "txt" } else { format.extension() };
+                    Some(unused_path(&dir, &file_name_for_url(&url, extension)))
+                }
+                None => output,
+            };
+            let target = OutputTarget {
+                format,
+                settings: OutputConfig { sort_keys: sort_keys || config.output.sort_keys, ..config.output.clone() },
+                flatten,
+                template,
+                file,
+                clipboard,
+                quiet: cli.quiet,
+            };
+            let cache = cache.then(|| ResponseCache::new(config.cache.dir.clone())).transpose()?;
+            handle_fetch(&http_client, cache.as_ref(), &url, &options, &target, max_depth, schema).await?;
+        }
+        Commands::FetchMany { file, concurrency, resume, retry_failed, max_retries_total, max_retry_seconds } => {
+            let options = FetchManyOptions { resume, retry_failed, concurrency };
+            let http_client = http_client.with_retry_budget(max_retries_total, max_retry_seconds.map(Duration::from_secs));
+            handle_fetch_many(&http_client, &storage, &file, &options).await?;
+        }
+        Commands::Stream { url, json, max_events, max_reconnects } => {
+            handle_stream(&http_client, &url, json, max_events, max_reconnects).await?;
+        }
+        Commands::Upload { url, fields, files, format } => {
+            let format = choose_format(format);
+            let files: Vec<(String, PathBuf)> = files.into_iter().map(|(name, path)| (name, PathBuf::from(path))).collect();
+            let response = http_client.post_multipart(&url, &fields, &files).await?;
+            let format = format.unwrap_or_else(|| config.output.default_format.clone());
+            println!("{}", format_output(&response, format, &config.output)?);
+        }
+        Commands::Request { method, url, data, retry, query, format, output } => {
+            let format = choose_format(format);
+            let options = RequestOptions { query, force_retry: retry, ..Default::default() };
+            let target = OutputTarget {
+                format: format.unwrap_or_else(|| config.output.default_format.clone()),
+                settings: config.output.clone(),
+                flatten: false,
+                template: None,
+                file: output,
+                clipboard: false,
+                quiet: cli.quiet,
+            };
+            handle_request(&http_client, method, &url, data, &options, &target).await?;
+        }
+        Commands::Post { url, body, store, format } => {
+            handle_post(&http_client, &storage, &url, &body, store, choose_format(format), &config.output).await?;
+        }
+        Commands::Store { key, value, file, binary, from_url, no_provenance, input_format, max_size_mb, schema_name, canonical, unflatten, expire_at, path, edit } => {
+            let storage = match max_size_mb {
+                Some(limit) => storage.with_max_file_size_mb(limit),
+                None => storage,
+            };
+            let schema = schema_name
+                .map(|name| SchemaRegistry::new(config.storage.schema_dir.clone()).get(&name))
+                .transpose()?;
+            let options = StoreOptions {
+                is_file: file,
+                binary,
+                input_format,
+                schema,
+                canonical,
+                unflatten,
+                expire_at,
+                provenance: !no_provenance,
+                path,
+                edit,
+            };
+            handle_store(&http_client, &storage, key, value, options, from_url).await?;
+        }
+        Commands::Get { key, format, max_depth, clipboard, decode, pointer, template, sort_keys, flatten, path } => {
+            let format = choose_format(format);
+            let target = OutputTarget {
+                format: format.unwrap_or_else(|| config.output.default_format.clone()),
+                settings: OutputConfig { sort_keys: sort_keys || config.output.sort_keys, ..config.output.clone() },
+                flatten,
+                template,
+                file: None,
+                clipboard,
+                quiet: cli.quiet,
+            };
+            let item = match (path, key) {
+                (Some(path), _) => storage.get_at_path(&path).await?,
+                (None, Some(key)) => storage.get(&key).await?,
+                (None, None) => {
+                    return Err(AppError::Validation {
+                        message: "A key or --path is required".to_string(),
+                    })
+                }
+            };
+            handle_get(item, max_depth, decode, pointer, &target)?;
+        }
+        Commands::Set { key, pointer, value, create } => {
+            let value: Value = serde_json::from_str(&value)?;
+            let item = storage.set_pointer(key, &pointer, value, create).await?;
+            println!("Updated '{}' at '{}'", item.key, pointer);
+        }
+        Commands::Inspect { key, format } => {
+            handle_inspect(&storage, key, choose_format(format), &config.output).await?;
+        }
+        Commands::List { detailed, format, columns, since, until, parallel, after, limit, conditions } => {
+            let format = choose_format(format);
+            let paged = after.is_some() || limit.is_some();
+            let mut next = None;
+            let mut keys = if paged {
+                let page = storage.list_after(after.as_deref(), limit.unwrap_or(usize::MAX)).await?;
+                next = page.next;
+                page.keys
+            } else if since.is_some() || until.is_some() {
+                storage.list_in_range(since, until).await?
+            } else {
+                storage.list().await?
+            };
+            if !conditions.is_empty() {
+                let matching = storage
+                    .find_where(|item| conditions.iter().all(|condition| condition.matches(&item.value)))
+                    .await?;
+                keys.retain(|key| matching.binary_search(key).is_ok());
+            }
+            let options = ListOptions { detailed, format, columns, concurrency: parallel.unwrap_or(1), paged, next };
+            handle_list(&storage, keys, options, &config.output).await?;
+        }
+        Commands::Query { field, value, format } => {
+            let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+            let keys = storage.query_index(&field, &value).await?;
+            let options = ListOptions {
+                detailed: false,
+                format: choose_format(format),
+                columns: Vec::new(),
+                concurrency: 1,
+                paged: false,
+                next: None,
+            };
+            handle_list(&storage, keys, options, &config.output).await?;
+        }
+        Commands::Delete { key, yes } => {
+            handle_delete(&storage, key, yes).await?;
+        }
+        Commands::DeleteMany { pattern, prefix, glob, yes } => {
+            handle_delete_many(&storage, pattern, prefix, glob, yes).await?;
+        }
+        Commands::InferSchema { key_or_url } => {
+            let value = if key_or_url.starts_with("http://") || key_or_url.starts_with("https://") {
+                http_client.fetch_json(&key_or_url).await?
+            } else {
+                storage.get(&key_or_url).await?.value
+            };
+            println!("{}", format_output(&schema::infer_document(&value), OutputFormat::Pretty, &config.output)?);
+        }
+        Commands::Size { key_or_file, format } => {
+            let path = Path::new(&key_or_file);
+            let value = if path.is_file() {
+                let format = InputFormat::from_path(path).unwrap_or_default();
+                parse_input(&std::fs::read_to_string(path)?, format)?
+            } else {
+                storage.get(&key_or_file).await?.value
+            };
+            handle_size(&value, choose_format(format), &config.output)?;
+        }
+        Commands::Schema { action } => {
+            handle_schema(&SchemaRegistry::new(config.storage.schema_dir.clone()), action)?;
+        }
+        Commands::Prune { max_items, yes } => {
+            let max_items = max_items.or(config.storage.max_items).ok_or_else(|| AppError::Validation {
+                message: "Pass --max-items or set storage.max_items".to_string(),
+            })?;
+            handle_prune(&storage, max_items, yes).await?;
+        }
+        Commands::Touch { key } => {
+            let item = storage.touch(&key).await?;
+            println!("Touched '{}' (updated: {})", item.key, format_timestamp(&item.updated_at, &config.output));
+        }
+        Commands::Meta { key, action } => {
+            handle_meta(&storage, &key, action).await?;
+        }
+        Commands::Dedupe { delete, yes } => {
+            handle_dedupe(&storage, delete, yes).await?;
+        }
+        Commands::Replay { key, url, method, prefix, delay_ms } => {
+            let keys = if prefix {
+                storage.find_matching(|candidate| candidate.starts_with(&key)).await?
+            } else {
+                vec![key]
+            };
+            let delay = Duration::from_millis(delay_ms);
+            handle_replay(&http_client, &storage, keys, &url, method, delay, &config.output).await?;
+        }
+        Commands::Run { spec_file, concurrency, format } => {
+            let specs = runner::load_specs(&spec_file)?;
+            let reports = runner::execute(&http_client, &storage, &specs, concurrency).await;
+            handle_run_report(&reports, choose_format(format), &config.output)?;
+        }
+        Commands::Audit { key } => {
+            for record in storage.audit_records(key.as_deref())? {
+                println!("{}", record);
+            }
+        }
+        Commands::Namespaces => {
+            for name in storage.namespaces()? {
+                println!("{}", name);
+            }
+        }
+        Commands::Logs { lines, follow } => {
+            handle_logs(config.logging.file_path.as_deref(), lines, follow).await?;
+        }
+        Commands::Import { file, overwrite, resume, concurrency } => {
+            handle_import(&storage, &file, &ImportOptions { overwrite, resume, concurrency }).await?;
+        }
+        Commands::Migrate => {
+            handle_migrate(&storage).await?;
         }
-        Commands::Store { key, value, file } => {
-            handle_store(&storage, key, value, file).await?;
+        Commands::Ping { path } => {
+            handle_ping(&http_client, path.as_deref().unwrap_or("")).await?;
         }
-        Commands::Get { key, format } => {
-            handle_get(&storage, key, format).await?;
+        Commands::Bench { url, requests, concurrency, format } => {
+            let format = choose_format(format);
+            let report = bench::run(&http_client, &url, requests, concurrency).await;
+            match format {
+                Some(format) => println!("{}", format_output(&serde_json::to_value(&report)?, format, &config.output)?),
+                None => println!("{}", report.summary()),
+            }
+        }
+        Commands::Cache { action } => {
+            let cache = ResponseCache::new(config.cache.dir.clone())?;
+            handle_cache(&cache, &http_client, action)?;
         }
-        Commands::List { detailed } => {
-            handle_list(&storage, detailed).await?;
+        Commands::Completions { shell, install } => {
+            let shell = completions::detect_shell(shell)?;
+            if install {
+                let path = completions::install(shell)?;
+                println!("Installed {} completions to {}", shell, path.display());
+                if shell == clap_complete::Shell::Zsh {
+                    println!("Add {} to fpath in ~/.zshrc before compinit", path.parent().unwrap_or(&path).display());
+                }
+            } else {
+                io::stdout().write_all(&completions::generate(shell))?;
+            }
         }
-        Commands::Delete { key } => {
-            handle_delete(&storage, key).await?;
+        // Already handled above, before validation and before storage is opened.
+        Commands::Doctor { .. } => {}
+        Commands::Version { check } => {
+            handle_version(&http_client, &config.update.check_url, check).await?;
         }
         Commands::Config { action } => {
-            handle_config(action, &config).await?;
+            handle_config(
+                action,
+                &config,
+                cli.config.as_deref(),
+                cli.config_dir.as_deref(),
+                cli.profile.as_deref(),
+            )
+            .await?;
         }
     }
 
@@ -75,45 +513,480 @@ async fn run() -> Result<()> {
 
 async fn handle_fetch(
     client: &HttpClient,
+    cache: Option<&ResponseCache>,
+    url: &str,
+    options: &RequestOptions,
+    target: &OutputTarget,
+    max_depth: Option<usize>,
+    schema_path: Option<PathBuf>,
+) -> Result<()> {
+    let mut data = match cache {
+        Some(cache) => cache.fetch(client, url, options).await?,
+        None => client.request_json(Method::GET, url, None, options).await?,
+    };
+    if let Some(path) = schema_path {
+        schema::validate(&schema::load_schema(&path)?, &data)?;
+    }
+    if let Some(depth) = max_depth {
+        data = truncate_value(&data, depth);
+    }
+    target.write(&data)
+}
+
+async fn handle_download(
+    client: &HttpClient,
+    url: &str,
+    query: &[(String, String)],
+    path: &Path,
+    quiet: bool,
+) -> Result<()> {
+    let download = client.fetch_to_file(url, query, path, true).await?;
+    if !quiet {
+        match download.resumed_from {
+            0 => println!("Downloaded {} bytes to {}", download.written, path.display()),
+            offset => println!(
+                "Resumed {} at byte {}: {} bytes downloaded",
+                path.display(),
+                offset,
+                download.written
+            ),
+        }
+    }
+    Ok(())
+}
+
+async fn handle_stream(
+    client: &HttpClient,
+    url: &str,
+    json: bool,
+    max_events: Option<usize>,
+    max_reconnects: Option<u32>,
+) -> Result<()> {
+    let mut printed = 0;
+    client
+        .stream_events(url, max_reconnects, |event| {
+            if json {
+                match serde_json::from_str::<Value>(&event.data) {
+                    Ok(value) => println!("{}", serde_json::to_string(&value)?),
+                    Err(e) => {
+                        warn!("Skipping event whose data is not JSON: {}", e);
+                        return Ok(ControlFlow::Continue(()));
+                    }
+                }
+            } else {
+                println!("{}", event.data);
+            }
+            printed += 1;
+            Ok(if max_events.is_some_and(|max| printed >= max) {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            })
+        })
+        .await
+}
+
+async fn handle_request(
+    client: &HttpClient,
+    method: HttpMethod,
     url: &str,
+    data: Option<String>,
+    options: &RequestOptions,
+    target: &OutputTarget,
+) -> Result<()> {
+    let body: Option<Value> = data.map(|d| serde_json::from_str(&d)).transpose()?;
+    let response = client
+        .request_json(method.into(), url, body.as_ref(), options)
+        .await?;
+    target.write(&response)
+}
+
+async fn handle_post(
+    client: &HttpClient,
+    storage: &Storage,
+    url: &str,
+    body: &str,
+    store_key: Option<String>,
     format: Option<OutputFormat>,
-    output: Option<PathBuf>,
+    settings: &OutputConfig,
 ) -> Result<()> {
-    let data = client.fetch_json(url).await?;
-    let formatted = format_output(&data, format.unwrap_or(OutputFormat::Pretty))?;
+    let body: Value = serde_json::from_str(body)?;
+    let response = client.post_json(url, &body).await?;
 
-    if let Some(output_path) = output {
-        std::fs::write(&output_path, &formatted)?;
-        println!("Data saved to: {}", output_path.display());
-    } else {
-        println!("{}", formatted);
+    if let Some(key) = store_key {
+        let item = storage.store(key, response).await?;
+        println!("Stored response under '{}' with ID: {}", item.key, item.id);
+        return Ok(());
     }
 
+    let format = format.unwrap_or_else(|| settings.default_format.clone());
+    println!("{}", format_output(&response, format, settings)?);
     Ok(())
 }
 
-async fn handle_store(storage: &Storage, key: String, value: String, is_file: bool) -> Result<()> {
-    let data: Value = if is_file {
-        let file_content = std::fs::read_to_string(&value)?;
-        serde_json::from_str(&file_content)?
-    } else {
-        serde_json::from_str(&value)?
+struct OutputTarget {
+    format: OutputFormat,
+    settings: OutputConfig,
+    flatten: bool,
+    template: Option<PathBuf>,
+    file: Option<PathBuf>,
+    clipboard: bool,
+    quiet: bool,
+}
+
+impl OutputTarget {
+    fn write(&self, value: &Value) -> Result<()> {
+        let flattened;
+        let value = if self.flatten {
+            flattened = flatten_value(value)?;
+            &flattened
+        } else {
+            value
+        };
+        let sorted;
+        let value = if self.settings.sort_keys {
+            sorted = sort_keys(value);
+            &sorted
+        } else {
+            value
+        };
+
+        let formatted = match &self.template {
+            Some(path) => render_template(&std::fs::read_to_string(path)?, value)?,
+            None => format_output(value, self.format.clone(), &self.settings)?,
+        };
+
+        if let Some(output_path) = &self.file {
+            std::fs::write(output_path, &formatted)?;
+            if !self.quiet {
+                println!("Data saved to: {}", output_path.display());
+            }
+        } else if self.clipboard {
+            clipboard::copy(&formatted)?;
+            if !self.quiet {
+                println!("Copied {} bytes to clipboard", formatted.len());
+            }
+        } else {
+            println!("{}", formatted);
+        }
+
+        Ok(())
+    }
+}
+
+struct StoreOptions {
+    is_file: bool,
+    binary: bool,
+    input_format: Option<InputFormat>,
+    schema: Option<Value>,
+    canonical: bool,
+    unflatten: bool,
+    expire_at: Option<chrono::DateTime<chrono::Utc>>,
+    provenance: bool,
+    path: Option<PathBuf>,
+    edit: bool,
+}
+
+async fn handle_store(
+    client: &HttpClient,
+    storage: &Storage,
+    key: String,
+    value: Option<String>,
+    options: StoreOptions,
+    from_url: Option<String>,
+) -> Result<()> {
+    if let Some(at) = options.expire_at.filter(|at| *at <= chrono::Utc::now()) {
+        return Err(AppError::Validation {
+            message: format!("--expire-at {} is already in the past", at.to_rfc3339()),
+        });
+    }
+
+    let mut provenance = Vec::new();
+    let data: Value = match (from_url, value) {
+        (Some(url), _) => {
+            let fetched_at = chrono::Utc::now();
+            let fetched = client
+                .request_with_meta::<Value>(Method::GET, &url, None, &RequestOptions::default())
+                .await?;
+            if options.provenance {
+                let etag = fetched.headers.get(ETAG).and_then(|etag| etag.to_str().ok());
+                provenance = vec![
+                    ("source_url".to_string(), Some(client.request_url(&url, &[])?)),
+                    ("fetched_at".to_string(), Some(fetched_at.to_rfc3339())),
+                    ("status".to_string(), Some(fetched.status.as_u16().to_string())),
+                    ("etag".to_string(), etag.map(str::to_string)),
+                ];
+            }
+            fetched.value
+        }
+        (None, Some(path)) if options.binary => {
+            let bytes = std::fs::read(&path)?;
+            let item = storage
+                .store_binary_with(key, &bytes, |item| item.expires_at = options.expire_at)
+                .await?;
+            println!("Stored {} bytes with ID: {}", bytes.len(), item.id);
+            return Ok(());
+        }
+        (None, Some(path)) if options.is_file => {
+            let format = options
+                .input_format
+                .or_else(|| InputFormat::from_path(Path::new(&path)))
+                .unwrap_or_default();
+            parse_input(&std::fs::read_to_string(&path)?, format)?
+        }
+        (None, Some(value)) => parse_input(&value, options.input_format.unwrap_or_default())?,
+        (None, None) if options.edit => {
+            let existing = match &options.path {
+                Some(path) => storage.get_at_path(path).await,
+                None => storage.get(&key).await,
+            };
+            let initial = match existing {
+                Ok(item) => item.value,
+                Err(AppError::NotFound { .. }) => json!({}),
+                Err(e) => return Err(e),
+            };
+            edit_value(&initial)?
+        }
+        (None, None) => {
+            return Err(AppError::Validation {
+                message: "A value or --from-url is required".to_string(),
+            })
+        }
     };
+    let data = if options.unflatten { unflatten_value(&data)? } else { data };
+    if let Some(schema) = &options.schema {
+        schema::validate(schema, &data)?;
+    }
+    let data = if options.canonical { sort_keys(&data) } else { data };
+
+    if let Some(path) = &options.path {
+        let item = storage.store_at_path(path, key, data).await?;
+        println!("Stored item with ID: {} at {}", item.id, path.display());
+        return Ok(());
+    }
 
-    let item = storage.store(key, data).await?;
+    let item = storage
+        .store_with(key, data, |item| {
+            item.expires_at = options.expire_at;
+            for (name, value) in provenance {
+                match value {
+                    Some(value) => item.metadata.insert(name, value),
+                    None => item.metadata.remove(&name),
+                };
+            }
+        })
+        .await?;
     println!("Stored item with ID: {}", item.id);
     Ok(())
 }
 
-async fn handle_get(storage: &Storage, key: String, format: Option<OutputFormat>) -> Result<()> {
+/// Opens the editor on `initial` until the result parses as JSON. When it
+/// does not, asks whether to edit again (keeping the invalid text), and fails
+/// with the parse error if the answer is no or stdin is not a terminal.
+fn edit_value(initial: &Value) -> Result<Value> {
+    let editor = editor::editor_command();
+    let mut text = serde_json::to_string_pretty(initial)?;
+    loop {
+        text = editor::edit_text(&editor, &text, "json")?;
+        match serde_json::from_str(&text) {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                let error = AppError::Validation {
+                    message: format!("Edited value is not valid JSON: {}", e),
+                };
+                if !io::stdin().is_terminal() {
+                    return Err(error);
+                }
+                eprintln!("{}", error);
+                if !confirm("Edit again?")? {
+                    return Err(error);
+                }
+            }
+        }
+    }
+}
+
+fn handle_size(value: &Value, format: Option<OutputFormat>, settings: &OutputConfig) -> Result<()> {
+    let breakdown = size_breakdown(value);
+    if let Some(format) = format {
+        println!("{}", format_output(&serde_json::to_value(&breakdown)?, format, settings)?);
+        return Ok(());
+    }
+
+    println!("Total: {} bytes", breakdown.total_bytes);
+    if breakdown.parts.is_empty() {
+        return Ok(());
+    }
+    let share = |bytes: usize| format!("{:.1}%", bytes as f64 * 100.0 / breakdown.total_bytes as f64);
+    let headers = [if value.is_array() { "index" } else { "field" }, "bytes", "share"].map(String::from);
+    let rows: Vec<Vec<String>> = breakdown
+        .parts
+        .iter()
+        .map(|part| vec![part.name.clone(), part.bytes.to_string(), share(part.bytes)])
+        .chain(std::iter::once(vec![
+            "(overhead)".to_string(),
+            breakdown.overhead_bytes.to_string(),
+            share(breakdown.overhead_bytes),
+        ]))
+        .collect();
+    println!("{}", render_table(&headers, &rows));
+    Ok(())
+}
+
+fn handle_schema(registry: &SchemaRegistry, action: SchemaAction) -> Result<()> {
+    match action {
+        SchemaAction::Add { name, file, force } => {
+            let path = registry.add(&name, &file, force)?;
+            println!("Registered schema '{}' at {}", name, path.display());
+        }
+        SchemaAction::List => {
+            for name in registry.list()? {
+                println!("{}", name);
+            }
+        }
+        SchemaAction::Remove { name } => {
+            registry.remove(&name)?;
+            println!("Removed schema '{}'", name);
+        }
+    }
+    Ok(())
+}
+
+fn parse_input(text: &str, format: InputFormat) -> Result<Value> {
+    match format {
+        InputFormat::Json => Ok(serde_json::from_str(text)?),
+        InputFormat::Yaml => Ok(serde_yaml::from_str(text)?),
+        InputFormat::Toml => toml::from_str(text).map_err(|e| AppError::Validation {
+            message: format!("TOML parsing error: {}", e),
+        }),
+        InputFormat::Xml => xml::from_xml(text),
+    }
+}
+
+fn handle_get(
+    item: StoredItem,
+    max_depth: Option<usize>,
+    decode: bool,
+    pointer: Option<String>,
+    target: &OutputTarget,
+) -> Result<()> {
+    if decode {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&decode_binary(&item.value)?)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+
+    let mut value = item.value;
+    if let Some(pointer) = pointer {
+        value = value.pointer(&pointer).cloned().ok_or_else(|| AppError::NotFound {
+            resource: format!("pointer '{}' in key '{}'", pointer, item.key),
+        })?;
+    }
+    if let Some(depth) = max_depth {
+        value = truncate_value(&value, depth);
+    }
+    target.write(&value)
+}
+
+async fn handle_inspect(
+    storage: &Storage,
+    key: String,
+    format: Option<OutputFormat>,
+    settings: &OutputConfig,
+) -> Result<()> {
     let item = storage.get(&key).await?;
-    let formatted = format_output(&item.value, format.unwrap_or(OutputFormat::Pretty))?;
-    println!("{}", formatted);
+    let format = format.unwrap_or_else(|| settings.default_format.clone());
+    let mut value = serde_json::to_value(&item)?;
+
+    // Machine-readable formats keep RFC 3339; the others follow output.time_format.
+    if matches!(format, OutputFormat::Pretty | OutputFormat::Yaml) {
+        value["created_at"] = Value::String(format_timestamp(&item.created_at, settings));
+        value["updated_at"] = Value::String(format_timestamp(&item.updated_at, settings));
+    }
+
+    println!("{}", format_output(&value, format, settings)?);
     Ok(())
 }
 
-async fn handle_list(storage: &Storage, detailed: bool) -> Result<()> {
-    let keys = storage.list().await?;
+struct ListOptions {
+    detailed: bool,
+    format: Option<OutputFormat>,
+    columns: Vec<String>,
+    concurrency: usize,
+    /// Formatted output becomes `{"items": [...], "next": ...}`.
+    paged: bool,
+    next: Option<String>,
+}
+
+async fn handle_list(
+    storage: &Storage,
+    keys: Vec<String>,
+    options: ListOptions,
+    settings: &OutputConfig,
+) -> Result<()> {
+    let ListOptions { detailed, format, columns, concurrency, paged, next } = options;
+    let print_entries = |entries: Vec<Value>, format: OutputFormat| -> Result<()> {
+        let output = if paged {
+            json!({ "items": entries, "next": next })
+        } else {
+            Value::Array(entries)
+        };
+        println!("{}", format_output(&output, format, settings)?);
+        Ok(())
+    };
+    let items = if detailed || !columns.is_empty() {
+        storage.get_many(&keys, concurrency).await
+    } else {
+        Vec::new()
+    };
+
+    if !columns.is_empty() {
+        let mut rows = Vec::with_capacity(keys.len());
+        for (key, item) in keys.iter().zip(items) {
+            let item = item.ok();
+            let mut row = Vec::with_capacity(columns.len());
+            for column in &columns {
+                row.push(column_value(storage, key, item.as_ref(), column, settings).await);
+            }
+            rows.push(row);
+        }
+
+        if let Some(format) = format {
+            let entries = rows
+                .into_iter()
+                .map(|row| columns.iter().cloned().zip(row.into_iter().map(Value::String)).collect())
+                .map(Value::Object)
+                .collect();
+            print_entries(entries, format)?;
+        } else {
+            println!("{}", render_table(&columns, &rows));
+            print_next_cursor(next.as_deref());
+        }
+        return Ok(());
+    }
+
+    if let Some(format) = format {
+        let entries = if detailed {
+            keys.into_iter()
+                .zip(items)
+                .map(|(key, item)| match item {
+                    Ok(item) => json!({
+                        "key": key,
+                        "id": item.id,
+                        "created_at": item.created_at,
+                        "updated_at": item.updated_at,
+                        "metadata": item.metadata,
+                    }),
+                    Err(e) => json!({ "key": key, "error": e.to_string() }),
+                })
+                .collect()
+        } else {
+            keys.into_iter().map(Value::String).collect()
+        };
+
+        return print_entries(entries, format);
+    }
     
     if detailed {
         let storage_info = storage.get_storage_info()?;
@@ -129,13 +1002,14 @@ async fn handle_list(storage: &Storage, detailed: bool) -> Result<()> {
         println!("No stored items found.");
     } else {
         println!("Stored keys ({}):", keys.len());
+        let mut items = items.into_iter();
         for key in keys {
             if detailed {
-                if let Ok(item) = storage.get(&key).await {
+                if let Some(Ok(item)) = items.next() {
                     println!("  {} (created: {}, updated: {})", 
                         key, 
-                        item.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
-                        item.updated_at.format("%Y-%m-%d %H:%M:%S UTC")
+                        format_timestamp(&item.created_at, settings),
+                        format_timestamp(&item.updated_at, settings)
                     );
                 } else {
                     println!("  {} (error reading metadata)", key);
@@ -145,34 +1019,631 @@ async fn handle_list(storage: &Storage, detailed: bool) -> Result<()> {
             }
         }
     }
+    print_next_cursor(next.as_deref());
     Ok(())
 }
 
-async fn handle_delete(storage: &Storage, key: String) -> Result<()> {
+fn print_next_cursor(next: Option<&str>) {
+    if let Some(next) = next {
+        println!("More keys follow; continue with --after {}", next);
+    }
+}
+
+async fn column_value(
+    storage: &Storage,
+    key: &str,
+    item: Option<&StoredItem>,
+    column: &str,
+    settings: &OutputConfig,
+) -> String {
+    let value = match column {
+        "key" => Some(key.to_string()),
+        "size" => storage.item_size(key).await.ok().map(|size| size.to_string()),
+        "id" => item.map(|item| item.id.clone()),
+        "created" => item.map(|item| format_timestamp(&item.created_at, settings)),
+        "updated" => item.map(|item| format_timestamp(&item.updated_at, settings)),
+        tag => item.and_then(|item| item.metadata.get(tag).cloned()),
+    };
+    value.unwrap_or_else(|| "-".to_string())
+}
+
+async fn handle_delete(storage: &Storage, key: String, yes: bool) -> Result<()> {
+    if !storage.exists(&key).await {
+        return Err(AppError::NotFound {
+            resource: format!("key '{}'", key),
+        });
+    }
+    if !yes && !confirm(&format!("Delete '{}'?", key))? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
     storage.delete(&key).await?;
     println!("Deleted key: {}", key);
     Ok(())
 }
 
-async fn handle_config(action: ConfigAction, config: &AppConfig) -> Result<()> {
+async fn handle_delete_many(storage: &Storage, pattern: String, prefix: bool, glob: bool, yes: bool) -> Result<()> {
+    let matcher: Box<dyn Fn(&str) -> bool> = match (prefix, glob) {
+        (true, false) => Box::new(move |key: &str| key.starts_with(&pattern)),
+        (false, true) => {
+            let glob = Glob::new(&pattern)
+                .map_err(|e| AppError::Validation {
+                    message: format!("Invalid glob pattern '{}': {}", pattern, e),
+                })?
+                .compile_matcher();
+            Box::new(move |key: &str| glob.is_match(key))
+        }
+        _ => {
+            return Err(AppError::Validation {
+                message: "Pass exactly one of --prefix or --glob".to_string(),
+            })
+        }
+    };
+
+    let keys = storage.find_matching(&matcher).await?;
+    if keys.is_empty() {
+        println!("No matching keys found.");
+        return Ok(());
+    }
+
+    println!("Matching keys ({}):", keys.len());
+    for key in &keys {
+        println!("  {}", key);
+    }
+
+    if !yes && !confirm(&format!("Delete {} keys?", keys.len()))? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let deleted = storage.delete_matching(&matcher).await?;
+    println!("Deleted {} keys", deleted);
+    Ok(())
+}
+
+async fn handle_prune(storage: &Storage, max_items: usize, yes: bool) -> Result<()> {
+    let keys = storage.oldest_beyond(max_items).await?;
+    if keys.is_empty() {
+        println!("Nothing to prune ({} items or fewer stored).", max_items);
+        return Ok(());
+    }
+
+    println!("Oldest keys over the limit of {} ({}):", max_items, keys.len());
+    for key in &keys {
+        println!("  {}", key);
+    }
+
+    if !yes && !confirm(&format!("Delete {} keys?", keys.len()))? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    for key in &keys {
+        storage.delete(key).await?;
+    }
+    println!("Pruned {} keys", keys.len());
+    Ok(())
+}
+
+fn handle_cache(cache: &ResponseCache, client: &HttpClient, action: CacheAction) -> Result<()> {
     match action {
-        ConfigAction::Init { output } => {
-            let output_path = output.unwrap_or_else(|| PathBuf::from("config.yaml"));
-            config.save_to_file(&output_path)?;
-            println!("Configuration saved to: {}", output_path.display());
+        CacheAction::List => {
+            let entries = cache.entries()?;
+            if entries.is_empty() {
+                println!("No cached responses");
+                return Ok(());
+            }
+            let now = chrono::Utc::now();
+            let headers = ["URL", "AGE", "SIZE", "ETAG"].map(String::from);
+            let rows: Vec<Vec<String>> = entries
+                .into_iter()
+                .map(|entry| {
+                    let age = (now - entry.fetched_at).to_std().unwrap_or_default();
+                    vec![
+                        entry.url,
+                        humantime::format_duration(Duration::from_secs(age.as_secs())).to_string(),
+                        format!("{} B", entry.body.len()),
+                        entry.etag.unwrap_or_else(|| "-".to_string()),
+                    ]
+                })
+                .collect();
+            println!("{}", render_table(&headers, &rows));
+        }
+        CacheAction::Clear { url: Some(url) } => {
+            let full_url = client.request_url(&url, &[])?;
+            if !cache.remove(&full_url)? {
+                return Err(AppError::NotFound {
+                    resource: format!("cached response for {}", full_url),
+                });
+            }
+            println!("Removed cached response for {}", full_url);
+        }
+        CacheAction::Clear { url: None } => {
+            println!("Removed {} cached responses", cache.clear()?);
+        }
+    }
+    Ok(())
+}
+
+async fn handle_meta(storage: &Storage, key: &str, action: MetaAction) -> Result<()> {
+    match action {
+        MetaAction::Set { name, value } => {
+            storage.set_metadata(key, name.clone(), value).await?;
+            println!("Set metadata '{}' on '{}'", name, key);
+        }
+        MetaAction::Get { name: Some(name) } => {
+            let metadata = storage.get_metadata(key).await?;
+            let value = metadata.get(&name).ok_or_else(|| AppError::NotFound {
+                resource: format!("metadata '{}' on key '{}'", name, key),
+            })?;
+            println!("{}", value);
+        }
+        MetaAction::Get { name: None } => {
+            let metadata = storage.get_metadata(key).await?;
+            println!("{}", serde_json::to_string_pretty(&serde_json::to_value(metadata)?)?);
+        }
+        MetaAction::Delete { name } => {
+            storage.delete_metadata(key, &name).await?;
+            println!("Deleted metadata '{}' from '{}'", name, key);
+        }
+        MetaAction::List => {
+            let mut names: Vec<String> = storage.get_metadata(key).await?.into_keys().collect();
+            names.sort();
+            for name in names {
+                println!("{}", name);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_dedupe(storage: &Storage, delete: bool, yes: bool) -> Result<()> {
+    let groups = storage.find_duplicates().await?;
+    if groups.is_empty() {
+        println!("No duplicate values found.");
+        return Ok(());
+    }
+
+    println!("Duplicate groups ({}):", groups.len());
+    for group in &groups {
+        let keys: Vec<&str> = group.iter().map(|item| item.key.as_str()).collect();
+        println!("  {}", keys.join(", "));
+    }
+
+    if !delete {
+        return Ok(());
+    }
+
+    let redundant: Vec<&str> = groups
+        .iter()
+        .flat_map(|group| group.iter().skip(1).map(|item| item.key.as_str()))
+        .collect();
+    if !yes && !confirm(&format!("Delete {} duplicate keys, keeping the oldest of each group?", redundant.len()))? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    for key in &redundant {
+        storage.delete(key).await?;
+    }
+    println!("Deleted {} duplicate keys", redundant.len());
+    Ok(())
+}
+
+async fn handle_replay(
+    client: &HttpClient,
+    storage: &Storage,
+    keys: Vec<String>,
+    url: &str,
+    method: HttpMethod,
+    delay: Duration,
+    settings: &OutputConfig,
+) -> Result<()> {
+    if keys.is_empty() {
+        println!("No matching keys found.");
+        return Ok(());
+    }
+
+    for (index, key) in keys.iter().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(delay).await;
+        }
+
+        let item = storage.get(key).await?;
+        let response = client
+            .request_json(method.into(), url, Some(&item.value), &RequestOptions::default())
+            .await?;
+        if keys.len() > 1 {
+            println!("==> {}", key);
+        }
+        println!("{}", format_output(&response, OutputFormat::Pretty, settings)?);
+    }
+
+    Ok(())
+}
+
+fn handle_run_report(reports: &[runner::StepReport], format: Option<OutputFormat>, settings: &OutputConfig) -> Result<()> {
+    match format {
+        Some(format) => println!("{}", format_output(&serde_json::to_value(reports)?, format, settings)?),
+        None => {
+            for report in reports {
+                let outcome = match (&report.error, &report.stored) {
+                    (Some(error), _) => format!("failed: {}", error),
+                    (None, Some(key)) => format!("ok, stored as '{}'", key),
+                    (None, None) => "ok".to_string(),
+                };
+                println!("[{}/{}] {} {} {}", report.step, reports.len(), report.method, report.url, outcome);
+            }
+        }
+    }
+
+    let failed = reports.iter().filter(|report| !report.ok).count();
+    if failed > 0 {
+        return Err(AppError::OperationFailed {
+            reason: format!("{} of {} steps failed", failed, reports.len()),
+        });
+    }
+    Ok(())
+}
+
+/// Asks a y/N question on stdin. A stdin that is not a terminal (as in
+/// scripts and CI) or runs out of input is an error rather than a silent
+/// "no", so a missing `--yes` is reported.
+fn confirm(prompt: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Err(AppError::Validation {
+            message: "Confirmation required but stdin is not a terminal; use --yes to skip the prompt".to_string(),
+        });
+    }
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer)? == 0 {
+        println!();
+        return Err(AppError::Validation {
+            message: "Confirmation required but no answer was given on stdin; pass --yes to skip the prompt".to_string(),
+        });
+    }
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+async fn handle_import(storage: &Storage, file: &Path, options: &ImportOptions) -> Result<()> {
+    let report = storage.import(file, options).await?;
+    println!(
+        "Imported {} items ({} existing skipped, {} already done)",
+        report.imported, report.skipped_existing, report.already_done
+    );
+
+    if !report.failed.is_empty() {
+        println!("Could not import {} items:", report.failed.len());
+        for (key, reason) in &report.failed {
+            println!("  {}: {}", key, reason);
+        }
+        return Err(AppError::OperationFailed {
+            reason: format!("{} items could not be imported; rerun with --resume to retry them", report.failed.len()),
+        });
+    }
+    Ok(())
+}
+
+async fn handle_fetch_many(
+    client: &HttpClient,
+    storage: &Storage,
+    file: &Path,
+    options: &FetchManyOptions,
+) -> Result<()> {
+    let report = batch::fetch_many(client, storage, file, options).await?;
+    println!("Fetched {} URLs ({} already done)", report.fetched, report.already_done);
+
+    if !report.failed.is_empty() {
+        println!("Could not fetch {} URLs:", report.failed.len());
+        for (url, reason) in &report.failed {
+            println!("  {}: {}", url, reason);
+        }
+        return Err(AppError::OperationFailed {
+            reason: format!(
+                "{} URLs could not be fetched (listed in {}); rerun with --retry-failed to retry them",
+                report.failed.len(),
+                batch::failed_path(file).display()
+            ),
+        });
+    }
+    Ok(())
+}
+
+async fn handle_migrate(storage: &Storage) -> Result<()> {
+    let report = storage.migrate().await?;
+    println!("Migrated {} items ({} already current)", report.migrated, report.up_to_date);
+
+    if !report.failed.is_empty() {
+        println!("Could not migrate {} files:", report.failed.len());
+        for path in &report.failed {
+            println!("  {}", path.display());
+        }
+        return Err(AppError::OperationFailed {
+            reason: format!("{} files could not be migrated", report.failed.len()),
+        });
+    }
+
+    Ok(())
+}
+
+async fn handle_logs(path: Option<&Path>, lines: usize, follow: bool) -> Result<()> {
+    let path = path.ok_or_else(|| AppError::Validation {
+        message: "No log file configured; set logging.file_path (or RCLI_LOGGING__FILE_PATH)".to_string(),
+    })?;
+    if !path.exists() {
+        return Err(AppError::NotFound {
+            resource: format!("log file {}", path.display()),
+        });
+    }
+
+    for line in logging::tail_lines(path, lines)? {
+        println!("{}", line);
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    let mut offset = std::fs::metadata(path)?.len();
+    let mut stdout = io::stdout();
+    loop {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let len = std::fs::metadata(path)?.len();
+        if len < offset {
+            // The file was truncated or rotated; start over from the top.
+            offset = 0;
         }
-        ConfigAction::Show => {
-            let yaml = serde_yaml::to_string(config)?;
-            println!("{}", yaml);
+        if len > offset {
+            let mut file = std::fs::File::open(path)?;
+            file.seek(SeekFrom::Start(offset))?;
+            let copied = io::copy(&mut file.take(len - offset), &mut stdout)?;
+            stdout.flush()?;
+            offset += copied;
         }
     }
+}
+
+async fn handle_ping(client: &HttpClient, path: &str) -> Result<()> {
+    let response = client.fetch_with_meta(path).await?;
+    println!("Reachable: HTTP {} in {} ms", response.status, response.elapsed.as_millis());
+
+    if !response.status.is_success() {
+        return Err(AppError::OperationFailed {
+            reason: format!("Server responded with HTTP {}", response.status),
+        });
+    }
+
     Ok(())
 }
 
-fn format_output(data: &Value, format: OutputFormat) -> Result<String> {
+async fn run_doctor(config: &AppConfig, cli: &Cli, no_ping: bool) -> Vec<doctor::Check> {
+    let mut checks = vec![
+        doctor::config_source(cli.config.as_deref(), cli.no_config, cli.config_dir.as_deref()),
+        doctor::config_values(config),
+        doctor::data_dir(&config.storage.data_dir),
+        doctor::disk_space(&config.storage),
+        doctor::log_file(config.logging.file_path.as_deref()),
+    ];
+    if !no_ping {
+        let connect_timeout = config.server.connect_timeout_seconds.filter(|seconds| *seconds > 0);
+        let client = HttpClient::new(config.server.base_url.clone(), config.server.timeout_seconds.max(1), 1)
+            .and_then(|client| client.with_connect_timeout(connect_timeout.map(Duration::from_secs)))
+            .map(|client| client.with_user_agent(config.server.user_agent.clone()));
+        checks.push(match client {
+            Ok(client) => doctor::server(&client).await,
+            Err(e) => doctor::Check {
+                name: "server",
+                status: doctor::Status::Fail,
+                detail: e.to_string(),
+            },
+        });
+    }
+    checks
+}
+
+fn handle_doctor(checks: &[doctor::Check], format: Option<OutputFormat>, settings: &OutputConfig) -> Result<()> {
     match format {
-        OutputFormat::Json => Ok(serde_json::to_string(data)?),
-        OutputFormat::Yaml => Ok(serde_yaml::to_string(data)?),
-        OutputFormat::Pretty => Ok(serde_json::to_string_pretty(data)?),
+        Some(format) => println!("{}", format_output(&serde_json::to_value(checks)?, format, settings)?),
+        None => {
+            for check in checks {
+                println!("[{}] {:<15} {}", check.status, check.name, check.detail);
+            }
+        }
+    }
+
+    let failed = checks.iter().filter(|check| check.status == doctor::Status::Fail).count();
+    if failed > 0 {
+        return Err(AppError::OperationFailed {
+            reason: format!("{} of {} doctor checks failed", failed, checks.len()),
+        });
+    }
+    Ok(())
+}
+
+async fn handle_version(client: &HttpClient, check_url: &str, check: bool) -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    println!("rcli {}", current);
+
+    if !check {
+        return Ok(());
+    }
+
+    match fetch_latest_version(client, check_url).await {
+        Ok(latest) => {
+            let current = Version::parse(current).map_err(|e| AppError::OperationFailed {
+                reason: format!("Invalid package version: {}", e),
+            })?;
+            if latest > current {
+                println!("Update available: {} -> {}", current, latest);
+            } else {
+                println!("rcli is up to date (latest release: {})", latest);
+            }
+        }
+        Err(e) => println!("Could not check for updates: {}", e),
     }
+
+    Ok(())
+}
+
+async fn fetch_latest_version(client: &HttpClient, check_url: &str) -> Result<Version> {
+    let release = client.fetch_json(check_url).await?;
+    let tag = release
+        .get("tag_name")
+        .or_else(|| release.get("version"))
+        .and_then(Value::as_str)
+        .ok_or_else(|| AppError::Validation {
+            message: "release response has no 'tag_name' or 'version' field".to_string(),
+        })?;
+
+    Version::parse(tag.trim_start_matches('v')).map_err(|e| AppError::Validation {
+        message: format!("Invalid release version '{}': {}", tag, e),
+    })
+}
+
+/// Shows what writing `new` to `path` would change, or the whole document
+/// when the file does not exist yet.
+fn print_config_dry_run(path: &Path, new: &Value) -> Result<()> {
+    let Some(current) = AppConfig::read_file_value(path)? else {
+        println!("Dry run: {} would be created with:", path.display());
+        print!("{}", serde_yaml::to_string(new)?);
+        return Ok(());
+    };
+    let changes = diff::json_diff(&current, new);
+    if changes.is_empty() {
+        println!("Dry run: {} would not change", path.display());
+    } else {
+        println!("Dry run: {} would change:", path.display());
+        for change in changes {
+            println!("  {}", change);
+        }
+    }
+    Ok(())
+}
+
+async fn handle_config(
+    action: ConfigAction,
+    config: &AppConfig,
+    config_file: Option<&Path>,
+    config_dir: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<()> {
+    match action {
+        ConfigAction::Init { output, dry_run } => {
+            let output_path = output.unwrap_or_else(|| PathBuf::from("config.yaml"));
+            if dry_run {
+                let new = serde_json::to_value(config)?;
+                print_config_dry_run(&output_path, &new)?;
+                return Ok(());
+            }
+            config.save_to_file(&output_path)?;
+            println!("Configuration saved to: {}", output_path.display());
+        }
+        ConfigAction::Set { key, value, dry_run } => {
+            let path = AppConfig::edit_target(config_file, config_dir)?;
+            let mut new = AppConfig::read_file_value(&path)?.unwrap_or(Value::Null);
+            let value = serde_yaml::from_str(&value).unwrap_or(Value::String(value));
+            let path_pointer: String = key
+                .split('.')
+                .map(|part| format!("/{}", part.replace('~', "~0").replace('/', "~1")))
+                .collect();
+            pointer::set(&mut new, &path_pointer, value)?;
+            AppConfig::check_file_value(&new)?;
+
+            if dry_run {
+                print_config_dry_run(&path, &new)?;
+                return Ok(());
+            }
+            AppConfig::write_file_value(&path, &new)?;
+            println!("Set {} in {}", key, path.display());
+        }
+        ConfigAction::Edit { dry_run } => {
+            let path = AppConfig::edit_target(config_file, config_dir)?;
+            let initial = match AppConfig::read_file_value(&path)? {
+                Some(_) => std::fs::read_to_string(&path)?,
+                None if path.extension().is_some_and(|ext| ext == "json") => {
+                    serde_json::to_string_pretty(&AppConfig::default())?
+                }
+                None => serde_yaml::to_string(&AppConfig::default())?,
+            };
+            let extension = if path.extension().is_some_and(|ext| ext == "json") { "json" } else { "yaml" };
+            let edited = editor::edit_text(&editor::editor_command(), &initial, extension)?;
+            if edited == initial {
+                println!("No changes to {}", path.display());
+                return Ok(());
+            }
+            let new: Value = if edited.trim().is_empty() {
+                json!({})
+            } else {
+                serde_yaml::from_str(&edited)?
+            };
+            AppConfig::check_file_value(&new)?;
+
+            if dry_run {
+                print_config_dry_run(&path, &new)?;
+                return Ok(());
+            }
+            AppConfig::write_file_text(&path, &edited)?;
+            println!("Saved {}", path.display());
+        }
+        ConfigAction::Show { format, redacted } => {
+            println!("{}", config.render(format, redacted)?);
+            if !config.fallbacks.is_empty() {
+                eprintln!("These fields are invalid and fell back to their defaults:");
+                for fallback in &config.fallbacks {
+                    eprintln!("  {}: {}", fallback.field, fallback.reason);
+                }
+            }
+        }
+        ConfigAction::Path => {
+            if config_file == Some(Path::new("-")) {
+                println!("Configuration loaded exclusively from: stdin");
+                return Ok(());
+            }
+            if let Some(path) = config_file {
+                println!("Configuration loaded exclusively from: {}", path.display());
+                return Ok(());
+            }
+
+            println!("Configuration sources (lowest to highest precedence):");
+            println!("  1. defaults        built-in");
+            let sources = AppConfig::sources(config_dir);
+            for (i, source) in sources.iter().enumerate() {
+                let status = match &source.path {
+                    Some(path) => format!("{} (loaded)", path.display()),
+                    None => format!("{} (not found)", source.location),
+                };
+                println!("  {}. {:<15} {}", i + 2, source.name, status);
+            }
+            let env_count = std::env::vars().filter(|(k, _)| k.starts_with("RCLI_")).count();
+            println!("  {}. {:<15} RCLI_* variables ({} set)", sources.len() + 2, "environment", env_count);
+        }
+        ConfigAction::Profiles => {
+            let names = config.profile_names();
+            if names.is_empty() {
+                println!("No profiles defined.");
+                return Ok(());
+            }
+
+            let active = profile.or(config.default_profile.as_deref());
+            println!("Profiles:");
+            for name in names {
+                let mut tags = Vec::new();
+                if config.default_profile.as_deref() == Some(name) {
+                    tags.push("default");
+                }
+                if active == Some(name) {
+                    tags.push("active");
+                }
+                if tags.is_empty() {
+                    println!("  {}", name);
+                } else {
+                    println!("  {} ({})", name, tags.join(", "));
+                }
+            }
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,393 @@
+use crate::config::{DisplayTimezone, OutputConfig};
+use crate::{AppError, Result};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use serde_json::ser::PrettyFormatter;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(clap::ValueEnum, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+    #[default]
+    Pretty,
+    Ndjson,
+    Xml,
+}
+
+impl OutputFormat {
+    /// File extension for output written in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Json | OutputFormat::Pretty => "json",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Xml => "xml",
+        }
+    }
+}
+
+pub fn format_output(data: &Value, format: OutputFormat, settings: &OutputConfig) -> Result<String> {
+    match format {
+        OutputFormat::Json => Ok(serde_json::to_string(data)?),
+        OutputFormat::Yaml => Ok(serde_yaml::to_string(data)?),
+        OutputFormat::Pretty => format_pretty(data, settings),
+        OutputFormat::Ndjson => format_ndjson(data),
+        OutputFormat::Xml => crate::xml::to_xml(data),
+    }
+}
+
+fn format_pretty(data: &Value, settings: &OutputConfig) -> Result<String> {
+    let indent = settings.indent.as_string();
+    let mut buffer = Vec::new();
+    let mut serializer =
+        serde_json::Serializer::with_formatter(&mut buffer, PrettyFormatter::with_indent(indent.as_bytes()));
+    data.serialize(&mut serializer)?;
+    Ok(String::from_utf8(buffer).expect("serde_json writes valid UTF-8"))
+}
+
+/// Renders `data` through a Handlebars template. Strict mode is on, so a
+/// field missing from the data is an error rather than an empty string.
+pub fn render_template(template: &str, data: &Value) -> Result<String> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars
+        .render_template(template, data)
+        .map_err(|e| AppError::Validation {
+            message: format!("Template error: {}", e),
+        })
+}
+
+/// Renders a timestamp with `output.time_format` in `output.timezone`,
+/// falling back to RFC 3339 if the format string is invalid.
+pub fn format_timestamp(timestamp: &DateTime<Utc>, settings: &OutputConfig) -> String {
+    match settings.timezone {
+        DisplayTimezone::Utc => format_timestamp_in(timestamp, &settings.time_format, &Utc),
+        DisplayTimezone::Local => format_timestamp_in(timestamp, &settings.time_format, &Local),
+    }
+}
+
+/// Renders a timestamp with `time_format` in `timezone`, falling back to
+/// RFC 3339 if the format string is invalid.
+pub fn format_timestamp_in<Tz>(timestamp: &DateTime<Utc>, time_format: &str, timezone: &Tz) -> String
+where
+    Tz: TimeZone,
+    Tz::Offset: Display,
+{
+    let mut rendered = String::new();
+    match write!(rendered, "{}", timestamp.with_timezone(timezone).format(time_format)) {
+        Ok(()) => rendered,
+        Err(_) => timestamp.to_rfc3339(),
+    }
+}
+
+/// One compact JSON document per line: each element of an array, or a
+/// single object on its own.
+fn format_ndjson(data: &Value) -> Result<String> {
+    match data {
+        Value::Array(items) => {
+            let lines = items
+                .iter()
+                .map(serde_json::to_string)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            Ok(lines.join("\n"))
+        }
+        Value::Object(_) => Ok(serde_json::to_string(data)?),
+        _ => Err(AppError::Validation {
+            message: "NDJSON output requires a JSON array or object".to_string(),
+        }),
+    }
+}
+
+/// Keeps `depth` levels of nesting and replaces any non-empty object or array
+/// below that with a `{"...": "truncated"}` placeholder.
+pub fn truncate_value(value: &Value, depth: usize) -> Value {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            if depth == 0 {
+                return truncated_placeholder();
+            }
+            let truncated: Map<String, Value> = map
+                .iter()
+                .map(|(key, child)| (key.clone(), truncate_value(child, depth - 1)))
+                .collect();
+            Value::Object(truncated)
+        }
+        Value::Array(items) if !items.is_empty() => {
+            if depth == 0 {
+                return truncated_placeholder();
+            }
+            Value::Array(items.iter().map(|item| truncate_value(item, depth - 1)).collect())
+        }
+        _ => value.clone(),
+    }
+}
+
+/// Rebuilds `value` with every object's keys in alphabetical order, at all
+/// nesting levels, so output does not depend on the map's iteration order.
+pub fn sort_keys(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            Value::Object(entries.into_iter().map(|(key, child)| (key.clone(), sort_keys(child))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(sort_keys).collect()),
+        _ => value.clone(),
+    }
+}
+
+/// Flattens nested objects and arrays into one object keyed by dotted paths,
+/// e.g. `{"a": {"b": [1]}}` becomes `{"a.b.0": 1}`. Array elements are keyed
+/// by index; empty objects and arrays are kept as values. Scalars and empty
+/// top-level objects and arrays are returned as they are. Fails when two
+/// paths produce the same key, as with `{"a.b": 1, "a": {"b": 2}}`.
+pub fn flatten_value(value: &Value) -> Result<Value> {
+    let nested = match value {
+        Value::Object(map) => !map.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        _ => false,
+    };
+    if !nested {
+        return Ok(value.clone());
+    }
+    let mut flat = Map::new();
+    flatten_into(value, String::new(), &mut flat)?;
+    Ok(Value::Object(flat))
+}
+
+fn flatten_into(value: &Value, path: String, flat: &mut Map<String, Value>) -> Result<()> {
+    let child_path = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, child) in map {
+                flatten_into(child, child_path(key), flat)?;
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (index, item) in items.iter().enumerate() {
+                flatten_into(item, child_path(&index.to_string()), flat)?;
+            }
+        }
+        _ => {
+            if flat.insert(path.clone(), value.clone()).is_some() {
+                return Err(AppError::Validation {
+                    message: format!("Cannot flatten: key '{}' is produced by more than one path", path),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The inverse of `flatten_value`: splits each key of an object on `.` and
+/// nests the values. A level whose keys are exactly `0..n` becomes an array.
+/// Values that are not objects are returned as they are. Fails when a key is
+/// both a value and a parent, as with `{"a": 1, "a.b": 2}`.
+pub fn unflatten_value(value: &Value) -> Result<Value> {
+    let Value::Object(flat) = value else {
+        return Ok(value.clone());
+    };
+
+    let mut root = FlatNode::Branch(BTreeMap::new());
+    for (key, child) in flat {
+        let conflict = || AppError::Validation {
+            message: format!("Cannot unflatten: '{}' conflicts with another key", key),
+        };
+        let segments: Vec<&str> = key.split('.').collect();
+        let (last, parents) = segments.split_last().expect("split always yields a segment");
+        let mut node = &mut root;
+        for segment in parents {
+            let FlatNode::Branch(children) = node else {
+                return Err(conflict());
+            };
+            node = children.entry(segment.to_string()).or_insert_with(|| FlatNode::Branch(BTreeMap::new()));
+        }
+        let FlatNode::Branch(children) = node else {
+            return Err(conflict());
+        };
+        if children.insert(last.to_string(), FlatNode::Leaf(child.clone())).is_some() {
+            return Err(conflict());
+        }
+    }
+    Ok(root.into_value())
+}
+
+enum FlatNode {
+    Leaf(Value),
+    Branch(BTreeMap<String, FlatNode>),
+}
+
+impl FlatNode {
+    fn into_value(self) -> Value {
+        match self {
+            FlatNode::Leaf(value) => value,
+            FlatNode::Branch(children) => {
+                let indexed = (0..children.len()).all(|index| children.contains_key(&index.to_string()));
+                if indexed && !children.is_empty() {
+                    let mut children = children;
+                    let items = (0..children.len())
+                        .map(|index| children.remove(&index.to_string()).expect("index checked above").into_value())
+                        .collect();
+                    Value::Array(items)
+                } else {
+                    Value::Object(children.into_iter().map(|(key, child)| (key, child.into_value())).collect())
+                }
+            }
+        }
+    }
+}
+
+/// Compact JSON with keys sorted at every level, so equivalent values
+/// serialize to identical bytes whatever their original formatting.
+pub fn canonicalize(value: &Value) -> String {
+    serde_json::to_string(&sort_keys(value)).expect("serializing a Value cannot fail")
+}
+
+/// Length in bytes of `canonicalize(value)`, computed without building the
+/// string.
+pub fn canonical_size(value: &Value) -> usize {
+    match value {
+        Value::Null => 4,
+        Value::Bool(true) => 4,
+        Value::Bool(false) => 5,
+        Value::Number(number) => number.to_string().len(),
+        Value::String(text) => json_string_size(text),
+        Value::Array(items) => 2 + items.len().saturating_sub(1) + items.iter().map(canonical_size).sum::<usize>(),
+        Value::Object(map) => {
+            2 + map.len().saturating_sub(1) + map.iter().map(|(key, child)| entry_size(key, child)).sum::<usize>()
+        }
+    }
+}
+
+/// Bytes of `"key":value` inside a canonical object.
+fn entry_size(key: &str, value: &Value) -> usize {
+    json_string_size(key) + 1 + canonical_size(value)
+}
+
+fn json_string_size(text: &str) -> usize {
+    serde_json::to_string(text).expect("serializing a string cannot fail").len()
+}
+
+/// Where the canonical bytes of a value go: one part per top-level field of
+/// an object (`"key":value`) or element of an array, largest first.
+/// `overhead_bytes` covers the brackets and separators, so for an object or
+/// array the parts and the overhead add up to `total_bytes`. Scalars have no
+/// parts and no overhead.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SizeBreakdown {
+    pub total_bytes: usize,
+    pub overhead_bytes: usize,
+    pub parts: Vec<SizePart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SizePart {
+    /// The field name, or the element index of an array.
+    pub name: String,
+    pub bytes: usize,
+}
+
+pub fn size_breakdown(value: &Value) -> SizeBreakdown {
+    let mut parts: Vec<SizePart> = match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, child)| SizePart { name: key.clone(), bytes: entry_size(key, child) })
+            .collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| SizePart { name: index.to_string(), bytes: canonical_size(item) })
+            .collect(),
+        _ => Vec::new(),
+    };
+    parts.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+
+    let total_bytes = canonical_size(value);
+    let overhead_bytes = match value {
+        Value::Object(_) | Value::Array(_) => total_bytes - parts.iter().map(|part| part.bytes).sum::<usize>(),
+        _ => 0,
+    };
+    SizeBreakdown { total_bytes, overhead_bytes, parts }
+}
+
+fn truncated_placeholder() -> Value {
+    json!({ "...": "truncated" })
+}
+
+/// Renders rows as left-aligned columns separated by two spaces, with the
+/// headers upper-cased on the first line.
+pub fn render_table(headers: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header_row: Vec<String> = headers.iter().map(|h| h.to_uppercase()).collect();
+    std::iter::once(&header_row)
+        .chain(rows)
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A file name for the response of `url`: the segments of the URL path
+/// joined with `_`, with the last segment's extension replaced by
+/// `extension` and anything but ASCII letters, digits, `-`, `_` and `.`
+/// replaced by `_`. `https://example.com/api/users/1.xml?page=2` gives
+/// `api_users_1.json`. A URL without a path is named after its host, or
+/// `index`.
+pub fn file_name_for_url(url: &str, extension: &str) -> String {
+    let (host, path) = match reqwest::Url::parse(url) {
+        Ok(parsed) => (parsed.host_str().map(str::to_string), parsed.path().to_string()),
+        Err(_) => (None, url.split(['?', '#']).next().unwrap_or_default().to_string()),
+    };
+    let mut segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+    if let Some(last) = segments.last_mut() {
+        if let Some((stem, _)) = last.rsplit_once('.').filter(|(stem, _)| !stem.is_empty()) {
+            *last = stem;
+        }
+    }
+
+    let joined = if segments.is_empty() { host.unwrap_or_default() } else { segments.join("_") };
+    let sanitized: String = joined
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect();
+    let stem = match sanitized.trim_start_matches('.') {
+        "" => "index",
+        stem => stem,
+    };
+    format!("{}.{}", stem, extension)
+}
+
+/// `dir/file_name`, or if that exists, the first free `dir/<stem>-N.<ext>`
+/// counting from 1.
+pub fn unused_path(dir: &Path, file_name: &str) -> PathBuf {
+    let path = dir.join(file_name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (file_name, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{}-{}{}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .expect("some numbered name is free")
+}
@@ -0,0 +1,78 @@
+use crate::{AppError, Result};
+use serde_json::{Map, Value};
+
+/// Writes `value` at an RFC 6901 JSON Pointer, creating missing objects
+/// along the way. Array segments must index an existing element, or be
+/// `-` / the current length to append.
+pub fn set(root: &mut Value, pointer: &str, value: Value) -> Result<()> {
+    if pointer.is_empty() {
+        *root = value;
+        return Ok(());
+    }
+    let Some(path) = pointer.strip_prefix('/') else {
+        return Err(invalid(pointer, "must be empty or start with '/'"));
+    };
+
+    let tokens: Vec<String> = path.split('/').map(unescape).collect();
+    let (last, parents) = tokens.split_last().expect("split always yields a token");
+
+    let mut current = root;
+    for token in parents {
+        if current.is_null() {
+            *current = Value::Object(Map::new());
+        }
+        current = match current {
+            Value::Object(map) => map.entry(token.clone()).or_insert(Value::Null),
+            Value::Array(items) => {
+                let index = array_index(token, items.len(), pointer)?;
+                items
+                    .get_mut(index)
+                    .ok_or_else(|| invalid(pointer, &format!("index {} is out of bounds", index)))?
+            }
+            _ => return Err(invalid(pointer, &format!("'{}' is not inside an object or array", token))),
+        };
+    }
+
+    if current.is_null() {
+        *current = Value::Object(Map::new());
+    }
+    match current {
+        Value::Object(map) => {
+            map.insert(last.clone(), value);
+        }
+        Value::Array(items) => {
+            let index = array_index(last, items.len(), pointer)?;
+            match index.cmp(&items.len()) {
+                std::cmp::Ordering::Less => items[index] = value,
+                std::cmp::Ordering::Equal => items.push(value),
+                std::cmp::Ordering::Greater => {
+                    return Err(invalid(pointer, &format!("index {} is out of bounds", index)))
+                }
+            }
+        }
+        _ => return Err(invalid(pointer, &format!("'{}' is not inside an object or array", last))),
+    }
+
+    Ok(())
+}
+
+fn unescape(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+fn array_index(token: &str, len: usize, pointer: &str) -> Result<usize> {
+    if token == "-" {
+        return Ok(len);
+    }
+    token
+        .parse()
+        .ok()
+        .filter(|_| token == "0" || !token.starts_with('0'))
+        .ok_or_else(|| invalid(pointer, &format!("'{}' is not an array index", token)))
+}
+
+fn invalid(pointer: &str, reason: &str) -> AppError {
+    AppError::Validation {
+        message: format!("Invalid JSON Pointer '{}': {}", pointer, reason),
+    }
+}
@@ -0,0 +1,101 @@
+use crate::http::{HttpClient, RequestOptions};
+use crate::storage::Storage;
+use crate::{AppError, Result};
+use futures::stream::{self, StreamExt};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// One request of a `run` spec file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequestSpec {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub body: Option<Value>,
+    #[serde(default)]
+    pub store_key: Option<String>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepReport {
+    pub step: usize,
+    pub method: String,
+    pub url: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stored: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Reads a JSON array of request specs. Every method is checked up front so
+/// that a typo fails before any request is sent.
+pub fn load_specs(path: &Path) -> Result<Vec<RequestSpec>> {
+    let content = std::fs::read_to_string(path)?;
+    let specs: Vec<RequestSpec> = serde_json::from_str(&content).map_err(|e| AppError::Validation {
+        message: format!("Invalid request spec file {}: {}", path.display(), e),
+    })?;
+    for (index, spec) in specs.iter().enumerate() {
+        parse_method(&spec.method).map_err(|message| AppError::Validation {
+            message: format!("Step {}: {}", index + 1, message),
+        })?;
+    }
+    Ok(specs)
+}
+
+fn parse_method(method: &str) -> std::result::Result<Method, String> {
+    match method.to_ascii_uppercase().as_str() {
+        "GET" => Ok(Method::GET),
+        "HEAD" => Ok(Method::HEAD),
+        "POST" => Ok(Method::POST),
+        "PUT" => Ok(Method::PUT),
+        "PATCH" => Ok(Method::PATCH),
+        "DELETE" => Ok(Method::DELETE),
+        _ => Err(format!("unsupported method '{}'", method)),
+    }
+}
+
+/// Runs the steps with up to `concurrency` in flight (1 runs them strictly
+/// in order). A failed step does not stop the others; reports come back in
+/// step order.
+pub async fn execute(
+    client: &HttpClient,
+    storage: &Storage,
+    specs: &[RequestSpec],
+    concurrency: usize,
+) -> Vec<StepReport> {
+    stream::iter(specs.iter().enumerate())
+        .map(|(index, spec)| async move {
+            let outcome = run_step(client, storage, spec).await;
+            StepReport {
+                step: index + 1,
+                method: spec.method.to_ascii_uppercase(),
+                url: spec.url.clone(),
+                ok: outcome.is_ok(),
+                stored: outcome.as_ref().ok().cloned().flatten(),
+                error: outcome.err().map(|e| e.to_string()),
+            }
+        })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+async fn run_step(client: &HttpClient, storage: &Storage, spec: &RequestSpec) -> Result<Option<String>> {
+    let method = parse_method(&spec.method).map_err(|message| AppError::Validation { message })?;
+    let response = client
+        .request_json(method, &spec.url, spec.body.as_ref(), &RequestOptions::default())
+        .await?;
+    match &spec.store_key {
+        Some(key) => Ok(Some(storage.store(key.clone(), response).await?.key)),
+        None => Ok(None),
+    }
+}
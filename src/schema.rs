@@ -0,0 +1,208 @@
+use crate::{AppError, Result};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn load_schema(path: &Path) -> Result<Value> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Checks `instance` against a JSON Schema, reporting every violation with
+/// its JSON Pointer location rather than stopping at the first.
+pub fn validate(schema: &Value, instance: &Value) -> Result<()> {
+    let validator = jsonschema::validator_for(schema).map_err(|e| AppError::Validation {
+        message: format!("Invalid JSON Schema: {}", e),
+    })?;
+
+    let violations: Vec<String> = validator
+        .iter_errors(instance)
+        .map(|error| {
+            let location = error.instance_path().to_string();
+            let location = if location.is_empty() { "(root)".to_string() } else { location };
+            format!("{}: {}", location, error)
+        })
+        .collect();
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::InvalidFields {
+            context: "Value does not match schema".to_string(),
+            issues: violations,
+        })
+    }
+}
+
+/// Named schemas kept as `<name>.json` files in one directory, so that
+/// `store --schema-name` can refer to them without a path.
+pub struct SchemaRegistry {
+    dir: PathBuf,
+}
+
+impl SchemaRegistry {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Registers the schema in `file` under `name`. The file must hold a
+    /// valid JSON Schema; an existing name is only replaced with `replace`.
+    pub fn add(&self, name: &str, file: &Path, replace: bool) -> Result<PathBuf> {
+        let path = self.path(name)?;
+        if path.exists() && !replace {
+            return Err(AppError::Validation {
+                message: format!("Schema '{}' already exists; pass --force to replace it", name),
+            });
+        }
+        let schema = load_schema(file)?;
+        jsonschema::validator_for(&schema).map_err(|e| AppError::Validation {
+            message: format!("Invalid JSON Schema in {}: {}", file.display(), e),
+        })?;
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&path, serde_json::to_string_pretty(&schema)?)?;
+        Ok(path)
+    }
+
+    pub fn get(&self, name: &str) -> Result<Value> {
+        let path = self.path(name)?;
+        if !path.exists() {
+            return Err(AppError::NotFound {
+                resource: format!("schema '{}'", name),
+            });
+        }
+        load_schema(&path)
+    }
+
+    /// Registered names in sorted order; a missing directory has none.
+    pub fn list(&self) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn remove(&self, name: &str) -> Result<()> {
+        let path = self.path(name)?;
+        if !path.exists() {
+            return Err(AppError::NotFound {
+                resource: format!("schema '{}'", name),
+            });
+        }
+        Ok(fs::remove_file(path)?)
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+            && !name.starts_with('.');
+        if !valid {
+            return Err(AppError::Validation {
+                message: format!(
+                    "Invalid schema name '{}': use only letters, digits, '-', '_' and '.'",
+                    name
+                ),
+            });
+        }
+        Ok(self.dir.join(format!("{}.json", name)))
+    }
+}
+
+/// Builds a draft 2020-12 JSON Schema describing `value`, for use as a
+/// starting point with `--schema`.
+pub fn infer_document(value: &Value) -> Value {
+    let mut schema = infer_schema(value);
+    if let Value::Object(map) = &mut schema {
+        map.insert("$schema".to_string(), json!("https://json-schema.org/draft/2020-12/schema"));
+    }
+    schema
+}
+
+/// Infers the schema of a single value. Every object key present is marked
+/// required; array elements are merged into one `items` schema.
+pub fn infer_schema(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({"type": "null"}),
+        Value::Bool(_) => json!({"type": "boolean"}),
+        Value::Number(n) if n.is_i64() || n.is_u64() => json!({"type": "integer"}),
+        Value::Number(_) => json!({"type": "number"}),
+        Value::String(_) => json!({"type": "string"}),
+        Value::Array(items) => match items.iter().map(infer_schema).reduce(|a, b| merge(&a, &b)) {
+            Some(item_schema) => json!({"type": "array", "items": item_schema}),
+            None => json!({"type": "array"}),
+        },
+        Value::Object(map) => {
+            let properties: Map<String, Value> =
+                map.iter().map(|(key, child)| (key.clone(), infer_schema(child))).collect();
+            let required: Vec<&String> = map.keys().collect();
+            json!({"type": "object", "properties": properties, "required": required})
+        }
+    }
+}
+
+/// Combines two inferred schemas into one that accepts both. Objects merge
+/// their properties (a key is required only if both require it), arrays
+/// merge their items, integer widens to number and other mismatches become
+/// `anyOf`.
+fn merge(a: &Value, b: &Value) -> Value {
+    if a == b {
+        return a.clone();
+    }
+
+    match (type_of(a), type_of(b)) {
+        (Some("object"), Some("object")) => {
+            let mut properties = a["properties"].as_object().cloned().unwrap_or_default();
+            for (key, schema) in b["properties"].as_object().into_iter().flatten() {
+                let merged = match properties.get(key) {
+                    Some(existing) => merge(existing, schema),
+                    None => schema.clone(),
+                };
+                properties.insert(key.clone(), merged);
+            }
+            let required_b = b["required"].as_array().cloned().unwrap_or_default();
+            let required: Vec<Value> = a["required"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|key| required_b.contains(key))
+                .cloned()
+                .collect();
+            json!({"type": "object", "properties": properties, "required": required})
+        }
+        (Some("array"), Some("array")) => match (a.get("items"), b.get("items")) {
+            (Some(x), Some(y)) => json!({"type": "array", "items": merge(x, y)}),
+            (Some(items), None) | (None, Some(items)) => json!({"type": "array", "items": items}),
+            (None, None) => json!({"type": "array"}),
+        },
+        (Some("integer"), Some("number")) | (Some("number"), Some("integer")) => json!({"type": "number"}),
+        _ => {
+            let mut variants: Vec<Value> = Vec::new();
+            for schema in [a, b] {
+                let options = schema["anyOf"].as_array().cloned().unwrap_or_else(|| vec![schema.clone()]);
+                for option in options {
+                    if !variants.contains(&option) {
+                        variants.push(option);
+                    }
+                }
+            }
+            json!({"anyOf": variants})
+        }
+    }
+}
+
+fn type_of(schema: &Value) -> Option<&str> {
+    schema.get("type").and_then(Value::as_str)
+}
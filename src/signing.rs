@@ -0,0 +1,342 @@
+use crate::config::{BearerConfig, HmacConfig, SigV4Config};
+use crate::{AppError, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::Request;
+use sha2::digest::Output;
+use sha2::{Digest, Sha256};
+use tokio::process::Command;
+use tokio::sync::OnceCell;
+
+/// Signs outgoing requests with HMAC-SHA256 over
+/// `METHOD\npath?query\ntimestamp\nbody`, adding the hex signature and the
+/// Unix timestamp as headers.
+pub struct HmacSigner {
+    secret: Vec<u8>,
+    header: HeaderName,
+    timestamp_header: HeaderName,
+}
+
+impl HmacSigner {
+    pub fn new(config: &HmacConfig) -> Result<Self> {
+        Ok(Self {
+            secret: config.secret.as_bytes().to_vec(),
+            header: header_name(&config.header)?,
+            timestamp_header: header_name(&config.timestamp_header)?,
+        })
+    }
+
+    pub fn sign(&self, request: &mut Request, timestamp: i64) {
+        let url = request.url();
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let body = request.body().and_then(|body| body.as_bytes()).unwrap_or_default();
+        let signature = self.signature(request.method().as_str(), &path, timestamp, body);
+
+        let headers = request.headers_mut();
+        headers.insert(self.timestamp_header.clone(), HeaderValue::from(timestamp));
+        headers.insert(
+            self.header.clone(),
+            HeaderValue::from_str(&signature).expect("hex is a valid header value"),
+        );
+    }
+
+    pub fn signature(&self, method: &str, path: &str, timestamp: i64, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(format!("{}\n{}\n{}\n", method, path, timestamp).as_bytes());
+        mac.update(body);
+        format!("{:x}", mac.finalize().into_bytes())
+    }
+}
+
+fn header_name(name: &str) -> Result<HeaderName> {
+    HeaderName::from_bytes(name.as_bytes()).map_err(|_| AppError::Validation {
+        message: format!("Invalid HMAC header name '{}'", name),
+    })
+}
+
+const SIGV4_ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Signs requests with AWS Signature Version 4, adding `Authorization`,
+/// `x-amz-date` and `x-amz-content-sha256` (plus `x-amz-security-token` for
+/// temporary credentials).
+pub struct SigV4Signer {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    region: String,
+    service: String,
+}
+
+impl std::fmt::Debug for SigV4Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigV4Signer")
+            .field("access_key_id", &self.access_key_id)
+            .field("region", &self.region)
+            .field("service", &self.service)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigV4Signer {
+    /// Takes each credential from `config`, else from `AWS_ACCESS_KEY_ID`,
+    /// `AWS_SECRET_ACCESS_KEY`, `AWS_SESSION_TOKEN` and `AWS_REGION` (or
+    /// `AWS_DEFAULT_REGION`).
+    pub fn new(config: &SigV4Config) -> Result<Self> {
+        let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let required = |value: Option<String>, field: &str, var: &str| {
+            value.ok_or_else(|| AppError::Validation {
+                message: format!("SigV4 auth needs server.auth.{} or {}", field, var),
+            })
+        };
+
+        Ok(Self {
+            access_key_id: required(
+                config.access_key_id.clone().or_else(|| env("AWS_ACCESS_KEY_ID")),
+                "access_key_id",
+                "AWS_ACCESS_KEY_ID",
+            )?,
+            secret_access_key: required(
+                config.secret_access_key.clone().or_else(|| env("AWS_SECRET_ACCESS_KEY")),
+                "secret_access_key",
+                "AWS_SECRET_ACCESS_KEY",
+            )?,
+            session_token: config.session_token.clone().or_else(|| env("AWS_SESSION_TOKEN")),
+            region: required(
+                config.region.clone().or_else(|| env("AWS_REGION")).or_else(|| env("AWS_DEFAULT_REGION")),
+                "region",
+                "AWS_REGION",
+            )?,
+            service: config.service.clone(),
+        })
+    }
+
+    pub fn sign(&self, request: &mut Request, now: DateTime<Utc>) {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = match request.body() {
+            None => hex_sha256(b""),
+            Some(body) => body.as_bytes().map(hex_sha256).unwrap_or_else(|| UNSIGNED_PAYLOAD.to_string()),
+        };
+
+        let url = request.url().clone();
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let headers = request.headers_mut();
+        headers.insert("x-amz-date", HeaderValue::from_str(&amz_date).expect("date is a valid header value"));
+        headers.insert(
+            "x-amz-content-sha256",
+            HeaderValue::from_str(&payload_hash).expect("hash is a valid header value"),
+        );
+        if let Some(token) = &self.session_token {
+            if let Ok(value) = HeaderValue::from_str(token) {
+                headers.insert("x-amz-security-token", value);
+            }
+        }
+
+        let mut signed: Vec<(String, String)> = vec![("host".to_string(), host)];
+        for (name, value) in headers.iter() {
+            let name = name.as_str();
+            if name.starts_with("x-amz-") || name == "content-type" {
+                signed.push((name.to_string(), value.to_str().unwrap_or_default().to_string()));
+            }
+        }
+
+        let path = if self.service == "s3" { url.path().to_string() } else { uri_encode(url.path(), false) };
+        let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        let (canonical, signed_headers) =
+            sigv4_canonical_request(request.method().as_str(), &path, &query, &signed, &payload_hash);
+        let signature = self.signature(&canonical, now);
+
+        let authorization = format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            SIGV4_ALGORITHM,
+            self.access_key_id,
+            self.scope(now),
+            signed_headers,
+            signature
+        );
+        if let Ok(value) = HeaderValue::from_str(&authorization) {
+            request.headers_mut().insert(AUTHORIZATION, value);
+        }
+    }
+
+    /// The hex signature of a canonical request made at `now`.
+    pub fn signature(&self, canonical_request: &str, now: DateTime<Utc>) -> String {
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            SIGV4_ALGORITHM,
+            now.format("%Y%m%dT%H%M%SZ"),
+            self.scope(now),
+            hex_sha256(canonical_request.as_bytes())
+        );
+
+        let date = now.format("%Y%m%d").to_string();
+        let key = hmac_sha256(format!("AWS4{}", self.secret_access_key).as_bytes(), date.as_bytes());
+        let key = hmac_sha256(&key, self.region.as_bytes());
+        let key = hmac_sha256(&key, self.service.as_bytes());
+        let key = hmac_sha256(&key, b"aws4_request");
+        format!("{:x}", hmac_sha256(&key, string_to_sign.as_bytes()))
+    }
+
+    fn scope(&self, now: DateTime<Utc>) -> String {
+        format!("{}/{}/{}/aws4_request", now.format("%Y%m%d"), self.region, self.service)
+    }
+}
+
+/// The token of a `mode: bearer` config. A `token_file` or `token_command`
+/// is only read on the first call to `token`, so runs that send no request
+/// never touch it, and the result is reused for the rest of the run.
+/// Concurrent first calls wait for a single resolution.
+pub struct BearerToken {
+    config: BearerConfig,
+    resolved: OnceCell<String>,
+}
+
+impl std::fmt::Debug for BearerToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BearerToken").field("config", &self.config).finish_non_exhaustive()
+    }
+}
+
+impl BearerToken {
+    pub fn new(config: &BearerConfig) -> Self {
+        Self {
+            config: config.clone(),
+            resolved: OnceCell::new(),
+        }
+    }
+
+    /// The token with surrounding whitespace trimmed. Fails when the file
+    /// cannot be read, the command fails, or the token is empty.
+    pub async fn token(&self) -> Result<&str> {
+        self.resolved.get_or_try_init(|| self.resolve()).await.map(String::as_str)
+    }
+
+    async fn resolve(&self) -> Result<String> {
+        let (source, token) = if let Some(token) = &self.config.token {
+            ("server.auth.token", token.clone())
+        } else if let Some(path) = &self.config.token_file {
+            let token = tokio::fs::read_to_string(path).await.map_err(|e| AppError::Validation {
+                message: format!("Cannot read server.auth.token_file {}: {}", path.display(), e),
+            })?;
+            ("server.auth.token_file", token)
+        } else if let Some(command) = &self.config.token_command {
+            ("server.auth.token_command", run_token_command(command).await?)
+        } else {
+            return Err(AppError::Validation {
+                message: "server.auth: bearer needs exactly one of token, token_file or token_command".to_string(),
+            });
+        };
+
+        let token = token.trim();
+        if token.is_empty() {
+            return Err(AppError::Validation {
+                message: format!("{} gave an empty bearer token", source),
+            });
+        }
+        Ok(token.to_string())
+    }
+}
+
+/// Runs `command` through the shell and returns its stdout. Only stderr is
+/// included in errors, since stdout holds the secret.
+async fn run_token_command(command: &str) -> Result<String> {
+    let output = shell(command).output().await.map_err(|e| AppError::OperationFailed {
+        reason: format!("Could not run server.auth.token_command: {}", e),
+    })?;
+    if !output.status.success() {
+        return Err(AppError::OperationFailed {
+            reason: format!(
+                "server.auth.token_command exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+    String::from_utf8(output.stdout).map_err(|_| AppError::Validation {
+        message: "server.auth.token_command printed a token that is not UTF-8".to_string(),
+    })
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.args(["-c", command]);
+    shell
+}
+
+/// Builds the SigV4 canonical request from an already-encoded `path`,
+/// decoded query pairs and the headers to sign. Returns it together with the
+/// `SignedHeaders` list.
+pub fn sigv4_canonical_request(
+    method: &str,
+    path: &str,
+    query: &[(String, String)],
+    headers: &[(String, String)],
+    payload_hash: &str,
+) -> (String, String) {
+    let mut query: Vec<(String, String)> = query
+        .iter()
+        .map(|(key, value)| (uri_encode(key, true), uri_encode(value, true)))
+        .collect();
+    query.sort();
+    let query = query
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let mut headers: Vec<(String, String)> = headers
+        .iter()
+        .map(|(name, value)| {
+            (name.to_ascii_lowercase(), value.split_whitespace().collect::<Vec<_>>().join(" "))
+        })
+        .collect();
+    headers.sort();
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+    let signed_headers = headers.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+
+    let path = if path.is_empty() { "/" } else { path };
+    let canonical = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method, path, query, canonical_headers, signed_headers, payload_hash
+    );
+    (canonical, signed_headers)
+}
+
+/// Percent-encodes everything but unreserved characters; `/` is kept unless
+/// `encode_slash` is set.
+fn uri_encode(input: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Output<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes()
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
@@ -0,0 +1,97 @@
+use serde::Serialize;
+use std::time::Duration;
+
+/// One server-sent event. `id` is the last event id the stream had set when
+/// the event was dispatched, which may come from an earlier event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SseEvent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub data: String,
+}
+
+/// Incremental parser for `text/event-stream` bodies. Chunks may split
+/// lines anywhere; lines end in LF or CRLF. Comment lines (`:`) and unknown
+/// fields are ignored, and a blank line dispatches the event being built if
+/// it has any `data:` lines.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    pending: Vec<u8>,
+    event: Option<String>,
+    data: Option<String>,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl SseParser {
+    /// Parses `chunk` and returns the events it completes, in order.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.pending.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        let mut start = 0;
+        while let Some(offset) = self.pending[start..].iter().position(|&byte| byte == b'\n') {
+            let line = &self.pending[start..start + offset];
+            let line = String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line)).into_owned();
+            if let Some(event) = self.parse_line(&line) {
+                events.push(event);
+            }
+            start += offset + 1;
+        }
+        self.pending.drain(..start);
+        events
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let event = self.event.take();
+            let data = self.data.take()?;
+            return Some(SseEvent { event, id: self.last_event_id.clone(), data });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => match &mut self.data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_string()),
+            },
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Drops a partly received line or event, as after a lost connection.
+    /// The last event id and reconnection delay are kept.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.event = None;
+        self.data = None;
+    }
+
+    /// The id to send as `Last-Event-ID` when reconnecting.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// The reconnection delay set by the server with `retry:`.
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+}
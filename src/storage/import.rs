@@ -0,0 +1,141 @@
+use super::Storage;
+use crate::{AppError, Result};
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{debug, info, instrument, warn};
+
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    pub overwrite: bool,
+    pub resume: bool,
+    pub concurrency: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped_existing: usize,
+    pub already_done: usize,
+    pub failed: Vec<(String, String)>,
+}
+
+#[derive(Deserialize)]
+struct ArchiveEntry {
+    key: String,
+    value: Value,
+}
+
+/// Where `import` records finished keys for `archive`: `<archive>.manifest`.
+pub fn manifest_path(archive: &Path) -> PathBuf {
+    let mut name = archive.as_os_str().to_owned();
+    name.push(".manifest");
+    PathBuf::from(name)
+}
+
+impl Storage {
+    /// Imports an NDJSON archive of `{"key": ..., "value": ...}` lines.
+    ///
+    /// Every key that is finished (imported, or skipped because it exists and
+    /// `overwrite` is off) is appended to the manifest next to the archive.
+    /// With `resume`, keys already in the manifest are not touched again. The
+    /// manifest is removed once every entry has been handled.
+    #[instrument(skip_all, fields(archive = %archive.display()))]
+    pub async fn import(&self, archive: &Path, options: &ImportOptions) -> Result<ImportReport> {
+        let entries = read_archive(archive)?;
+        let manifest = manifest_path(archive);
+
+        let done: HashSet<String> = match (options.resume, manifest.exists()) {
+            (true, true) => fs::read_to_string(&manifest)?.lines().map(str::to_string).collect(),
+            (true, false) => HashSet::new(),
+            (false, true) => {
+                return Err(AppError::Validation {
+                    message: format!(
+                        "An interrupted import left {}; pass --resume to continue it or delete the file to start over",
+                        manifest.display()
+                    ),
+                })
+            }
+            (false, false) => HashSet::new(),
+        };
+
+        let mut report = ImportReport::default();
+        let pending: Vec<ArchiveEntry> = entries
+            .into_iter()
+            .filter(|entry| {
+                let finished = done.contains(&entry.key);
+                if finished {
+                    report.already_done += 1;
+                }
+                !finished
+            })
+            .collect();
+
+        let log = Mutex::new(OpenOptions::new().create(true).append(true).open(&manifest)?);
+        let outcomes: Vec<(String, Result<bool>)> = stream::iter(pending)
+            .map(|entry| self.import_entry(entry, options.overwrite, &log))
+            .buffer_unordered(options.concurrency.max(1))
+            .collect()
+            .await;
+
+        for (key, outcome) in outcomes {
+            match outcome {
+                Ok(true) => report.imported += 1,
+                Ok(false) => report.skipped_existing += 1,
+                Err(e) => report.failed.push((key, e.to_string())),
+            }
+        }
+
+        if report.failed.is_empty() {
+            fs::remove_file(&manifest)?;
+        }
+        info!(
+            "Imported {} items ({} existing skipped, {} already done, {} failed)",
+            report.imported,
+            report.skipped_existing,
+            report.already_done,
+            report.failed.len()
+        );
+        Ok(report)
+    }
+
+    /// Returns whether the entry was written (`false` when skipped).
+    async fn import_entry(&self, entry: ArchiveEntry, overwrite: bool, log: &Mutex<File>) -> (String, Result<bool>) {
+        let key = entry.key;
+        let outcome = async {
+            let _permit = self.permit().await;
+            let written = overwrite || !self.exists(&key).await;
+            if written {
+                self.store(key.clone(), entry.value).await?;
+            } else {
+                debug!("Skipping existing key {}", key);
+            }
+            writeln!(log.lock().unwrap(), "{}", key)?;
+            Ok(written)
+        }
+        .await;
+
+        if let Err(e) = &outcome {
+            warn!("Failed to import {}: {}", key, e);
+        }
+        (key, outcome)
+    }
+}
+
+fn read_archive(archive: &Path) -> Result<Vec<ArchiveEntry>> {
+    fs::read_to_string(archive)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| AppError::Validation {
+                message: format!("{} line {}: expected {{\"key\": ..., \"value\": ...}}: {}", archive.display(), index + 1, e),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,122 @@
+use super::{read_item_file, Storage};
+use crate::output::canonicalize;
+use crate::{AppError, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+
+/// Canonical JSON of a field value mapped to the keys holding it.
+type Index = BTreeMap<String, BTreeSet<String>>;
+
+/// Index files live in this directory inside the data directory. The leading
+/// dot keeps it from being taken for a namespace.
+const INDEX_DIR: &str = ".index";
+
+impl Storage {
+    /// Keys whose value has `field` equal to `value`, read from the field's
+    /// index rather than by scanning items. The field must be listed in
+    /// `storage.indexed_fields`. An index that does not exist yet is built
+    /// from the stored items first.
+    pub async fn query_index(&self, field: &str, value: &Value) -> Result<Vec<String>> {
+        if !self.indexed_fields.iter().any(|indexed| indexed == field) {
+            return Err(AppError::Validation {
+                message: format!("Field '{}' is not indexed; add it to storage.indexed_fields", field),
+            });
+        }
+
+        let _guard = self.index_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let index = match self.read_index(field)? {
+            Some(index) => index,
+            None => self.rebuild_index(field)?,
+        };
+        let keys = index.get(&canonicalize(value)).map(|keys| keys.iter().cloned().collect()).unwrap_or_default();
+        Ok(keys)
+    }
+
+    /// Moves `key` from the index entries of its old value to those of its
+    /// new one. `None` stands for an item that does not exist (before a first
+    /// store, after a delete). Failures only log a warning, since the item
+    /// itself was written; `query_index` then misses or over-reports until
+    /// the index file is deleted and rebuilt.
+    pub(super) fn update_indexes(&self, key: &str, old: Option<&Value>, new: Option<&Value>) {
+        if self.indexed_fields.is_empty() {
+            return;
+        }
+
+        let _guard = self.index_lock.lock().unwrap_or_else(|e| e.into_inner());
+        for field in &self.indexed_fields {
+            let old_entry = old.and_then(|value| value.get(field)).map(canonicalize);
+            let new_entry = new.and_then(|value| value.get(field)).map(canonicalize);
+            if old_entry == new_entry {
+                continue;
+            }
+
+            let updated = self.read_index(field).and_then(|index| {
+                // A missing index is built in full on the next query.
+                let Some(mut index) = index else {
+                    return Ok(());
+                };
+                if let Some(entry) = &old_entry {
+                    if let Some(keys) = index.get_mut(entry) {
+                        keys.remove(key);
+                        if keys.is_empty() {
+                            index.remove(entry);
+                        }
+                    }
+                }
+                if let Some(entry) = new_entry.clone() {
+                    index.entry(entry).or_default().insert(key.to_string());
+                }
+                self.write_index(field, &index)
+            });
+            if let Err(e) = updated {
+                warn!("Failed to update index of field '{}' for key {}: {}", field, key, e);
+            }
+        }
+    }
+
+    fn rebuild_index(&self, field: &str) -> Result<Index> {
+        let mut index = Index::new();
+        for path in self.item_paths()? {
+            let Some(key) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            match read_item_file(&path, key) {
+                Ok(item) => {
+                    if let Some(value) = item.value.get(field) {
+                        index.entry(canonicalize(value)).or_default().insert(item.key);
+                    }
+                }
+                Err(e) => debug!("Not indexing {}", e),
+            }
+        }
+
+        self.write_index(field, &index)?;
+        info!("Built index of field '{}' with {} values", field, index.len());
+        Ok(index)
+    }
+
+    fn read_index(&self, field: &str) -> Result<Option<Index>> {
+        let path = self.index_path(field);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    fn write_index(&self, field: &str, index: &Index) -> Result<()> {
+        let path = self.index_path(field);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(index)?)?;
+        Ok(())
+    }
+
+    fn index_path(&self, field: &str) -> PathBuf {
+        let safe_field = field.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|', '\0', '.'], "_");
+        self.data_dir.join(INDEX_DIR).join(format!("{}.json", safe_field))
+    }
+}
@@ -1,3 +1,7 @@
+mod import;
+mod index;
+#[allow(clippy::module_inception)]
 mod storage;
 
-pub use storage::*;
\ No newline at end of file
+pub use import::*;
+pub use storage::*;
@@ -1,126 +1,633 @@
-use crate::{AppError, Result};
+use crate::output::canonicalize;
+use crate::{pointer, AppError, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use tracing::{debug, info};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{debug, info, instrument, warn};
 use uuid::Uuid;
 
+/// Version of the on-disk `StoredItem` layout written by this build. Files
+/// with a lower version are upgraded by `Storage::migrate`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Marker field of the JSON object that wraps base64-encoded binary values.
+pub const BINARY_TAG: &str = "_rcli_binary";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredItem {
+    #[serde(default = "new_item_id")]
     pub id: String,
     pub key: String,
     pub value: Value,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
     pub metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+fn new_item_id() -> String {
+    Uuid::new_v4().to_string()
 }
 
 impl StoredItem {
     pub fn new(key: String, value: Value) -> Self {
         let now = Utc::now();
         Self {
-            id: Uuid::new_v4().to_string(),
+            id: new_item_id(),
             key,
             value,
             created_at: now,
             updated_at: now,
             metadata: HashMap::new(),
+            schema_version: SCHEMA_VERSION,
+            expires_at: None,
         }
     }
 
+    /// Replaces the value. Any expiry belonged to the old value and is cleared.
     pub fn update_value(&mut self, value: Value) {
         self.value = value;
         self.updated_at = Utc::now();
+        self.expires_at = None;
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
     }
 }
 
 pub struct Storage {
-    data_dir: PathBuf,
+    root_dir: PathBuf,
+    pub(super) data_dir: PathBuf,
     max_file_size_mb: u64,
+    max_items: Option<usize>,
+    audit_log: Option<PathBuf>,
+    limiter: Option<Arc<Semaphore>>,
+    canonical_dir: PathBuf,
+    pub(super) indexed_fields: Vec<String>,
+    pub(super) index_lock: Mutex<()>,
+    key_pattern: Option<(String, Regex)>,
+    normalize_keys: bool,
 }
 
 impl Storage {
-    pub fn new(data_dir: PathBuf, max_file_size_mb: u64) -> Result<Self> {
-        fs::create_dir_all(&data_dir)?;
-        
+    /// Opens storage rooted at `data_dir`. With a namespace, items live in
+    /// `data_dir/<namespace>/` and are invisible to other namespaces.
+    pub fn new(data_dir: PathBuf, namespace: Option<&str>, max_file_size_mb: u64) -> Result<Self> {
+        let items_dir = match namespace {
+            Some(name) => {
+                validate_namespace(name)?;
+                data_dir.join(name)
+            }
+            None => data_dir.clone(),
+        };
+        fs::create_dir_all(&items_dir)?;
+        let canonical_dir = fs::canonicalize(&items_dir)?;
+
         Ok(Self {
-            data_dir,
+            root_dir: data_dir,
+            data_dir: items_dir,
+            canonical_dir,
             max_file_size_mb,
+            max_items: None,
+            audit_log: None,
+            limiter: None,
+            indexed_fields: Vec::new(),
+            index_lock: Mutex::new(()),
+            key_pattern: None,
+            normalize_keys: false,
         })
     }
 
-    fn get_file_path(&self, key: &str) -> PathBuf {
-        let safe_key = key.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
-        self.data_dir.join(format!("{}.json", safe_key))
+    /// Starts storage with named settings instead of `new`'s positional
+    /// ones. Unset options keep the built-in defaults: `./data`, no
+    /// namespace and a 100 MB item limit.
+    ///
+    /// ```
+    /// use rust_advanced_cli::storage::Storage;
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let storage = Storage::builder()
+    ///     .data_dir(dir.path())
+    ///     .namespace("staging")
+    ///     .max_items(1000)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(storage.get_storage_info().unwrap().data_dir.ends_with("staging"));
+    /// ```
+    pub fn builder() -> StorageBuilder {
+        StorageBuilder::default()
+    }
+
+    /// Appends an NDJSON record for every `store`, `get` and `delete` to
+    /// `path`. Failing to write a record only logs a warning.
+    pub fn with_audit_log(mut self, path: Option<PathBuf>) -> Self {
+        self.audit_log = path;
+        self
+    }
+
+    /// Keeps at most `max_items` items: every store that goes over the limit
+    /// evicts the oldest items by `created_at`.
+    pub fn with_max_items(mut self, max_items: Option<usize>) -> Self {
+        self.max_items = max_items;
+        self
+    }
+
+    /// Replaces the per-item size limit, e.g. for a single `store --max-size-mb`.
+    pub fn with_max_file_size_mb(mut self, max_file_size_mb: u64) -> Self {
+        self.max_file_size_mb = max_file_size_mb;
+        self
+    }
+
+    /// Makes the concurrent paths (`get_many`, `import`) take a permit from
+    /// `limiter`, which may be shared with the HTTP client, for each item.
+    pub fn with_concurrency_limit(mut self, limiter: Option<Arc<Semaphore>>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Keeps a value→keys index for each of these top-level fields of stored
+    /// values, for `query_index`.
+    pub fn with_indexed_fields(mut self, fields: Vec<String>) -> Self {
+        self.indexed_fields = fields;
+        self
+    }
+
+    /// Makes `store` reject keys that do not match `pattern` in full. Fails
+    /// if `pattern` is not a valid regex.
+    pub fn with_key_pattern(mut self, pattern: Option<&str>) -> Result<Self> {
+        self.key_pattern = match pattern {
+            Some(pattern) => {
+                let regex = Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| AppError::Validation {
+                    message: format!("Invalid key pattern '{}': {}", pattern, e),
+                })?;
+                Some((pattern.to_string(), regex))
+            }
+            None => None,
+        };
+        Ok(self)
+    }
+
+    /// Trims and lowercases every key before it is checked against the key
+    /// pattern or looked up, so `" Users/Alice "` and `"users/alice"` are the
+    /// same item.
+    pub fn with_normalize_keys(mut self, normalize: bool) -> Self {
+        self.normalize_keys = normalize;
+        self
+    }
+
+    pub(crate) async fn permit(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.limiter {
+            Some(limiter) => limiter.acquire().await.ok(),
+            None => None,
+        }
+    }
+
+    /// Refuses to operate when the data directory (or the namespace
+    /// directory inside it) is a symlink, unless `follow` is set.
+    pub fn with_follow_symlinks(self, follow: bool) -> Result<Self> {
+        if !follow {
+            for dir in [&self.root_dir, &self.data_dir] {
+                if fs::symlink_metadata(dir)?.file_type().is_symlink() {
+                    return Err(AppError::Validation {
+                        message: format!(
+                            "Data directory {} is a symlink and storage.follow_symlinks is false",
+                            dir.display()
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// The item file for `key`. Keys with a `..` path segment are rejected and
+    /// separator and reserved characters are replaced; an existing file must
+    /// resolve (through any symlink) to a file directly inside the data
+    /// directory, and a dangling symlink is rejected since writing through it
+    /// would create its target.
+    fn get_file_path(&self, key: &str) -> Result<PathBuf> {
+        let key = &self.normalize_key(key);
+        if key.split(['/', '\\']).any(|segment| segment == "..") {
+            return Err(AppError::Validation {
+                message: format!("Key '{}' must not contain '..' path segments", key),
+            });
+        }
+        let safe_key = key.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|', '\0'], "_");
+        let file_path = self.data_dir.join(format!("{}.json", safe_key));
+
+        if file_path.exists() {
+            if fs::canonicalize(&file_path)?.parent() != Some(self.canonical_dir.as_path()) {
+                return Err(AppError::Validation {
+                    message: format!("Key '{}' resolves outside the data directory", key),
+                });
+            }
+        } else if fs::symlink_metadata(&file_path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            return Err(AppError::Validation {
+                message: format!("Key '{}' is a symlink to a missing file", key),
+            });
+        }
+        Ok(file_path)
+    }
+
+    fn normalize_key(&self, key: &str) -> String {
+        if self.normalize_keys {
+            key.trim().to_lowercase()
+        } else {
+            key.to_string()
+        }
+    }
+
+    /// The key an item is stored under: normalized if enabled, and checked
+    /// against the key pattern.
+    fn checked_key(&self, key: &str) -> Result<String> {
+        let key = self.normalize_key(key);
+        if let Some((pattern, regex)) = &self.key_pattern {
+            if !regex.is_match(&key) {
+                return Err(AppError::Validation {
+                    message: format!("Key '{}' does not match storage.key_pattern '{}'", key, pattern),
+                });
+            }
+        }
+        Ok(key)
     }
 
+    #[instrument(skip_all, fields(key = %key))]
     pub async fn store(&self, key: String, value: Value) -> Result<StoredItem> {
-        let file_path = self.get_file_path(&key);
+        self.store_with(key, value, |_| {}).await
+    }
+
+    /// Like `store`, but lets `prepare` set fields such as `expires_at` or
+    /// `metadata` on the item before its single write.
+    #[instrument(skip_all, fields(key = %key))]
+    pub async fn store_with<F>(&self, key: String, value: Value, prepare: F) -> Result<StoredItem>
+    where
+        F: FnOnce(&mut StoredItem),
+    {
+        let result = self.write_item(key.clone(), value, None, prepare).await;
+        self.audit("store", &key, &result);
+        self.enforce_max_items(&result).await?;
+        result
+    }
+
+    /// Stores raw bytes base64-encoded inside a `{"_rcli_binary": true,
+    /// "data": ...}` object. The size limit applies to the raw bytes rather
+    /// than the encoded form.
+    #[instrument(skip_all, fields(key = %key))]
+    pub async fn store_binary(&self, key: String, bytes: &[u8]) -> Result<StoredItem> {
+        self.store_binary_with(key, bytes, |_| {}).await
+    }
+
+    /// `store_binary` with a `prepare` step, as for `store_with`.
+    #[instrument(skip_all, fields(key = %key))]
+    pub async fn store_binary_with<F>(&self, key: String, bytes: &[u8], prepare: F) -> Result<StoredItem>
+    where
+        F: FnOnce(&mut StoredItem),
+    {
+        let value = json!({ BINARY_TAG: true, "data": STANDARD.encode(bytes) });
+        let result = self.write_item(key.clone(), value, Some(bytes.len()), prepare).await;
+        self.audit("store", &key, &result);
+        self.enforce_max_items(&result).await?;
+        result
+    }
+
+    async fn enforce_max_items(&self, stored: &Result<StoredItem>) -> Result<()> {
+        if let (Ok(_), Some(max_items)) = (stored, self.max_items) {
+            let removed = self.prune_to(max_items).await?;
+            if !removed.is_empty() {
+                info!("Evicted {} items over storage.max_items: {}", removed.len(), removed.join(", "));
+            }
+        }
+        Ok(())
+    }
+
+    async fn write_item<F>(&self, key: String, value: Value, raw_size: Option<usize>, prepare: F) -> Result<StoredItem>
+    where
+        F: FnOnce(&mut StoredItem),
+    {
+        let key = self.checked_key(&key)?;
+        let file_path = self.get_file_path(&key)?;
         
-        let item = if file_path.exists() {
-            let mut existing_item = self.get(&key).await?;
+        let (mut item, old_value) = if file_path.exists() {
+            let mut existing_item = self.read_item(&key)?;
+            let old_value = std::mem::take(&mut existing_item.value);
             existing_item.update_value(value);
-            existing_item
+            (existing_item, Some(old_value))
         } else {
-            StoredItem::new(key, value)
+            (StoredItem::new(key, value), None)
         };
+        prepare(&mut item);
 
         let json_data = serde_json::to_string_pretty(&item)?;
         
-        if json_data.len() > (self.max_file_size_mb * 1024 * 1024) as usize {
+        if raw_size.unwrap_or(json_data.len()) > (self.max_file_size_mb * 1024 * 1024) as usize {
             return Err(AppError::Validation {
                 message: format!("Data size exceeds maximum allowed size of {} MB", self.max_file_size_mb),
             });
         }
 
         fs::write(&file_path, json_data)?;
+        self.update_indexes(&item.key, old_value.as_ref(), Some(&item.value));
         info!("Stored item with key: {}", item.key);
         
         Ok(item)
     }
 
+    /// Reads an item. Items past their `expires_at` are reported as not found.
+    #[instrument(skip_all, fields(key = %key))]
     pub async fn get(&self, key: &str) -> Result<StoredItem> {
-        let file_path = self.get_file_path(key);
-        
-        if !file_path.exists() {
+        let result = self.read_item(key).and_then(unexpired);
+        self.audit("get", key, &result);
+        result
+    }
+
+    /// Reads several items on the blocking pool, at most `concurrency` at a
+    /// time. Results are returned in the same order as `keys`.
+    pub async fn get_many(&self, keys: &[String], concurrency: usize) -> Vec<Result<StoredItem>> {
+        let reads = keys.iter().map(|key| {
+            let path = self.get_file_path(key);
+            let key = key.clone();
+            async move {
+                let _permit = self.permit().await;
+                let path = path?;
+                tokio::task::spawn_blocking(move || read_item_file(&path, &key).and_then(unexpired))
+                    .await
+                    .unwrap_or_else(|e| {
+                        Err(AppError::OperationFailed {
+                            reason: format!("read task failed: {}", e),
+                        })
+                    })
+            }
+        });
+        let results: Vec<Result<StoredItem>> = stream::iter(reads).buffered(concurrency.max(1)).collect().await;
+
+        for (key, result) in keys.iter().zip(&results) {
+            self.audit("get", key, result);
+        }
+        results
+    }
+
+    fn read_item(&self, key: &str) -> Result<StoredItem> {
+        read_item_file(&self.get_file_path(key)?, key)
+    }
+
+    /// Writes an item to `path` instead of the data directory, keeping the id
+    /// and `created_at` of an item already there. The key rules and size
+    /// limit apply as for `store`, and the file is replaced atomically. Audit and indexes
+    /// only cover the data directory and are not updated.
+    #[instrument(skip_all, fields(path = %path.display()))]
+    pub async fn store_at_path(&self, path: &Path, key: String, value: Value) -> Result<StoredItem> {
+        reject_directory(path)?;
+        let key = self.checked_key(&key)?;
+        let item = if path.exists() {
+            let mut existing_item = read_item_file(path, &key)?;
+            existing_item.key = key;
+            existing_item.update_value(value);
+            existing_item
+        } else {
+            StoredItem::new(key, value)
+        };
+
+        let json_data = serde_json::to_string_pretty(&item)?;
+        if json_data.len() > (self.max_file_size_mb * 1024 * 1024) as usize {
+            return Err(AppError::Validation {
+                message: format!("Data size exceeds maximum allowed size of {} MB", self.max_file_size_mb),
+            });
+        }
+
+        write_atomic(path, json_data.as_bytes())?;
+        info!("Stored item with key {} at {}", item.key, path.display());
+        Ok(item)
+    }
+
+    /// Reads an item written by `store_at_path` (or any item file). Items
+    /// past their `expires_at` are reported as not found.
+    #[instrument(skip_all, fields(path = %path.display()))]
+    pub async fn get_at_path(&self, path: &Path) -> Result<StoredItem> {
+        reject_directory(path)?;
+        if !path.exists() {
             return Err(AppError::NotFound {
-                resource: format!("key '{}'", key),
+                resource: format!("file '{}'", path.display()),
             });
         }
+        read_item_file(path, &path.display().to_string()).and_then(unexpired)
+    }
 
-        let json_data = fs::read_to_string(&file_path)?;
-        let item: StoredItem = serde_json::from_str(&json_data)?;
-        
-        debug!("Retrieved item with key: {}", key);
+    /// Writes `value` at a JSON Pointer inside the stored value. A missing key
+    /// is an error unless `create` is set, in which case it starts empty.
+    #[instrument(skip_all, fields(key = %key))]
+    pub async fn set_pointer(&self, key: String, pointer: &str, value: Value, create: bool) -> Result<StoredItem> {
+        let mut root = match self.read_item(&key) {
+            Ok(item) => item.value,
+            Err(AppError::NotFound { .. }) if create => Value::Null,
+            Err(e) => return Err(e),
+        };
+
+        pointer::set(&mut root, pointer, value)?;
+        self.store(key, root).await
+    }
+
+    /// Sets `updated_at` to now, leaving the value, id and `created_at` as
+    /// they are.
+    #[instrument(skip_all, fields(key = %key))]
+    pub async fn touch(&self, key: &str) -> Result<StoredItem> {
+        let mut item = self.read_item(key)?;
+        item.updated_at = Utc::now();
+
+        fs::write(self.get_file_path(key)?, serde_json::to_string_pretty(&item)?)?;
+        debug!("Touched item with key: {}", key);
+        Ok(item)
+    }
+
+    /// Sets or clears the instant after which `get` treats the item as gone.
+    #[instrument(skip_all, fields(key = %key))]
+    pub async fn set_expiry(&self, key: &str, expires_at: Option<DateTime<Utc>>) -> Result<StoredItem> {
+        let mut item = self.read_item(key)?;
+        item.expires_at = expires_at;
+
+        fs::write(self.get_file_path(key)?, serde_json::to_string_pretty(&item)?)?;
+        debug!("Set expiry of key {} to {:?}", key, expires_at);
+        Ok(item)
+    }
+
+    /// Sets one metadata entry, leaving the value untouched. Bumps
+    /// `updated_at`.
+    #[instrument(skip_all, fields(key = %key))]
+    pub async fn set_metadata(&self, key: &str, name: String, value: String) -> Result<StoredItem> {
+        let result = self.update_metadata(key, |metadata| {
+            metadata.insert(name, value);
+            Ok(())
+        });
+        self.audit("set_metadata", key, &result);
+        result
+    }
+
+    /// Removes one metadata entry; a missing entry is `NotFound`.
+    #[instrument(skip_all, fields(key = %key))]
+    pub async fn delete_metadata(&self, key: &str, name: &str) -> Result<StoredItem> {
+        let result = self.update_metadata(key, |metadata| {
+            metadata.remove(name).map(|_| ()).ok_or_else(|| AppError::NotFound {
+                resource: format!("metadata '{}' on key '{}'", name, key),
+            })
+        });
+        self.audit("delete_metadata", key, &result);
+        result
+    }
+
+    fn update_metadata<F>(&self, key: &str, change: F) -> Result<StoredItem>
+    where
+        F: FnOnce(&mut HashMap<String, String>) -> Result<()>,
+    {
+        let mut item = self.read_item(key)?;
+        change(&mut item.metadata)?;
+        item.updated_at = Utc::now();
+
+        fs::write(self.get_file_path(key)?, serde_json::to_string_pretty(&item)?)?;
+        debug!("Updated metadata of key: {}", key);
         Ok(item)
     }
 
+    /// Keys of all readable items, sorted. Collects `stream_list`.
     pub async fn list(&self) -> Result<Vec<String>> {
-        let mut keys = Vec::new();
-        
-        for entry in fs::read_dir(&self.data_dir)? {
+        let mut keys: Vec<String> = self.stream_list().try_collect().await?;
+        keys.sort();
+        debug!("Listed {} keys", keys.len());
+        Ok(keys)
+    }
+
+    /// Keys of all readable items in directory order, read one entry at a
+    /// time so memory use does not grow with the size of the store. Files
+    /// that do not parse as stored items are skipped with a warning. An
+    /// error reading the directory is yielded and ends the stream.
+    pub fn stream_list(&self) -> impl Stream<Item = Result<String>> + '_ {
+        stream::try_unfold(None, move |entries: Option<tokio::fs::ReadDir>| async move {
+            let mut entries = match entries {
+                Some(entries) => entries,
+                None => tokio::fs::read_dir(&self.data_dir).await?,
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if path.extension().is_none_or(|ext| ext != "json")
+                    || !tokio::fs::metadata(&path).await.is_ok_and(|metadata| metadata.is_file())
+                {
+                    continue;
+                }
+                let Some(key) = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string) else {
+                    continue;
+                };
+                let item = match tokio::fs::read_to_string(&path).await {
+                    Ok(json_data) => parse_item(&path, &json_data),
+                    Err(e) => Err(e.into()),
+                };
+                match item {
+                    Ok(_) => return Ok(Some((key, Some(entries)))),
+                    Err(e) => warn!("Skipping {}", e),
+                }
+            }
+            Ok(None)
+        })
+    }
+
+    /// Up to `limit` keys sorted after `cursor` (from the start without one).
+    /// Only the files on the page are read, so a page costs the same however
+    /// deep into the store it is. `next` is the last key of the page when
+    /// more keys follow.
+    pub async fn list_after(&self, cursor: Option<&str>, limit: usize) -> Result<KeyPage> {
+        let mut candidates: Vec<(String, PathBuf)> = self
+            .item_paths()?
+            .into_iter()
+            .filter_map(|path| Some((path.file_stem()?.to_str()?.to_string(), path)))
+            .filter(|(key, _)| cursor.is_none_or(|cursor| key.as_str() > cursor))
+            .collect();
+        candidates.sort();
+
+        let mut page = KeyPage::default();
+        for (key, path) in candidates {
+            if let Err(e) = read_item_file(&path, &key) {
+                warn!("Skipping {}", e);
+                continue;
+            }
+            if page.keys.len() == limit {
+                page.next = page.keys.last().cloned();
+                break;
+            }
+            page.keys.push(key);
+        }
+
+        debug!("Listed {} keys after {:?}", page.keys.len(), cursor);
+        Ok(page)
+    }
+
+    /// Names of the namespaces that exist under the root data directory.
+    pub fn namespaces(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.root_dir)? {
             let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
-                if let Some(stem) = path.file_stem() {
-                    if let Some(key) = stem.to_str() {
-                        keys.push(key.to_string());
+            if entry.file_type()?.is_dir() {
+                if let Some(name) = entry.file_name().to_str() {
+                    if validate_namespace(name).is_ok() {
+                        names.push(name.to_string());
                     }
                 }
             }
         }
-        
+        names.sort();
+        Ok(names)
+    }
+
+    /// Keys whose `updated_at` falls within the inclusive bounds; a missing
+    /// bound is open-ended.
+    pub async fn list_in_range(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+
+        for path in self.item_paths()? {
+            let json_data = fs::read_to_string(&path)?;
+            match serde_json::from_str::<StoredItem>(&json_data) {
+                Ok(item)
+                    if since.is_none_or(|since| item.updated_at >= since)
+                        && until.is_none_or(|until| item.updated_at <= until) =>
+                {
+                    keys.push(item.key)
+                }
+                Ok(_) => {}
+                Err(e) => debug!("Skipping unreadable file {}: {}", path.display(), e),
+            }
+        }
+
         keys.sort();
-        debug!("Listed {} keys", keys.len());
+        debug!("Listed {} keys in range", keys.len());
         Ok(keys)
     }
 
+    #[instrument(skip_all, fields(key = %key))]
     pub async fn delete(&self, key: &str) -> Result<()> {
-        let file_path = self.get_file_path(key);
+        let result = self.remove_item(key);
+        self.audit("delete", key, &result);
+        result
+    }
+
+    fn remove_item(&self, key: &str) -> Result<()> {
+        let key = &self.normalize_key(key);
+        let file_path = self.get_file_path(key)?;
         
         if !file_path.exists() {
             return Err(AppError::NotFound {
@@ -128,13 +635,219 @@ impl Storage {
             });
         }
 
+        let old_value = self.read_item(key).ok().map(|item| item.value);
         fs::remove_file(&file_path)?;
+        self.update_indexes(key, old_value.as_ref(), None);
         info!("Deleted item with key: {}", key);
         Ok(())
     }
 
+    pub async fn find_matching<F>(&self, predicate: F) -> Result<Vec<String>>
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.scan_items(|_, item| predicate(&item.key).then_some(item.key))
+    }
+
+    /// Sorted listing keys (file stems, as returned by `list`) of the items
+    /// `predicate` accepts. Items are read one at a time, so only the
+    /// matching keys are held in memory.
+    pub async fn find_where<F>(&self, predicate: F) -> Result<Vec<String>>
+    where
+        F: Fn(&StoredItem) -> bool,
+    {
+        self.scan_items(|stem, item| predicate(&item).then(|| stem.to_string()))
+    }
+
+    /// Reads every item file and keeps what `pick` returns for it, given the
+    /// file stem and the item, sorted.
+    fn scan_items<F>(&self, pick: F) -> Result<Vec<String>>
+    where
+        F: Fn(&str, StoredItem) -> Option<String>,
+    {
+        let mut keys = Vec::new();
+
+        for path in self.item_paths()? {
+            let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let json_data = fs::read_to_string(&path)?;
+            match serde_json::from_str::<StoredItem>(&json_data) {
+                Ok(item) => keys.extend(pick(stem, item)),
+                Err(e) => debug!("Skipping unreadable file {}: {}", path.display(), e),
+            }
+        }
+
+        keys.sort();
+        Ok(keys)
+    }
+
+    pub async fn delete_matching<F>(&self, predicate: F) -> Result<usize>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let keys = self.find_matching(predicate).await?;
+
+        for key in &keys {
+            self.delete(key).await?;
+        }
+
+        info!("Deleted {} matching items", keys.len());
+        Ok(keys.len())
+    }
+
+    /// Keys that `prune_to(max_items)` would delete: the oldest items by
+    /// `created_at` (ties broken by key) beyond the newest `max_items`.
+    pub async fn oldest_beyond(&self, max_items: usize) -> Result<Vec<String>> {
+        let mut items: Vec<StoredItem> = Vec::new();
+        for path in self.item_paths()? {
+            let json_data = fs::read_to_string(&path)?;
+            match serde_json::from_str::<StoredItem>(&json_data) {
+                Ok(item) => items.push(item),
+                Err(e) => debug!("Skipping unreadable file {}: {}", path.display(), e),
+            }
+        }
+
+        let excess = items.len().saturating_sub(max_items);
+        items.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.key.cmp(&b.key)));
+        Ok(items.into_iter().take(excess).map(|item| item.key).collect())
+    }
+
+    /// Deletes the oldest items until at most `max_items` remain and returns
+    /// the removed keys, oldest first.
+    pub async fn prune_to(&self, max_items: usize) -> Result<Vec<String>> {
+        let keys = self.oldest_beyond(max_items).await?;
+        for key in &keys {
+            self.delete(key).await?;
+        }
+        Ok(keys)
+    }
+
+    /// Groups items whose values are identical, oldest first within each
+    /// group. Only groups with more than one item are returned.
+    pub async fn find_duplicates(&self) -> Result<Vec<Vec<StoredItem>>> {
+        let mut by_checksum: HashMap<String, Vec<StoredItem>> = HashMap::new();
+
+        for path in self.item_paths()? {
+            let json_data = fs::read_to_string(&path)?;
+            match serde_json::from_str::<StoredItem>(&json_data) {
+                Ok(item) => by_checksum.entry(value_checksum(&item.value)).or_default().push(item),
+                Err(e) => debug!("Skipping unreadable file {}: {}", path.display(), e),
+            }
+        }
+
+        let mut groups: Vec<Vec<StoredItem>> = by_checksum
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .map(|mut group| {
+                group.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.key.cmp(&b.key)));
+                group
+            })
+            .collect();
+        groups.sort_by(|a, b| a[0].key.cmp(&b[0].key));
+        Ok(groups)
+    }
+
+    pub async fn migrate(&self) -> Result<MigrationReport> {
+        let mut report = MigrationReport::default();
+
+        for path in self.item_paths()? {
+            let json_data = fs::read_to_string(&path)?;
+            let mut item: StoredItem = match serde_json::from_str(&json_data) {
+                Ok(item) => item,
+                Err(e) => {
+                    warn!("Cannot migrate {}: {}", path.display(), e);
+                    report.failed.push(path);
+                    continue;
+                }
+            };
+
+            if item.schema_version >= SCHEMA_VERSION {
+                report.up_to_date += 1;
+                continue;
+            }
+
+            item.schema_version = SCHEMA_VERSION;
+            fs::write(&path, serde_json::to_string_pretty(&item)?)?;
+            debug!("Migrated {}", path.display());
+            report.migrated += 1;
+        }
+
+        info!("Migrated {} items", report.migrated);
+        Ok(report)
+    }
+
+    /// Audit records, oldest first, optionally only those for `key`.
+    pub fn audit_records(&self, key: Option<&str>) -> Result<Vec<Value>> {
+        let path = self.audit_log.as_ref().ok_or_else(|| AppError::Validation {
+            message: "storage.audit_log is not configured".to_string(),
+        })?;
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut records = Vec::new();
+        for line in fs::read_to_string(path)?.lines().filter(|line| !line.trim().is_empty()) {
+            let record: Value = serde_json::from_str(line)?;
+            if key.is_none_or(|key| record["key"] == key) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    fn audit<T>(&self, operation: &str, key: &str, result: &Result<T>) {
+        let Some(path) = &self.audit_log else {
+            return;
+        };
+
+        let record = json!({
+            "timestamp": Utc::now(),
+            "operation": operation,
+            "key": key,
+            "result": match result {
+                Ok(_) => "ok".to_string(),
+                Err(e) => format!("error: {}", e),
+            },
+        });
+        let written = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", record));
+        if let Err(e) = written {
+            warn!("Failed to write audit record to {}: {}", path.display(), e);
+        }
+    }
+
+    pub(super) fn item_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+
+        for entry in fs::read_dir(&self.data_dir)? {
+            let path = entry?.path();
+
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+
+        Ok(paths)
+    }
+
+    pub async fn item_size(&self, key: &str) -> Result<u64> {
+        let file_path = self.get_file_path(key)?;
+
+        if !file_path.exists() {
+            return Err(AppError::NotFound {
+                resource: format!("key '{}'", key),
+            });
+        }
+
+        Ok(fs::metadata(&file_path)?.len())
+    }
+
     pub async fn exists(&self, key: &str) -> bool {
-        self.get_file_path(key).exists()
+        self.get_file_path(key).is_ok_and(|path| path.exists())
     }
 
     pub async fn get_metadata(&self, key: &str) -> Result<HashMap<String, String>> {
@@ -150,7 +863,7 @@ impl Storage {
             let entry = entry?;
             let path = entry.path();
             
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
                 if let Ok(metadata) = fs::metadata(&path) {
                     total_size += metadata.len();
                     file_count += 1;
@@ -167,10 +880,212 @@ impl Storage {
     }
 }
 
+/// Named settings for a `Storage`; see `Storage::builder`.
+pub struct StorageBuilder {
+    data_dir: PathBuf,
+    namespace: Option<String>,
+    max_file_size_mb: u64,
+    max_items: Option<usize>,
+    audit_log: Option<PathBuf>,
+    limiter: Option<Arc<Semaphore>>,
+    follow_symlinks: bool,
+    indexed_fields: Vec<String>,
+    key_pattern: Option<String>,
+    normalize_keys: bool,
+}
+
+impl Default for StorageBuilder {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("./data"),
+            namespace: None,
+            max_file_size_mb: 100,
+            max_items: None,
+            audit_log: None,
+            limiter: None,
+            follow_symlinks: true,
+            indexed_fields: Vec::new(),
+            key_pattern: None,
+            normalize_keys: false,
+        }
+    }
+}
+
+impl StorageBuilder {
+    pub fn data_dir(mut self, data_dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = data_dir.into();
+        self
+    }
+
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn max_file_size_mb(mut self, max_file_size_mb: u64) -> Self {
+        self.max_file_size_mb = max_file_size_mb;
+        self
+    }
+
+    /// See `Storage::with_max_items`.
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    /// See `Storage::with_audit_log`.
+    pub fn audit_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.audit_log = Some(path.into());
+        self
+    }
+
+    /// See `Storage::with_concurrency_limit`.
+    pub fn concurrency_limit(mut self, limiter: Arc<Semaphore>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// See `Storage::with_follow_symlinks`.
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// See `Storage::with_indexed_fields`.
+    pub fn indexed_fields(mut self, fields: Vec<String>) -> Self {
+        self.indexed_fields = fields;
+        self
+    }
+
+    /// See `Storage::with_key_pattern`.
+    pub fn key_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.key_pattern = Some(pattern.into());
+        self
+    }
+
+    /// See `Storage::with_normalize_keys`.
+    pub fn normalize_keys(mut self, normalize: bool) -> Self {
+        self.normalize_keys = normalize;
+        self
+    }
+
+    /// Creates the data directory if needed. Fails on an invalid namespace or
+    /// key pattern, or a symlinked data directory when `follow_symlinks` is
+    /// off.
+    pub fn build(self) -> Result<Storage> {
+        Storage::new(self.data_dir, self.namespace.as_deref(), self.max_file_size_mb)?
+            .with_audit_log(self.audit_log)
+            .with_max_items(self.max_items)
+            .with_concurrency_limit(self.limiter)
+            .with_indexed_fields(self.indexed_fields)
+            .with_normalize_keys(self.normalize_keys)
+            .with_key_pattern(self.key_pattern.as_deref())?
+            .with_follow_symlinks(self.follow_symlinks)
+    }
+}
+
+fn unexpired(item: StoredItem) -> Result<StoredItem> {
+    match item.expires_at {
+        Some(at) if item.is_expired() => Err(AppError::NotFound {
+            resource: format!("key '{}' (expired at {})", item.key, at.to_rfc3339()),
+        }),
+        _ => Ok(item),
+    }
+}
+
+pub(super) fn read_item_file(file_path: &Path, key: &str) -> Result<StoredItem> {
+    if !file_path.exists() {
+        return Err(AppError::NotFound {
+            resource: format!("key '{}'", key),
+        });
+    }
+
+    let item = parse_item(file_path, &fs::read_to_string(file_path)?)?;
+    debug!("Retrieved item with key: {}", key);
+    Ok(item)
+}
+
+fn parse_item(file_path: &Path, json_data: &str) -> Result<StoredItem> {
+    serde_json::from_str(json_data).map_err(|e| AppError::CorruptItem {
+        path: file_path.to_path_buf(),
+        reason: e.to_string(),
+    })
+}
+
+fn reject_directory(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        return Err(AppError::Validation {
+            message: format!("{} is a directory, not an item file", path.display()),
+        });
+    }
+    Ok(())
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it into
+/// place, so readers never see a partly written file.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("item");
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4()));
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })?;
+    Ok(())
+}
+
+fn validate_namespace(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(AppError::Validation {
+            message: format!(
+                "Invalid namespace '{}': use only letters, digits, '-' and '_'",
+                name
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// SHA-256 of the value's canonical JSON form (see `canonicalize`), so
+/// equal values hash equally regardless of input order.
+pub fn value_checksum(value: &Value) -> String {
+    format!("{:x}", Sha256::digest(canonicalize(value).as_bytes()))
+}
+
+/// Decodes a value written by `Storage::store_binary`.
+pub fn decode_binary(value: &Value) -> Result<Vec<u8>> {
+    let data = value
+        .get(BINARY_TAG)
+        .filter(|tag| tag.as_bool() == Some(true))
+        .and_then(|_| value.get("data"))
+        .and_then(Value::as_str)
+        .ok_or_else(|| AppError::Validation {
+            message: "Stored value is not binary data".to_string(),
+        })?;
+
+    STANDARD.decode(data).map_err(|e| AppError::Validation {
+        message: format!("Invalid base64 data: {}", e),
+    })
+}
+
 #[derive(Debug, Serialize)]
 pub struct StorageInfo {
     pub data_dir: PathBuf,
     pub file_count: u32,
     pub total_size_bytes: u64,
     pub max_file_size_mb: u64,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Default)]
+pub struct KeyPage {
+    pub keys: Vec<String>,
+    pub next: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub migrated: usize,
+    pub up_to_date: usize,
+    pub failed: Vec<PathBuf>,
+}
@@ -0,0 +1,178 @@
+use crate::{AppError, Result};
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use serde_json::{Map, Value};
+
+const ROOT: &str = "root";
+const ARRAY_ITEM: &str = "item";
+
+/// Serializes a value as XML under a `<root>` element. Object keys become
+/// element names and array elements become repeated `<item>` elements.
+/// Keys that are not valid XML names cannot be mapped and are an error.
+pub fn to_xml(value: &Value) -> Result<String> {
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    write_element(&mut writer, ROOT, value)?;
+    Ok(String::from_utf8(writer.into_inner()).expect("quick-xml writes valid UTF-8"))
+}
+
+/// Parses XML produced by `to_xml` (or shaped like it) back into a value.
+/// Text is read as strings, empty elements as null, elements whose children
+/// are all `<item>` as arrays and repeated child names as arrays. Attributes
+/// and mixed text/element content are rejected.
+pub fn from_xml(input: &str) -> Result<Value> {
+    let mut reader = Reader::from_str(input);
+    reader.config_mut().trim_text(true);
+
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut root = None;
+
+    loop {
+        match reader.read_event().map_err(invalid_xml)? {
+            Event::Start(start) => {
+                reject_attributes(&start)?;
+                stack.push(Frame::new(element_name(&start)));
+            }
+            Event::Empty(start) => {
+                reject_attributes(&start)?;
+                let name = element_name(&start);
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push((name, Value::Null)),
+                    None => root = Some(Value::Null),
+                }
+            }
+            Event::Text(text) => {
+                if let Some(frame) = stack.last_mut() {
+                    frame.text.push_str(&text.unescape().map_err(invalid_xml)?);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(frame) = stack.last_mut() {
+                    frame.text.push_str(&String::from_utf8_lossy(&data));
+                }
+            }
+            Event::End(_) => {
+                let frame = stack.pop().expect("reader checks that end tags match");
+                let name = frame.name.clone();
+                let value = frame.into_value()?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push((name, value)),
+                    None => root = Some(value),
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    root.ok_or_else(|| AppError::Validation {
+        message: "Invalid XML: no root element".to_string(),
+    })
+}
+
+fn write_element(writer: &mut Writer<Vec<u8>>, name: &str, value: &Value) -> Result<()> {
+    if !is_xml_name(name) {
+        return Err(AppError::Validation {
+            message: format!("Cannot map key '{}' to an XML element name", name),
+        });
+    }
+
+    let is_empty = match value {
+        Value::Null => true,
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => false,
+    };
+    if is_empty {
+        writer.write_event(Event::Empty(BytesStart::new(name)))?;
+        return Ok(());
+    }
+
+    writer.write_event(Event::Start(BytesStart::new(name)))?;
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                write_element(writer, ARRAY_ITEM, item)?;
+            }
+        }
+        Value::Object(map) => {
+            for (key, child) in map {
+                write_element(writer, key, child)?;
+            }
+        }
+        Value::String(text) => writer.write_event(Event::Text(BytesText::new(text)))?,
+        scalar => writer.write_event(Event::Text(BytesText::new(&scalar.to_string())))?,
+    }
+    writer.write_event(Event::End(BytesEnd::new(name)))?;
+    Ok(())
+}
+
+struct Frame {
+    name: String,
+    text: String,
+    children: Vec<(String, Value)>,
+}
+
+impl Frame {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            text: String::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn into_value(self) -> Result<Value> {
+        if self.children.is_empty() {
+            return Ok(if self.text.is_empty() { Value::Null } else { Value::String(self.text) });
+        }
+        if !self.text.is_empty() {
+            return Err(AppError::Validation {
+                message: format!("Cannot map mixed text and element content in <{}>", self.name),
+            });
+        }
+
+        if self.children.iter().all(|(name, _)| name == ARRAY_ITEM) {
+            return Ok(Value::Array(self.children.into_iter().map(|(_, value)| value).collect()));
+        }
+
+        let mut map = Map::new();
+        for (name, value) in self.children {
+            match map.get_mut(&name) {
+                Some(Value::Array(repeated)) => repeated.push(value),
+                Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+                None => {
+                    map.insert(name, value);
+                }
+            }
+        }
+        Ok(Value::Object(map))
+    }
+}
+
+fn element_name(start: &BytesStart) -> String {
+    String::from_utf8_lossy(start.name().as_ref()).into_owned()
+}
+
+fn reject_attributes(start: &BytesStart) -> Result<()> {
+    if start.attributes().next().is_some() {
+        return Err(AppError::Validation {
+            message: format!(
+                "Cannot map XML attributes on <{}>",
+                String::from_utf8_lossy(start.name().as_ref())
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn invalid_xml(e: impl std::fmt::Display) -> AppError {
+    AppError::Validation {
+        message: format!("Invalid XML: {}", e),
+    }
+}
@@ -0,0 +1,135 @@
+use rust_advanced_cli::batch::{failed_path, fetch_many, manifest_path, FetchManyOptions};
+use rust_advanced_cli::http::HttpClient;
+use rust_advanced_cli::storage::Storage;
+use serde_json::json;
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn test_fetch_many_resumes_after_interruption_without_refetching() {
+    let mut server = mockito::Server::new_async().await;
+    let mut fast = Vec::new();
+    for i in 0..3 {
+        let mock = server
+            .mock("GET", format!("/items/{}", i).as_str())
+            .with_body(format!(r#"{{"id": {}}}"#, i))
+            .expect(1)
+            .create_async()
+            .await;
+        fast.push(mock);
+    }
+    // The last URL is held on the server until the test releases it.
+    let (arrived_tx, mut arrived_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+    let release_rx = std::sync::Mutex::new(release_rx);
+    let slow = server
+        .mock("GET", "/items/3")
+        .with_chunked_body(move |w| {
+            let _ = arrived_tx.send(());
+            let _ = release_rx.lock().unwrap().recv();
+            w.write_all(br#"{"id": 3}"#)
+        })
+        .create_async()
+        .await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().join("data"), None, 10).unwrap();
+    let client = HttpClient::new(server.url(), 30, 1).unwrap();
+    let list = temp_dir.path().join("urls.txt");
+    std::fs::write(&list, "# crawl\n/items/0\n/items/1\n\n/items/2\n/items/3\n").unwrap();
+    let options = FetchManyOptions { resume: false, retry_failed: false, concurrency: 1 };
+
+    // The run is cut off once the last URL has reached the server.
+    tokio::select! {
+        _ = fetch_many(&client, &storage, &list, &options) => panic!("fetch-many finished while a request was held"),
+        _ = arrived_rx.recv() => {}
+    }
+    drop(release_tx);
+    let manifest = std::fs::read_to_string(manifest_path(&list)).unwrap();
+    assert_eq!(manifest.lines().collect::<Vec<_>>(), ["/items/0", "/items/1", "/items/2"]);
+
+    // Starting over without --resume is refused while the manifest exists.
+    assert!(fetch_many(&client, &storage, &list, &options).await.is_err());
+
+    slow.remove_async().await;
+    server.mock("GET", "/items/3").with_body(r#"{"id": 3}"#).expect(1).create_async().await;
+    let resume = FetchManyOptions { resume: true, ..options };
+    let report = fetch_many(&client, &storage, &list, &resume).await.unwrap();
+    assert_eq!(report.fetched, 1);
+    assert_eq!(report.already_done, 3);
+    assert!(report.failed.is_empty());
+    assert!(!manifest_path(&list).exists());
+    assert!(!failed_path(&list).exists());
+
+    for mock in fast {
+        mock.assert_async().await;
+    }
+    assert_eq!(storage.get("/items/3").await.unwrap().value, json!({"id": 3}));
+    assert_eq!(storage.list().await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_fetch_many_retry_failed_fetches_only_failures() {
+    let mut server = mockito::Server::new_async().await;
+    let ok = server.mock("GET", "/ok").with_body("1").expect(1).create_async().await;
+    let broken = server.mock("GET", "/flaky").with_status(404).expect(1).create_async().await;
+    let pending = server.mock("GET", "/later").with_body("3").expect(0).create_async().await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().join("data"), None, 10).unwrap();
+    let client = HttpClient::new(server.url(), 30, 1).unwrap();
+    let list = temp_dir.path().join("urls.txt");
+    std::fs::write(&list, "/ok\n/flaky\n").unwrap();
+    let options = FetchManyOptions { resume: false, retry_failed: false, concurrency: 2 };
+
+    let report = fetch_many(&client, &storage, &list, &options).await.unwrap();
+    assert_eq!(report.fetched, 1);
+    assert_eq!(report.failed.len(), 1);
+    assert!(std::fs::read_to_string(failed_path(&list)).unwrap().contains(r#""url":"/flaky""#));
+
+    // A URL added since is not part of the retry.
+    std::fs::write(&list, "/ok\n/flaky\n/later\n").unwrap();
+    broken.remove_async().await;
+    let fixed = server.mock("GET", "/flaky").with_body("2").expect(1).create_async().await;
+    let retry = FetchManyOptions { retry_failed: true, ..options };
+    let report = fetch_many(&client, &storage, &list, &retry).await.unwrap();
+    assert_eq!(report.fetched, 1);
+    assert!(report.failed.is_empty());
+    assert!(!failed_path(&list).exists());
+    assert!(manifest_path(&list).exists());
+
+    ok.assert_async().await;
+    fixed.assert_async().await;
+    pending.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_fetch_many_stops_retrying_once_budget_is_spent() {
+    let mut server = mockito::Server::new_async().await;
+    // Three attempts would be allowed per URL; the budget covers one retry.
+    let first = server.mock("GET", "/a").with_status(503).expect(2).create_async().await;
+    let later: Vec<_> = futures::future::join_all(
+        ["/b", "/c"].map(|path| server.mock("GET", path).with_status(503).expect(1).create_async()),
+    )
+    .await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().join("data"), None, 10).unwrap();
+    let client = HttpClient::builder()
+        .base_url(server.url())
+        .retry_attempts(3)
+        .max_retries_total(1)
+        .build()
+        .unwrap();
+    let list = temp_dir.path().join("urls.txt");
+    std::fs::write(&list, "/a\n/b\n/c\n").unwrap();
+    let options = FetchManyOptions { resume: false, retry_failed: false, concurrency: 1 };
+
+    let report = fetch_many(&client, &storage, &list, &options).await.unwrap();
+    assert_eq!(report.fetched, 0);
+    assert_eq!(report.failed.len(), 3);
+
+    first.assert_async().await;
+    for mock in later {
+        mock.assert_async().await;
+    }
+}
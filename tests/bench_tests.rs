@@ -0,0 +1,37 @@
+use rust_advanced_cli::bench::{self, percentile};
+use rust_advanced_cli::http::HttpClient;
+
+#[test]
+fn test_percentile_nearest_rank() {
+    let sorted: Vec<f64> = (1..=10).map(f64::from).collect();
+
+    assert_eq!(percentile(&sorted, 50.0), 5.0);
+    assert_eq!(percentile(&sorted, 90.0), 9.0);
+    assert_eq!(percentile(&sorted, 99.0), 10.0);
+    assert_eq!(percentile(&[3.0], 50.0), 3.0);
+}
+
+#[tokio::test]
+async fn test_bench_reports_every_request() {
+    let mut server = mockito::Server::new_async().await;
+    let ok = server.mock("GET", "/items").with_body("[]").expect(25).create_async().await;
+    let broken = server.mock("GET", "/broken").with_status(500).expect(5).create_async().await;
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+
+    let report = bench::run(&client, "/items", 25, 4).await;
+    assert_eq!((report.requests, report.succeeded, report.failed), (25, 25, 0));
+    assert_eq!(report.error_rate, 0.0);
+    assert!(report.requests_per_second > 0.0);
+    let latency = report.latency.unwrap();
+    assert!(latency.min > 0.0);
+    assert!(latency.min <= latency.p50 && latency.p50 <= latency.p90);
+    assert!(latency.p90 <= latency.p99 && latency.p99 <= latency.max);
+    assert!(latency.max < report.elapsed_ms);
+
+    let report = bench::run(&client, "/broken", 5, 2).await;
+    assert_eq!((report.requests, report.failed, report.error_rate), (5, 5, 1.0));
+    assert!(report.latency.is_none());
+
+    ok.assert_async().await;
+    broken.assert_async().await;
+}
@@ -0,0 +1,110 @@
+use mockito::Matcher;
+use rust_advanced_cli::cache::ResponseCache;
+use rust_advanced_cli::http::{HttpClient, RequestOptions};
+use serde_json::json;
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn test_cache_revalidates_with_etag() {
+    let mut server = mockito::Server::new_async().await;
+    let first = server
+        .mock("GET", "/report")
+        .match_header("if-none-match", Matcher::Missing)
+        .with_header("etag", "\"v1\"")
+        .with_header("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT")
+        .with_body(r#"{"rows": 3}"#)
+        .expect(1)
+        .create_async()
+        .await;
+    let revalidated = server
+        .mock("GET", "/report")
+        .match_header("if-none-match", "\"v1\"")
+        .match_header("if-modified-since", "Wed, 21 Oct 2015 07:28:00 GMT")
+        .with_status(304)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let cache = ResponseCache::new(temp_dir.path().to_path_buf()).unwrap();
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+
+    assert_eq!(cache.fetch(&client, "/report", &RequestOptions::default()).await.unwrap(), json!({"rows": 3}));
+    assert_eq!(cache.fetch(&client, "/report", &RequestOptions::default()).await.unwrap(), json!({"rows": 3}));
+
+    first.assert_async().await;
+    revalidated.assert_async().await;
+    let entry = cache.get(&format!("{}/report", server.url())).unwrap().unwrap();
+    assert_eq!(entry.etag.as_deref(), Some("\"v1\""));
+}
+
+#[tokio::test]
+async fn test_cache_skips_responses_without_validators() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/live")
+        .with_body("[1]")
+        .create_async()
+        .await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let cache = ResponseCache::new(temp_dir.path().to_path_buf()).unwrap();
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+
+    assert_eq!(cache.fetch(&client, "/live", &RequestOptions::default()).await.unwrap(), json!([1]));
+    assert!(cache.get(&format!("{}/live", server.url())).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_cache_entries_and_clear() {
+    let mut server = mockito::Server::new_async().await;
+    for path in ["/a", "/b"] {
+        server
+            .mock("GET", path)
+            .with_header("etag", "\"1\"")
+            .with_body("{}")
+            .create_async()
+            .await;
+    }
+
+    let temp_dir = TempDir::new().unwrap();
+    let cache = ResponseCache::new(temp_dir.path().to_path_buf()).unwrap();
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+    cache.fetch(&client, "/b", &RequestOptions::default()).await.unwrap();
+    cache.fetch(&client, "/a", &RequestOptions::default()).await.unwrap();
+
+    let urls: Vec<String> = cache.entries().unwrap().into_iter().map(|entry| entry.url).collect();
+    assert_eq!(urls, vec![format!("{}/a", server.url()), format!("{}/b", server.url())]);
+
+    assert!(cache.remove(&urls[0]).unwrap());
+    assert!(!cache.remove(&urls[0]).unwrap());
+    assert_eq!(cache.clear().unwrap(), 1);
+    assert!(cache.entries().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_cache_parses_ndjson_bodies() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/events")
+        .match_header("if-none-match", Matcher::Missing)
+        .with_header("content-type", "application/x-ndjson")
+        .with_header("etag", "\"v1\"")
+        .with_body("{\"id\": 1}\n{\"id\": 2}\n")
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/events")
+        .match_header("if-none-match", "\"v1\"")
+        .with_status(304)
+        .create_async()
+        .await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let cache = ResponseCache::new(temp_dir.path().to_path_buf()).unwrap();
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+
+    let expected = json!([{"id": 1}, {"id": 2}]);
+    assert_eq!(cache.fetch(&client, "/events", &RequestOptions::default()).await.unwrap(), expected);
+    assert_eq!(cache.fetch(&client, "/events", &RequestOptions::default()).await.unwrap(), expected);
+}
@@ -0,0 +1,16 @@
+#![cfg(feature = "clipboard")]
+
+use rust_advanced_cli::{clipboard, AppError};
+
+#[test]
+fn test_clipboard_feature_is_wired() {
+    assert!(clipboard::is_supported());
+
+    // Headless environments have no clipboard; that must surface as a
+    // readable error rather than a panic.
+    match clipboard::copy("{\"ok\": true}") {
+        Ok(()) => {}
+        Err(AppError::OperationFailed { reason }) => assert!(reason.contains("clipboard")),
+        Err(e) => panic!("unexpected error: {}", e),
+    }
+}
@@ -0,0 +1,26 @@
+use rust_advanced_cli::color::{red, ColorChoice};
+
+#[test]
+fn test_color_choice_auto_follows_terminal() {
+    assert!(ColorChoice::Auto.resolve(false, true));
+    assert!(!ColorChoice::Auto.resolve(false, false));
+}
+
+#[test]
+fn test_color_choice_always_and_never_ignore_terminal() {
+    assert!(ColorChoice::Always.resolve(false, false));
+    assert!(!ColorChoice::Never.resolve(false, true));
+}
+
+#[test]
+fn test_no_color_overrides_every_choice() {
+    assert!(!ColorChoice::Always.resolve(true, true));
+    assert!(!ColorChoice::Auto.resolve(true, true));
+    assert!(!ColorChoice::Never.resolve(true, true));
+}
+
+#[test]
+fn test_red_only_wraps_when_enabled() {
+    assert_eq!(red("Error", true), "\x1b[31mError\x1b[0m");
+    assert_eq!(red("Error", false), "Error");
+}
@@ -0,0 +1,154 @@
+use rust_advanced_cli::config::{expand_path, AppConfig, AuthConfig, ConfigFormat, HmacAlgorithm, HmacConfig};
+use rust_advanced_cli::AppError;
+use std::path::{Path, PathBuf};
+
+#[test]
+fn test_expand_path_home() {
+    let home = dirs::home_dir().unwrap();
+
+    assert_eq!(expand_path(Path::new("~/rcli-data")), home.join("rcli-data"));
+    assert_eq!(expand_path(Path::new("~")), home);
+    assert_eq!(expand_path(Path::new("./~data")), PathBuf::from("./~data"));
+}
+
+#[test]
+fn test_expand_path_env_vars() {
+    std::env::set_var("RCLI_TEST_EXPAND_BASE", "/srv/rcli");
+
+    assert_eq!(
+        expand_path(Path::new("$RCLI_TEST_EXPAND_BASE/data")),
+        PathBuf::from("/srv/rcli/data")
+    );
+    assert_eq!(
+        expand_path(Path::new("${RCLI_TEST_EXPAND_BASE}_logs/app.log")),
+        PathBuf::from("/srv/rcli_logs/app.log")
+    );
+    assert_eq!(
+        expand_path(Path::new("$RCLI_TEST_EXPAND_UNSET/data")),
+        PathBuf::from("$RCLI_TEST_EXPAND_UNSET/data")
+    );
+}
+
+#[test]
+fn test_validate_reports_every_invalid_field() {
+    let mut config = AppConfig::default();
+    config.server.timeout_seconds = 0;
+    config.storage.max_file_size_mb = 0;
+
+    let err = config.validate().unwrap_err();
+    match &err {
+        AppError::InvalidFields { issues, .. } => assert_eq!(issues.len(), 2),
+        other => panic!("unexpected error: {}", other),
+    }
+
+    let message = err.to_string();
+    assert!(message.contains("\n  - server.timeout_seconds: must be greater than 0"));
+    assert!(message.contains("\n  - storage.max_file_size_mb: must be greater than 0"));
+}
+
+#[test]
+fn test_validate_accepts_defaults() {
+    assert!(AppConfig::default().validate().is_ok());
+}
+
+#[test]
+fn test_validate_rejects_malformed_resolve_entry() {
+    let mut config = AppConfig::default();
+    config.server.resolve = vec!["api.example.com:443:127.0.0.1".to_string(), "api.example.com:443".to_string()];
+
+    let message = config.validate().unwrap_err().to_string();
+    assert!(message.contains("server.resolve: Invalid resolve entry 'api.example.com:443'"));
+}
+
+#[test]
+fn test_render_redacted_masks_secrets() {
+    let mut config = AppConfig::default();
+    config.server.hmac = Some(HmacConfig {
+        secret: "s3cr3t".to_string(),
+        header: "X-Signature".to_string(),
+        timestamp_header: "X-Timestamp".to_string(),
+        algorithm: HmacAlgorithm::HmacSha256,
+    });
+    config
+        .profiles
+        .insert("ci".to_string(), serde_json::json!({"server": {"token": "abc123"}}));
+
+    for format in [ConfigFormat::Json, ConfigFormat::Yaml, ConfigFormat::Toml] {
+        let plain = config.render(format, false).unwrap();
+        assert!(plain.contains("s3cr3t"), "{}", plain);
+
+        let redacted = config.render(format, true).unwrap();
+        assert!(!redacted.contains("s3cr3t") && !redacted.contains("abc123"), "{}", redacted);
+        assert!(redacted.contains("<redacted>"), "{}", redacted);
+        assert!(redacted.contains("X-Signature"), "{}", redacted);
+    }
+}
+
+#[test]
+fn test_sigv4_auth_config() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("config.yaml");
+    let auth = "server:\n  auth:\n    mode: sigv4\n    region: eu-west-1\n    secret_access_key: hunter2\n";
+    let yaml = serde_yaml::to_string(&AppConfig::default()).unwrap().replacen("server:\n", auth, 1);
+    std::fs::write(&path, yaml).unwrap();
+
+    let mut config = AppConfig::load_from_file(&path, None).unwrap();
+    let Some(AuthConfig::Sigv4(sigv4)) = &config.server.auth else {
+        panic!("expected sigv4 auth, got {:?}", config.server.auth);
+    };
+    assert_eq!(sigv4.region.as_deref(), Some("eu-west-1"));
+    assert_eq!(sigv4.service, "s3");
+    assert!(!format!("{:?}", sigv4).contains("hunter2"));
+    assert!(!config.render(ConfigFormat::Yaml, true).unwrap().contains("hunter2"));
+
+    config.server.hmac = Some(HmacConfig {
+        secret: "s".to_string(),
+        header: "X-Signature".to_string(),
+        timestamp_header: "X-Timestamp".to_string(),
+        algorithm: HmacAlgorithm::HmacSha256,
+    });
+    let message = config.validate().unwrap_err().to_string();
+    assert!(message.contains("server.auth: cannot be combined with server.hmac"), "{}", message);
+}
+
+#[test]
+fn test_bearer_auth_config_needs_one_token_source() {
+    let auth = "server:\n  auth:\n    mode: bearer\n    token: hunter2\n";
+    let yaml = serde_yaml::to_string(&AppConfig::default()).unwrap().replacen("server:\n", auth, 1);
+    let mut config = AppConfig::load_from_str(&yaml, None).unwrap();
+    assert!(!format!("{:?}", config.server.auth).contains("hunter2"));
+    assert!(!config.render(ConfigFormat::Yaml, true).unwrap().contains("hunter2"));
+
+    let Some(AuthConfig::Bearer(bearer)) = &mut config.server.auth else {
+        panic!("expected bearer auth, got {:?}", config.server.auth);
+    };
+    bearer.token_command = Some("print-token".to_string());
+    let message = config.validate().unwrap_err().to_string();
+    assert!(message.contains("exactly one of token, token_file or token_command"), "{}", message);
+}
+
+#[test]
+fn test_invalid_fields_fall_back_to_defaults() {
+    let text = "server:\n  base_url: https://api.test\n  timeout_seconds: soon\n  retry_attempts: [1]\nstorage:\n  data_dir: /srv/rcli\n  max_file_size_mb: 5\n";
+
+    let config = AppConfig::load_from_str(text, None).unwrap();
+
+    let fields: Vec<&str> = config.fallbacks.iter().map(|fallback| fallback.field.as_str()).collect();
+    assert_eq!(fields, vec!["server.retry_attempts", "server.timeout_seconds"]);
+    assert!(config.fallbacks[1].reason.contains("invalid type"));
+    assert_eq!(config.server.base_url, "https://api.test");
+    assert_eq!(config.server.timeout_seconds, 30);
+    assert_eq!(config.server.retry_attempts, 3);
+    assert_eq!(config.storage.data_dir, PathBuf::from("/srv/rcli"));
+    assert_eq!(config.storage.max_file_size_mb, 5);
+}
+
+#[test]
+fn test_partial_config_fills_in_defaults() {
+    let config = AppConfig::load_from_str("server:\n  timeout_seconds: 5\n", None).unwrap();
+
+    assert!(config.fallbacks.is_empty());
+    assert_eq!(config.server.timeout_seconds, 5);
+    assert_eq!(config.server.base_url, AppConfig::default().server.base_url);
+    assert_eq!(config.storage.max_file_size_mb, 100);
+}
@@ -0,0 +1,27 @@
+use rust_advanced_cli::diff::{json_diff, Change};
+use serde_json::json;
+
+#[test]
+fn test_json_diff_reports_nested_changes_in_key_order() {
+    let old = json!({"server": {"timeout_seconds": 30, "user_agent": "a"}, "tags": [1]});
+    let new = json!({"server": {"timeout_seconds": 60, "retries": 2}, "tags": [1, 2]});
+
+    let rendered: Vec<String> = json_diff(&old, &new).iter().map(ToString::to_string).collect();
+
+    assert_eq!(
+        rendered,
+        vec![
+            "+ server.retries: 2",
+            "~ server.timeout_seconds: 30 -> 60",
+            "- server.user_agent: \"a\"",
+            "~ tags: [1] -> [1,2]",
+        ]
+    );
+}
+
+#[test]
+fn test_json_diff_of_equal_documents_is_empty() {
+    let value = json!({"a": {"b": [1, {"c": null}]}});
+
+    assert_eq!(json_diff(&value, &value), Vec::<Change>::new());
+}
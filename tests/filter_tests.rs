@@ -0,0 +1,54 @@
+use rust_advanced_cli::filter::{Condition, Op};
+use serde_json::json;
+
+#[test]
+fn test_parse_condition() {
+    let condition = Condition::parse("/status==active").unwrap();
+    assert_eq!(condition.pointer, "/status");
+    assert_eq!(condition.op, Op::Eq);
+    assert_eq!(condition.value, json!("active"));
+
+    let condition = Condition::parse("/tags contains \"a b\"").unwrap();
+    assert_eq!((condition.op, condition.value), (Op::Contains, json!("a b")));
+
+    assert_eq!(Condition::parse("/n!=3").unwrap().value, json!(3));
+    assert!(Condition::parse("/status").is_err());
+    assert!(Condition::parse("status==active").is_err());
+}
+
+#[test]
+fn test_equality_conditions() {
+    let value = json!({"status": "active", "owner": {"id": 7}, "flag": null});
+
+    assert!(Condition::parse("/status==active").unwrap().matches(&value));
+    assert!(Condition::parse("/status==\"active\"").unwrap().matches(&value));
+    assert!(Condition::parse("/owner/id==7").unwrap().matches(&value));
+    assert!(Condition::parse("/flag==null").unwrap().matches(&value));
+    assert!(!Condition::parse("/status==inactive").unwrap().matches(&value));
+    assert!(Condition::parse("/status!=inactive").unwrap().matches(&value));
+    assert!(!Condition::parse("/missing==1").unwrap().matches(&value));
+    assert!(Condition::parse("/missing!=1").unwrap().matches(&value));
+}
+
+#[test]
+fn test_numeric_and_contains_conditions() {
+    let value = json!({"age": 42, "score": 7.5, "name": "octocat", "tags": ["a", 1]});
+
+    assert!(Condition::parse("/age>30").unwrap().matches(&value));
+    assert!(!Condition::parse("/age<30").unwrap().matches(&value));
+    assert!(Condition::parse("/score<7.6").unwrap().matches(&value));
+    assert!(!Condition::parse("/name>30").unwrap().matches(&value));
+    assert!(Condition::parse("/name>octo").unwrap().matches(&value));
+
+    assert_eq!(Condition::parse("/age>=42").unwrap().op, Op::Ge);
+    assert!(Condition::parse("/age>=42").unwrap().matches(&value));
+    assert!(!Condition::parse("/age>=43").unwrap().matches(&value));
+    assert!(Condition::parse("/age<=42").unwrap().matches(&value));
+    assert!(!Condition::parse("/score<=7").unwrap().matches(&value));
+    assert!(!Condition::parse("/name<=42").unwrap().matches(&value));
+
+    assert!(Condition::parse("/tags contains a").unwrap().matches(&value));
+    assert!(Condition::parse("/tags contains 1").unwrap().matches(&value));
+    assert!(Condition::parse("/name contains cat").unwrap().matches(&value));
+    assert!(Condition::parse(" contains age").unwrap().matches(&value));
+}
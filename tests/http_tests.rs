@@ -0,0 +1,812 @@
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use mockito::Matcher;
+use rust_advanced_cli::config::{BearerConfig, HmacAlgorithm, HmacConfig, SigV4Config};
+use rust_advanced_cli::har::HarRecorder;
+use rust_advanced_cli::http::{parse_resolve, Download, HttpClient, RequestOptions};
+use rust_advanced_cli::signing::{sigv4_canonical_request, BearerToken, HmacSigner, SigV4Signer};
+use rust_advanced_cli::AppError;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_rate_limit_spaces_requests() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/items")
+        .with_body(r#"{"ok": true}"#)
+        .expect(6)
+        .create_async()
+        .await;
+
+    let client = HttpClient::new(server.url(), 5, 1).unwrap().with_rate_limit(5);
+
+    let start = Instant::now();
+    for _ in 0..6 {
+        client.fetch_json("/items").await.unwrap();
+    }
+
+    // The first request passes immediately, the remaining five wait 200ms each.
+    assert!(start.elapsed() >= Duration::from_millis(950));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_rate_limit_disabled_with_zero() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/items")
+        .with_body(r#"{"ok": true}"#)
+        .create_async()
+        .await;
+
+    let client = HttpClient::new(server.url(), 5, 1).unwrap().with_rate_limit(0);
+
+    let start = Instant::now();
+    for _ in 0..6 {
+        client.fetch_json("/items").await.unwrap();
+    }
+
+    assert!(start.elapsed() < Duration::from_millis(950));
+}
+
+const UUID_PATTERN: &str = "^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$";
+
+#[tokio::test]
+async fn test_default_user_agent_and_request_id() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/items")
+        .match_header(
+            "user-agent",
+            format!("rust-advanced-cli/{}", env!("CARGO_PKG_VERSION")).as_str(),
+        )
+        .match_header("x-request-id", mockito::Matcher::Regex(UUID_PATTERN.to_string()))
+        .with_body("{}")
+        .create_async()
+        .await;
+
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+    client.fetch_json("/items").await.unwrap();
+
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_user_agent_override() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/items")
+        .match_header("user-agent", "custom-agent/2.0")
+        .match_header("x-request-id", mockito::Matcher::Regex(UUID_PATTERN.to_string()))
+        .with_body("{}")
+        .create_async()
+        .await;
+
+    let client = HttpClient::new(server.url(), 5, 1)
+        .unwrap()
+        .with_user_agent(Some("custom-agent/2.0".to_string()));
+    client.fetch_json("/items").await.unwrap();
+
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_builder_applies_settings() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/items")
+        .match_header("user-agent", "builder-agent/1.0")
+        .with_status(500)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = HttpClient::builder()
+        .base_url(server.url())
+        .timeout_seconds(5)
+        .retry_attempts(3)
+        .retry_on_status(Vec::new())
+        .user_agent("builder-agent/1.0")
+        .build()
+        .unwrap();
+    assert!(client.fetch_json("/items").await.is_err());
+    mock.assert_async().await;
+
+    let invalid = HttpClient::builder().resolve(vec!["no-port".to_string()]).build();
+    assert!(matches!(invalid, Err(AppError::Validation { .. })));
+}
+
+#[tokio::test]
+async fn test_circuit_breaker_transitions() {
+    let mut server = mockito::Server::new_async().await;
+    let failing = server
+        .mock("GET", "/items")
+        .with_status(503)
+        .expect(3)
+        .create_async()
+        .await;
+
+    let cooldown = Duration::from_millis(300);
+    let client = HttpClient::new(server.url(), 5, 1)
+        .unwrap()
+        .with_circuit_breaker(2, cooldown);
+
+    // Closed: failures are counted until the threshold opens the circuit.
+    for _ in 0..2 {
+        let err = client.fetch_json("/items").await.unwrap_err();
+        assert!(err.to_string().contains("HTTP 503"));
+    }
+
+    // Open: requests fail without reaching the server.
+    let err = client.fetch_json("/items").await.unwrap_err();
+    assert!(err.to_string().contains("circuit open"));
+
+    // Half-open: a failing probe opens the circuit again.
+    tokio::time::sleep(cooldown).await;
+    let err = client.fetch_json("/items").await.unwrap_err();
+    assert!(err.to_string().contains("HTTP 503"));
+    let err = client.fetch_json("/items").await.unwrap_err();
+    assert!(err.to_string().contains("circuit open"));
+    failing.assert_async().await;
+    failing.remove_async().await;
+
+    // A successful probe closes it.
+    let healthy = server
+        .mock("GET", "/items")
+        .with_body(r#"{"ok": true}"#)
+        .expect(3)
+        .create_async()
+        .await;
+    tokio::time::sleep(cooldown).await;
+    for _ in 0..3 {
+        client.fetch_json("/items").await.unwrap();
+    }
+    healthy.assert_async().await;
+}
+
+fn gzip(body: &str) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body.as_bytes()).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[tokio::test]
+async fn test_gzip_response_is_decoded() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/large")
+        .match_header("accept-encoding", mockito::Matcher::Regex("gzip".to_string()))
+        .with_header("content-encoding", "gzip")
+        .with_body(gzip(r#"{"items": [1, 2, 3]}"#))
+        .create_async()
+        .await;
+
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+    let value = client.fetch_json("/large").await.unwrap();
+
+    assert_eq!(value, serde_json::json!({"items": [1, 2, 3]}));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_deflate_ndjson_response_is_decoded() {
+    let mut server = mockito::Server::new_async().await;
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(b"{\"id\": 1}\n{\"id\": 2}\n").unwrap();
+    let mock = server
+        .mock("GET", "/events")
+        .with_header("content-encoding", "deflate")
+        .with_header("content-type", "application/x-ndjson")
+        .with_body(encoder.finish().unwrap())
+        .create_async()
+        .await;
+
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+    let value = client.fetch_json("/events").await.unwrap();
+
+    assert_eq!(value, serde_json::json!([{"id": 1}, {"id": 2}]));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_compression_can_be_disabled() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/large")
+        .match_header("accept-encoding", mockito::Matcher::Missing)
+        .with_body(r#"{"items": []}"#)
+        .create_async()
+        .await;
+
+    let client = HttpClient::new(server.url(), 5, 1)
+        .unwrap()
+        .with_compression(false)
+        .unwrap();
+    client.fetch_json("/large").await.unwrap();
+
+    mock.assert_async().await;
+}
+
+fn hmac_config() -> HmacConfig {
+    HmacConfig {
+        secret: "topsecret".to_string(),
+        header: "X-Signature".to_string(),
+        timestamp_header: "X-Timestamp".to_string(),
+        algorithm: HmacAlgorithm::HmacSha256,
+    }
+}
+
+#[test]
+fn test_hmac_signature_known_vector() {
+    let signer = HmacSigner::new(&hmac_config()).unwrap();
+
+    assert_eq!(
+        signer.signature("POST", "/webhooks?v=1", 1_700_000_000, br#"{"event":"created"}"#),
+        "8fc1b5ed3c84e5c2c6abfaf10d0e949dec7b98be6915be1a3948c32384a12afa"
+    );
+}
+
+#[test]
+fn test_hmac_config_debug_hides_secret() {
+    let debug = format!("{:?}", hmac_config());
+
+    assert!(!debug.contains("topsecret"));
+    assert!(debug.contains("<redacted>"));
+}
+
+#[tokio::test]
+async fn test_signed_request_headers() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/webhooks")
+        .match_header("x-signature", mockito::Matcher::Regex("^[0-9a-f]{64}$".to_string()))
+        .match_header("x-timestamp", mockito::Matcher::Regex("^[0-9]+$".to_string()))
+        .with_body("{}")
+        .create_async()
+        .await;
+
+    let client = HttpClient::new(server.url(), 5, 1)
+        .unwrap()
+        .with_signer(Some(HmacSigner::new(&hmac_config()).unwrap()));
+    client
+        .post_json("/webhooks", &serde_json::json!({"event": "created"}))
+        .await
+        .unwrap();
+
+    mock.assert_async().await;
+}
+
+fn sigv4_config(service: &str) -> SigV4Config {
+    SigV4Config {
+        access_key_id: Some("AKIDEXAMPLE".to_string()),
+        secret_access_key: Some("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string()),
+        session_token: None,
+        region: Some("us-east-1".to_string()),
+        service: service.to_string(),
+    }
+}
+
+#[test]
+fn test_sigv4_get_vanilla_test_vector() {
+    // `get-vanilla` from the AWS SigV4 test suite.
+    let headers = [
+        ("Host".to_string(), "example.amazonaws.com".to_string()),
+        ("X-Amz-Date".to_string(), "20150830T123600Z".to_string()),
+    ];
+    let empty_hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    let (canonical, signed_headers) = sigv4_canonical_request("GET", "/", &[], &headers, empty_hash);
+
+    assert_eq!(
+        canonical,
+        format!("GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\nhost;x-amz-date\n{}", empty_hash)
+    );
+    assert_eq!(signed_headers, "host;x-amz-date");
+
+    let signer = SigV4Signer::new(&sigv4_config("service")).unwrap();
+    let now = chrono::DateTime::parse_from_rfc3339("2015-08-30T12:36:00Z").unwrap().with_timezone(&chrono::Utc);
+    assert_eq!(
+        signer.signature(&canonical, now),
+        "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+    );
+}
+
+#[test]
+fn test_sigv4_canonical_query_is_sorted_and_encoded() {
+    let query = [
+        ("prefix".to_string(), "photos/2024 q1".to_string()),
+        ("list-type".to_string(), "2".to_string()),
+    ];
+    let (canonical, _) = sigv4_canonical_request("GET", "/bucket", &query, &[], "UNSIGNED-PAYLOAD");
+
+    assert_eq!(canonical.lines().nth(2), Some("list-type=2&prefix=photos%2F2024%20q1"));
+}
+
+#[tokio::test]
+async fn test_sigv4_request_headers() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/bucket/report.json")
+        .match_header(
+            "authorization",
+            Matcher::Regex(
+                "^AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/[0-9]{8}/us-east-1/s3/aws4_request, \
+                 SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature=[0-9a-f]{64}$"
+                    .to_string(),
+            ),
+        )
+        .match_header("x-amz-date", Matcher::Regex("^[0-9]{8}T[0-9]{6}Z$".to_string()))
+        .with_body("{}")
+        .create_async()
+        .await;
+
+    let signer = SigV4Signer::new(&sigv4_config("s3")).unwrap();
+    assert!(!format!("{:?}", signer).contains("EXAMPLEKEY"));
+    let client = HttpClient::new(server.url(), 5, 1).unwrap().with_sigv4(Some(signer));
+    client.fetch_json("/bucket/report.json").await.unwrap();
+
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_builder_sets_auth() {
+    let mut server = mockito::Server::new_async().await;
+    let hmac = server
+        .mock("POST", "/webhooks")
+        .match_header("x-signature", Matcher::Regex("^[0-9a-f]{64}$".to_string()))
+        .with_body("{}")
+        .expect(1)
+        .create_async()
+        .await;
+    let sigv4 = server
+        .mock("GET", "/bucket/report.json")
+        .match_header("authorization", Matcher::Regex("^AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/".to_string()))
+        .with_body("{}")
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = HttpClient::builder()
+        .base_url(server.url())
+        .hmac(HmacSigner::new(&hmac_config()).unwrap())
+        .build()
+        .unwrap();
+    client.post_json("/webhooks", &serde_json::json!({})).await.unwrap();
+
+    let client = HttpClient::builder()
+        .base_url(server.url())
+        .sigv4(SigV4Signer::new(&sigv4_config("s3")).unwrap())
+        .build()
+        .unwrap();
+    client.fetch_json("/bucket/report.json").await.unwrap();
+
+    hmac.assert_async().await;
+    sigv4.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_har_records_fetch() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/users/1")
+        .with_header("content-type", "application/json")
+        .with_header("set-cookie", "session=abc")
+        .with_body(r#"{"id": 1}"#)
+        .create_async()
+        .await;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let har_path = temp_dir.path().join("session.har");
+    let client = HttpClient::new(server.url(), 5, 1)
+        .unwrap()
+        .with_har(Some(HarRecorder::new(har_path.clone(), &["Set-Cookie".to_string()])));
+
+    let value = client.fetch_json("/users/1").await.unwrap();
+    assert_eq!(value, serde_json::json!({"id": 1}));
+
+    let har: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&har_path).unwrap()).unwrap();
+    assert_eq!(har["log"]["version"], "1.2");
+    let entries = har["log"]["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["request"]["method"], "GET");
+    assert_eq!(entries[0]["request"]["url"], format!("{}/users/1", server.url()));
+    assert_eq!(entries[0]["response"]["status"], 200);
+    assert_eq!(entries[0]["response"]["content"]["text"], r#"{"id": 1}"#);
+
+    let cookie = entries[0]["response"]["headers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|header| header["name"] == "set-cookie")
+        .unwrap();
+    assert_eq!(cookie["value"], "<redacted>");
+}
+
+#[derive(serde::Deserialize, Debug, PartialEq)]
+struct User {
+    id: u64,
+    login: String,
+    admin: bool,
+}
+
+#[tokio::test]
+async fn test_fetch_as_deserializes_into_struct() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/users/7")
+        .with_body(r#"{"id": 7, "login": "octocat", "admin": false, "extra": [1, 2]}"#)
+        .create_async()
+        .await;
+
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+    let user: User = client.fetch_as("/users/7").await.unwrap();
+
+    assert_eq!(user, User { id: 7, login: "octocat".to_string(), admin: false });
+}
+
+#[tokio::test]
+async fn test_resolve_override_routes_hostname_to_local_server() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/status")
+        .match_header("host", Matcher::Regex("^api\\.example\\.test".to_string()))
+        .with_body(r#"{"ok": true}"#)
+        .create_async()
+        .await;
+    let port = server.socket_address().port();
+
+    let client = HttpClient::new(format!("http://api.example.test:{}", port), 5, 1)
+        .unwrap()
+        .with_resolve(&[format!("api.example.test:{}:127.0.0.1", port)])
+        .unwrap();
+
+    assert_eq!(client.fetch_json("/status").await.unwrap(), serde_json::json!({"ok": true}));
+    mock.assert_async().await;
+}
+
+#[test]
+fn test_parse_resolve_rejects_malformed_entries() {
+    let (host, addr) = parse_resolve("api.example.com:443:[::1]").unwrap();
+    assert_eq!(host, "api.example.com");
+    assert_eq!(addr, "[::1]:443".parse().unwrap());
+
+    for entry in ["api.example.com", "api.example.com:https:127.0.0.1", ":443:127.0.0.1", "api.example.com:443:localhost"] {
+        let err = parse_resolve(entry).unwrap_err();
+        assert!(err.to_string().contains("expected HOST:PORT:ADDRESS"), "{}", err);
+    }
+}
+
+const ASSET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+#[tokio::test]
+async fn test_fetch_to_file_resumes_partial_download() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/asset.bin")
+        .match_header("range", "bytes=10-")
+        .with_status(206)
+        .with_header("content-range", &format!("bytes 10-{}/{}", ASSET.len() - 1, ASSET.len()))
+        .with_body(&ASSET[10..])
+        .create_async()
+        .await;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("asset.bin");
+    std::fs::write(&path, &ASSET[..10]).unwrap();
+
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+    let download = client.fetch_to_file("/asset.bin", &[], &path, true).await.unwrap();
+
+    assert_eq!(download, Download { written: ASSET.len() as u64 - 10, resumed_from: 10 });
+    assert_eq!(std::fs::read(&path).unwrap(), ASSET);
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_fetch_to_file_restarts_when_range_is_ignored() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/asset.bin")
+        .with_body(ASSET)
+        .create_async()
+        .await;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("asset.bin");
+    std::fs::write(&path, &ASSET[..10]).unwrap();
+
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+    let download = client.fetch_to_file("/asset.bin", &[], &path, true).await.unwrap();
+
+    assert_eq!(download, Download { written: ASSET.len() as u64, resumed_from: 0 });
+    assert_eq!(std::fs::read(&path).unwrap(), ASSET);
+}
+
+#[tokio::test]
+async fn test_fetch_to_file_rejects_partial_content_without_range() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/asset.bin")
+        .with_status(206)
+        .with_body(&ASSET[20..])
+        .create_async()
+        .await;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("asset.bin");
+    std::fs::write(&path, &ASSET[..10]).unwrap();
+
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+    for resume in [true, false] {
+        let err = client.fetch_to_file("/asset.bin", &[], &path, resume).await.unwrap_err();
+        assert!(matches!(err, AppError::OperationFailed { .. }), "{:?}", err);
+    }
+    assert_eq!(std::fs::read(&path).unwrap(), &ASSET[..10]);
+}
+
+/// Echoes each multipart part as `{name, filename, content_type, size}`.
+fn echo_parts(request: &mockito::Request) -> Vec<u8> {
+    let content_type = request.header("content-type")[0].to_str().unwrap().to_string();
+    let boundary = format!("--{}", content_type.split("boundary=").nth(1).unwrap());
+    let body = request.utf8_lossy_body().unwrap();
+
+    let parts: Vec<serde_json::Value> = body
+        .split(boundary.as_str())
+        .filter_map(|part| part.strip_prefix("\r\n"))
+        .map(|part| {
+            let (head, content) = part.split_once("\r\n\r\n").unwrap();
+            let content = content.strip_suffix("\r\n").unwrap_or(content);
+            let attribute = |key: &str| {
+                head.split(&format!("{}=\"", key))
+                    .nth(1)
+                    .map(|rest| rest.split('"').next().unwrap().to_string())
+            };
+            let part_type = head
+                .lines()
+                .find_map(|line| line.strip_prefix("Content-Type: "))
+                .map(str::to_string);
+            serde_json::json!({
+                "name": attribute(" name"),
+                "filename": attribute("filename"),
+                "content_type": part_type,
+                "size": content.len(),
+            })
+        })
+        .collect();
+    serde_json::to_vec(&parts).unwrap()
+}
+
+#[tokio::test]
+async fn test_post_multipart_sends_fields_and_files() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/attachments")
+        .match_header("content-type", Matcher::Regex("^multipart/form-data; boundary=".to_string()))
+        .with_body_from_request(echo_parts)
+        .create_async()
+        .await;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("notes.txt");
+    std::fs::write(&path, "twelve bytes").unwrap();
+
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+    let response = client
+        .post_multipart(
+            "/attachments",
+            &[("title".to_string(), "Q3".to_string())],
+            &[("attachment".to_string(), path)],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response,
+        serde_json::json!([
+            {"name": "title", "filename": null, "content_type": null, "size": 2},
+            {"name": "attachment", "filename": "notes.txt", "content_type": "text/plain", "size": 12},
+        ])
+    );
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_concurrency_limit_bounds_in_flight_requests() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Answers every connection after a pause, tracking how many overlap.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (server_in_flight, server_peak) = (in_flight.clone(), peak.clone());
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let (in_flight, peak) = (server_in_flight.clone(), server_peak.clone());
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                let _ = socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}")
+                    .await;
+            });
+        }
+    });
+
+    let client = HttpClient::new(base_url, 5, 1)
+        .unwrap()
+        .with_concurrency_limit(Some(Arc::new(tokio::sync::Semaphore::new(2))));
+    let paths: Vec<String> = (0..8).map(|i| format!("/items/{}", i)).collect();
+    for result in futures::future::join_all(paths.iter().map(|path| client.fetch_json(path))).await {
+        result.unwrap();
+    }
+
+    assert_eq!(peak.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_ndjson_body_parsed_into_array() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/events")
+        .with_header("content-type", "application/x-ndjson; charset=utf-8")
+        .with_body("{\"id\": 1}\n\n{\"id\": 2, \"tags\": [\"a\"]}\n{\"id\": 3}")
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/export")
+        .with_header("content-type", "text/plain")
+        .with_body("1\n\"two\"\n")
+        .create_async()
+        .await;
+    server
+        .mock("GET", "/broken")
+        .with_header("content-type", "application/x-ndjson")
+        .with_body("{\"id\": 1}\n{oops\n")
+        .create_async()
+        .await;
+
+    let client = HttpClient::new(server.url(), 5, 1).unwrap();
+    assert_eq!(
+        client.fetch_json("/events").await.unwrap(),
+        serde_json::json!([{"id": 1}, {"id": 2, "tags": ["a"]}, {"id": 3}])
+    );
+
+    let forced = RequestOptions { ndjson: true, ..Default::default() };
+    let value = client.request_json(reqwest::Method::GET, "/export", None, &forced).await.unwrap();
+    assert_eq!(value, serde_json::json!([1, "two"]));
+
+    let err = client.fetch_json("/broken").await.unwrap_err();
+    assert!(err.to_string().contains("Invalid NDJSON on line 2"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_timeout_and_refused_connection_errors() {
+    // Accepts connections but never answers.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let slow_url = format!("http://{}/slow", listener.local_addr().unwrap());
+    let client = HttpClient::new(slow_url.clone(), 1, 1).unwrap();
+
+    let start = Instant::now();
+    match client.fetch_json(&slow_url).await {
+        Err(AppError::Timeout { url, after_seconds }) => assert_eq!((url, after_seconds), (slow_url.clone(), 1)),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert!(matches!(client.fetch_with_meta(&slow_url).await, Err(AppError::Timeout { .. })));
+
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let closed_url = format!("http://{}/", closed.local_addr().unwrap());
+    drop(closed);
+    let err = client.fetch_json(&closed_url).await.unwrap_err();
+    assert!(matches!(err, AppError::Connection { .. }), "{:?}", err);
+    assert_eq!((err.code(), err.exit_code()), ("CONNECTION_ERROR", 3));
+    drop(listener);
+}
+
+#[tokio::test]
+async fn test_connect_timeout_is_separate_from_total_timeout() {
+    // A listener whose accept queue is full never completes another
+    // handshake, so connecting hangs until the connect timeout fires.
+    let socket = tokio::net::TcpSocket::new_v4().unwrap();
+    socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let full = socket.listen(1).unwrap();
+    let address = full.local_addr().unwrap();
+    let queued: Vec<_> = (0..16)
+        .map_while(|_| std::net::TcpStream::connect_timeout(&address, Duration::from_millis(200)).ok())
+        .collect();
+
+    let client = HttpClient::new(format!("http://{}", address), 30, 1)
+        .unwrap()
+        .with_connect_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    let start = Instant::now();
+    let err = client.fetch_json("/unreachable").await.unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(5), "took {:?}", start.elapsed());
+    assert!(matches!(err, AppError::Timeout { after_seconds: 1, .. }), "{:?}", err);
+    drop(queued);
+
+    // A connected server that is slow to answer gets the full total timeout.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let slow_url = format!("http://{}/slow", listener.local_addr().unwrap());
+    let client = HttpClient::builder().timeout_seconds(2).connect_timeout_seconds(1).retry_attempts(1).build().unwrap();
+    let start = Instant::now();
+    let err = client.fetch_json(&slow_url).await.unwrap_err();
+    assert!(matches!(err, AppError::Timeout { after_seconds: 2, .. }), "{:?}", err);
+    assert!(start.elapsed() >= Duration::from_millis(1900));
+    drop(listener);
+}
+
+#[tokio::test]
+async fn test_retry_wait_budget_too_small_for_any_retry() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server.mock("GET", "/flaky").with_status(503).expect(2).create_async().await;
+    // The first retry would wait 1s, which is already over the budget.
+    let client = HttpClient::new(server.url(), 30, 3)
+        .unwrap()
+        .with_retry_budget(None, Some(Duration::from_millis(500)));
+
+    let started = Instant::now();
+    for _ in 0..2 {
+        assert!(client.fetch_json("/flaky").await.is_err());
+    }
+    assert!(started.elapsed() < Duration::from_millis(900));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_bearer_token_from_file() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/me")
+        .match_header("authorization", "Bearer from-file")
+        .with_body("{}")
+        .create_async()
+        .await;
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let token_file = temp_dir.path().join("token");
+    std::fs::write(&token_file, "from-file\n").unwrap();
+
+    let bearer = BearerConfig { token_file: Some(token_file), ..Default::default() };
+    let client = HttpClient::new(server.url(), 5, 1).unwrap().with_bearer(Some(BearerToken::new(&bearer)));
+    client.fetch_json("/me").await.unwrap();
+    mock.assert_async().await;
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_bearer_token_from_command_runs_once() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/me")
+        .match_header("authorization", "Bearer from-command")
+        .with_body("{}")
+        .expect(2)
+        .create_async()
+        .await;
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let runs = temp_dir.path().join("runs");
+    let command = format!("echo run >> '{}'; sleep 0.2; echo '  from-command  '", runs.display());
+
+    let bearer = BearerConfig { token_command: Some(command), ..Default::default() };
+    let client = HttpClient::new(server.url(), 5, 1).unwrap().with_bearer(Some(BearerToken::new(&bearer)));
+    assert!(!runs.exists());
+    // Both requests start before the token is known and share one run.
+    let (first, second) = tokio::join!(client.fetch_json("/me"), client.fetch_json("/me"));
+    first.unwrap();
+    second.unwrap();
+    mock.assert_async().await;
+    assert_eq!(std::fs::read_to_string(&runs).unwrap(), "run\n");
+
+    let failing = BearerConfig { token_command: Some("echo nope >&2; exit 3".to_string()), ..Default::default() };
+    let client = HttpClient::new(server.url(), 5, 1).unwrap().with_bearer(Some(BearerToken::new(&failing)));
+    let message = client.fetch_json("/me").await.unwrap_err().to_string();
+    assert!(message.contains("token_command exited") && message.contains("nope"), "{}", message);
+}
@@ -1,8 +1,18 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
 use std::fs;
+use std::path::Path;
 use tempfile::TempDir;
 
+/// `rcli` with its storage isolated in `data_dir`.
+fn rcli(data_dir: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir);
+    cmd
+}
+
 #[test]
 fn test_cli_help() {
     let mut cmd = Command::cargo_bin("rcli").unwrap();
@@ -28,6 +38,128 @@ fn test_config_init() {
     assert!(content.contains("storage:"));
 }
 
+#[test]
+fn test_config_set_dry_run_leaves_file_untouched() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.yaml");
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--no-config", "config", "init", "--output", config_path.to_str().unwrap()])
+        .assert()
+        .success();
+    let before = fs::read_to_string(&config_path).unwrap();
+
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--config", config_path.to_str().unwrap(), "config", "set", "server.timeout_seconds", "60", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("~ server.timeout_seconds: 30 -> 60"));
+    assert_eq!(fs::read_to_string(&config_path).unwrap(), before);
+
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--config", config_path.to_str().unwrap(), "config", "set", "server.timeout_seconds", "60"])
+        .assert()
+        .success();
+    assert!(fs::read_to_string(&config_path).unwrap().contains("timeout_seconds: 60"));
+
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--config", config_path.to_str().unwrap(), "config", "set", "sever.timeout", "60"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown config key 'sever'"));
+
+    // Only dots separate segments; `/` and `~` are part of the key.
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--config", config_path.to_str().unwrap(), "config", "set", "profiles.a/b~c", "{}"])
+        .assert()
+        .success();
+    let written: serde_yaml::Value = serde_yaml::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+    assert!(written["profiles"]["a/b~c"].is_mapping(), "{:?}", written["profiles"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_config_edit_checks_and_saves_edited_file() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.yaml");
+    fs::write(&config_path, "server:\n  timeout_seconds: 30\n").unwrap();
+    let edit = |contents: &str, dry_run: bool| {
+        let script = temp_dir.path().join("editor.sh");
+        fs::write(&script, format!("#!/bin/sh\nprintf '{}' > \"$1\"\n", contents)).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let mut cmd = Command::cargo_bin("rcli").unwrap();
+        cmd.env_remove("VISUAL")
+            .env("EDITOR", &script)
+            .args(["--config", config_path.to_str().unwrap(), "config", "edit"]);
+        if dry_run {
+            cmd.arg("--dry-run");
+        }
+        cmd
+    };
+
+    edit("server:\\n  timeout_seconds: 90\\n", true)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("~ server.timeout_seconds: 30 -> 90"));
+    assert_eq!(fs::read_to_string(&config_path).unwrap(), "server:\n  timeout_seconds: 30\n");
+
+    edit("sever:\\n  timeout_seconds: 90\\n", false)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown config key 'sever'"));
+    assert_eq!(fs::read_to_string(&config_path).unwrap(), "server:\n  timeout_seconds: 30\n");
+
+    edit("server:\\n  timeout_seconds: 90\\n", false).assert().success();
+    assert_eq!(fs::read_to_string(&config_path).unwrap(), "server:\n  timeout_seconds: 90\n");
+}
+
+#[test]
+fn test_malformed_server_section_only_blocks_commands_that_need_it() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.yaml");
+    let data_dir = temp_dir.path().join("data");
+    fs::write(
+        &config_path,
+        format!("server:\n  timeout_seconds: soon\nstorage:\n  data_dir: {}\n", data_dir.display()),
+    )
+    .unwrap();
+
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--config", config_path.to_str().unwrap(), "config", "show"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(data_dir.to_str().unwrap()))
+        .stderr(predicate::str::contains("fell back to their defaults"))
+        .stderr(predicate::str::contains("server.timeout_seconds"));
+
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--config", config_path.to_str().unwrap(), "store", "k", "1"])
+        .assert()
+        .failure();
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--config", config_path.to_str().unwrap(), "list"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Config field server.timeout_seconds is invalid"));
+    assert!(data_dir.is_dir());
+
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--debug-errors", "--config", config_path.to_str().unwrap(), "fetch", "http://127.0.0.1:9/x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("server.timeout_seconds: invalid type"));
+}
+
 #[test]
 fn test_config_show() {
     let mut cmd = Command::cargo_bin("rcli").unwrap();
@@ -61,25 +193,2131 @@ fn test_store_and_get() {
 }
 
 #[test]
-fn test_list_empty() {
+fn test_get_uses_configured_default_format() {
     let temp_dir = TempDir::new().unwrap();
     let data_dir = temp_dir.path().join("data");
-    
+    let yaml_default = |args: &[&str]| {
+        let mut cmd = rcli(&data_dir);
+        cmd.env("RCLI_OUTPUT__DEFAULT_FORMAT", "yaml").args(args);
+        cmd
+    };
+    yaml_default(&["store", "user", r#"{"name": "Ada"}"#]).assert().success();
+
+    yaml_default(&["get", "user"]).assert().success().stdout("name: Ada\n\n");
+    yaml_default(&["get", "user", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#"{"name":"Ada"}"#));
+}
+
+#[test]
+fn test_store_unflatten_and_get_flatten() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    rcli(&data_dir)
+        .args(["store", "user", r#"{"name.first": "Ada", "tags.0": "admin"}"#, "--unflatten"])
+        .assert()
+        .success();
+    rcli(&data_dir)
+        .args(["get", "user", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#"{"name":{"first":"Ada"},"tags":["admin"]}"#));
+    rcli(&data_dir)
+        .args(["get", "user", "--flatten", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#"{"name.first":"Ada","tags.0":"admin"}"#));
+}
+
+#[test]
+fn test_color_flag_gates_error_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
     let mut cmd = Command::cargo_bin("rcli").unwrap();
     cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
-        .args(&["list"]);
+        .env_remove("NO_COLOR")
+        .args(["--color", "always", "--debug-errors", "get", "missing"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("\x1b[31mError\x1b[0m"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .env_remove("NO_COLOR")
+        .args(["--color", "never", "--debug-errors", "get", "missing"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("\x1b[").not());
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .env("NO_COLOR", "1")
+        .args(["--color", "always", "--debug-errors", "get", "missing"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("\x1b[").not());
+}
+
+#[test]
+fn test_get_sort_keys() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["store", "nested", r#"{"b": {"z": 1, "y": 2}, "a": 3}"#]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "nested", "--format", "json", "--sort-keys"]);
     cmd.assert()
         .success()
-        .stdout(predicate::str::contains("No stored items found"));
+        .stdout(predicate::str::contains(r#"{"a":3,"b":{"y":2,"z":1}}"#));
 }
 
 #[test]
-fn test_delete_nonexistent() {
+fn test_store_max_size_override() {
     let temp_dir = TempDir::new().unwrap();
     let data_dir = temp_dir.path().join("data");
-    
+    let file = temp_dir.path().join("large.json");
+    std::fs::write(&file, format!("\"{}\"", "x".repeat(1_500_000))).unwrap();
+
     let mut cmd = Command::cargo_bin("rcli").unwrap();
     cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
-        .args(&["delete", "nonexistent_key"]);
+        .env("RCLI_STORAGE__MAX_FILE_SIZE_MB", "1")
+        .args(["store", "large", file.to_str().unwrap(), "--file"]);
+    cmd.assert().failure();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .env("RCLI_STORAGE__MAX_FILE_SIZE_MB", "1")
+        .args(["store", "large", file.to_str().unwrap(), "--file", "--max-size-mb", "2"]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["store", "small", "1", "--max-size-mb", "0"]);
+    cmd.assert().failure();
+}
+
+#[test]
+fn test_logs_prints_last_lines() {
+    let temp_dir = TempDir::new().unwrap();
+    let log_file = temp_dir.path().join("rcli.log");
+    std::fs::write(&log_file, "one\ntwo\nthree\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", temp_dir.path().join("data").to_str().unwrap())
+        .env("RCLI_LOGGING__FILE_PATH", log_file.to_str().unwrap())
+        .args(["logs", "-n", "2"]);
+    cmd.assert()
+        .success()
+        .stdout("two\nthree\n");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", temp_dir.path().join("data").to_str().unwrap())
+        .args(["--debug-errors", "logs"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No log file configured"));
+}
+
+#[test]
+fn test_namespace_flag_isolates_keys() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    for (namespace, value) in [("proj1", "1"), ("proj2", "2")] {
+        let mut cmd = Command::cargo_bin("rcli").unwrap();
+        cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+            .args(["--namespace", namespace, "store", "k", value]);
+        cmd.assert().success();
+    }
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["--namespace", "proj2", "get", "k", "--format", "json"]);
+    cmd.assert().success().stdout("2\n");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "k"]);
     cmd.assert().failure();
-}
\ No newline at end of file
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .arg("namespaces");
+    cmd.assert().success().stdout("proj1\nproj2\n");
+}
+
+#[test]
+fn test_get_corrupt_file_reports_friendly_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    std::fs::create_dir_all(&data_dir).unwrap();
+    std::fs::write(data_dir.join("broken.json"), "{ definitely not json").unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["--debug-errors", "get", "broken"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Corrupt stored item"))
+        .stderr(predicate::str::contains("broken.json"))
+        .stderr(predicate::str::contains("rcli migrate"));
+}
+
+#[test]
+fn test_store_yaml_and_toml_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let yaml_file = temp_dir.path().join("settings.yaml");
+    std::fs::write(&yaml_file, "name: app\nports:\n  - 80\n  - 443\n").unwrap();
+    let toml_file = temp_dir.path().join("Cargo.toml");
+    std::fs::write(&toml_file, "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+    let yaml_as_txt = temp_dir.path().join("settings.txt");
+    std::fs::write(&yaml_as_txt, "enabled: true\n").unwrap();
+
+    for (key, file, extra) in [
+        ("yaml", &yaml_file, None),
+        ("toml", &toml_file, None),
+        ("forced", &yaml_as_txt, Some("yaml")),
+    ] {
+        let mut cmd = Command::cargo_bin("rcli").unwrap();
+        cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+            .args(["store", key, file.to_str().unwrap(), "--file"]);
+        if let Some(format) = extra {
+            cmd.args(["--input-format", format]);
+        }
+        cmd.assert().success();
+    }
+
+    for (key, expected) in [
+        ("yaml", r#"{"name":"app","ports":[80,443]}"#),
+        ("toml", r#"{"package":{"name":"demo","version":"0.1.0"}}"#),
+        ("forced", r#"{"enabled":true}"#),
+    ] {
+        let mut cmd = Command::cargo_bin("rcli").unwrap();
+        cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+            .args(["get", key, "--format", "json"]);
+        cmd.assert().success().stdout(format!("{}\n", expected));
+    }
+}
+
+#[test]
+fn test_store_canonical_values_are_identical() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    let inputs = [
+        ("spaced", "{ \"b\" : [1,  2],\n \"a\": {\"z\": null, \"y\": 1} }"),
+        ("reordered", "{\"b\":[1,2],\n\t\"a\":{\"z\":null,  \"y\":1}}"),
+    ];
+    for (key, value) in inputs {
+        let mut cmd = Command::cargo_bin("rcli").unwrap();
+        cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+            .args(["store", key, value, "--canonical"]);
+        cmd.assert().success();
+    }
+
+    let stored_value = |key: &str| {
+        let text = std::fs::read_to_string(data_dir.join(format!("{}.json", key))).unwrap();
+        let item: serde_json::Value = serde_json::from_str(&text).unwrap();
+        serde_json::to_string(&item["value"]).unwrap()
+    };
+    assert_eq!(stored_value("spaced"), stored_value("reordered"));
+    assert_eq!(stored_value("reordered"), r#"{"a":{"y":1,"z":null},"b":[1,2]}"#);
+}
+
+#[test]
+fn test_meta_set_get_delete() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    rcli(&data_dir).args(["store", "report", r#"{"rows": 3}"#]).assert().success();
+    rcli(&data_dir).args(["meta", "report", "set", "owner", "ops"]).assert().success();
+    rcli(&data_dir).args(["meta", "report", "get", "owner"]).assert().success().stdout("ops\n");
+    rcli(&data_dir).args(["meta", "report", "list"]).assert().success().stdout("owner\n");
+    rcli(&data_dir).args(["meta", "report", "delete", "owner"]).assert().success();
+    rcli(&data_dir).args(["meta", "report", "get", "owner"]).assert().failure();
+    rcli(&data_dir).args(["meta", "report", "get"]).assert().success().stdout("{}\n");
+    rcli(&data_dir).args(["get", "report", "--format", "json"]).assert().success().stdout("{\"rows\":3}\n");
+}
+
+#[test]
+fn test_deadline_aborts_slow_fetch() {
+    // Accepts connections but never answers.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/slow", listener.local_addr().unwrap());
+
+    let start = std::time::Instant::now();
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_SERVER__TIMEOUT_SECONDS", "60")
+        .args(["--deadline", "1", "--output-json", "fetch", &url]);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("deadline exceeded"))
+        .stdout(predicate::str::contains("OPERATION_FAILED"));
+    assert!(start.elapsed() < std::time::Duration::from_secs(30));
+    drop(listener);
+}
+
+#[test]
+fn test_deadline_covers_reading_config_from_stdin() {
+    use assert_cmd::prelude::*;
+    use std::process::Stdio;
+
+    let mut child = std::process::Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--deadline", "1", "--output-json", "--config", "-", "config", "show"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // Held open without writing, so reading the config never finishes.
+    let _stdin = child.stdin.take();
+
+    let start = std::time::Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if start.elapsed() > std::time::Duration::from_secs(30) {
+            child.kill().unwrap();
+            panic!("rcli kept waiting on stdin past its deadline");
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    let output = child.wait_with_output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("deadline exceeded"));
+}
+
+#[test]
+fn test_timeout_exit_code() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/slow", listener.local_addr().unwrap());
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_SERVER__TIMEOUT_SECONDS", "1")
+        .args(["--retries", "1", "--output-json", "fetch", &url]);
+    cmd.assert()
+        .code(4)
+        .stdout(predicate::str::contains("\"code\":\"TIMEOUT\""))
+        .stdout(predicate::str::contains("timed out after 1s"));
+    drop(listener);
+}
+
+#[test]
+fn test_completions_install_writes_to_home() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("HOME", home)
+        .env_remove("XDG_DATA_HOME")
+        .args(["--no-config", "completions", "--shell", "bash", "--install"]);
+    let expected = home.join(".local/share/bash-completion/completions/rcli");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(expected.to_str().unwrap()));
+
+    let script = std::fs::read_to_string(&expected).unwrap();
+    assert!(script.contains("_rcli()"), "{}", script);
+}
+
+#[test]
+fn test_completions_install_rejects_unsupported_shell() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("HOME", temp_dir.path())
+        .args(["--no-config", "--debug-errors", "completions", "--shell", "elvish", "--install"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No conventional completion directory for elvish"));
+}
+
+#[test]
+fn test_config_show_json() {
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--no-config", "config", "show", "--format", "json"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+
+    let config: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    for section in ["server", "logging", "storage"] {
+        assert!(config[section].is_object(), "missing {}: {}", section, config);
+    }
+    assert_eq!(config["server"]["base_url"], "https://api.example.com");
+}
+
+#[test]
+fn test_config_from_stdin() {
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--no-config", "config", "show"]);
+    let yaml = String::from_utf8(cmd.assert().success().get_output().stdout.clone())
+        .unwrap()
+        .replace("https://api.example.com", "https://stdin.example.com");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--config", "-", "config", "show"]).write_stdin(yaml);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("base_url: https://stdin.example.com"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--no-config", "config", "show", "--format", "json"]);
+    let json = String::from_utf8(cmd.assert().success().get_output().stdout.clone())
+        .unwrap()
+        .replace("https://api.example.com", "https://json.example.com");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--config", "-", "config", "show"]).write_stdin(json);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("base_url: https://json.example.com"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--config", "-", "--debug-errors", "config", "show"]).write_stdin("  \n");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Configuration from stdin is empty"));
+}
+
+#[test]
+fn test_store_expire_at() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    rcli(&data_dir)
+        .args(["--debug-errors", "store", "old", "1", "--expire-at", "2001-01-01T00:00:00Z"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already in the past"));
+    rcli(&data_dir).args(["store", "old", "1", "--expire-at", "tomorrow"]).assert().failure();
+
+    rcli(&data_dir)
+        .args(["store", "token", r#""abc""#, "--expire-at", "2999-01-01T00:00:00+02:00"])
+        .assert()
+        .success();
+    rcli(&data_dir).args(["get", "token", "--format", "json"]).assert().success().stdout("\"abc\"\n");
+    let stored = std::fs::read_to_string(data_dir.join("token.json")).unwrap();
+    assert!(stored.contains(r#""expires_at": "2998-12-31T22:00:00Z""#), "{}", stored);
+}
+
+#[test]
+fn test_list_where_filters_by_value() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    for (key, value) in [
+        ("alice", r#"{"status": "active", "age": 34}"#),
+        ("bob", r#"{"status": "inactive", "age": 51}"#),
+        ("carol", r#"{"status": "active", "age": 27}"#),
+        ("users/1", r#"{"status": "active", "age": 45}"#),
+    ] {
+        rcli(&data_dir).args(["store", key, value]).assert().success();
+    }
+    let list = |conditions: &[&str]| {
+        let mut cmd = rcli(&data_dir);
+        cmd.args(["list", "--format", "json"]);
+        for condition in conditions {
+            cmd.args(["--where", condition]);
+        }
+        let output = cmd.assert().success().get_output().stdout.clone();
+        serde_json::from_slice::<serde_json::Value>(&output).unwrap()
+    };
+
+    // `list` shows the file stem, so `users/1` is listed as `users_1`.
+    assert_eq!(list(&["/status==active"]), serde_json::json!(["alice", "carol", "users_1"]));
+    assert_eq!(list(&["/age>30"]), serde_json::json!(["alice", "bob", "users_1"]));
+    assert_eq!(list(&["/status==active", "/age>30"]), serde_json::json!(["alice", "users_1"]));
+    assert_eq!(list(&["/age>50"]), serde_json::json!(["bob"]));
+}
+
+#[test]
+fn test_query_uses_indexed_field() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let config_path = temp_dir.path().join("config.yaml");
+    // `--config` ignores RCLI_* variables, so the data directory is set in
+    // the file.
+    let with_config = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("rcli").unwrap();
+        cmd.args(["--config", config_path.to_str().unwrap()]).args(args);
+        cmd
+    };
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--no-config", "config", "init", "--output", config_path.to_str().unwrap()])
+        .assert()
+        .success();
+    with_config(&["config", "set", "storage.data_dir", data_dir.to_str().unwrap()]).assert().success();
+    with_config(&["config", "set", "storage.indexed_fields", "[status]"]).assert().success();
+
+    with_config(&["store", "alice", r#"{"status": "active"}"#]).assert().success();
+    with_config(&["store", "bob", r#"{"status": "inactive"}"#]).assert().success();
+    with_config(&["query", "status", "active"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("alice").and(predicate::str::contains("bob").not()));
+
+    with_config(&["store", "bob", r#"{"status": "active"}"#]).assert().success();
+    with_config(&["delete", "alice", "--yes"]).assert().success();
+    let output = with_config(&["query", "status", "active", "--format", "json"]).assert().success().get_output().stdout.clone();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&output).unwrap(), serde_json::json!(["bob"]));
+    assert!(data_dir.join("bob.json").exists());
+
+    with_config(&["query", "age", "3"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not indexed"));
+}
+
+#[test]
+fn test_compact_prints_single_line_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["store", "user1", r#"{"name": "Ada", "tags": ["a", "b"]}"#]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "user1"]);
+    let pretty = cmd.assert().success().get_output().stdout.clone();
+    assert!(String::from_utf8(pretty).unwrap().trim_end().lines().count() > 1);
+
+    for args in [["--compact", "get", "user1"], ["get", "user1", "--compact"]] {
+        let mut cmd = Command::cargo_bin("rcli").unwrap();
+        cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap()).args(args);
+        cmd.assert()
+            .success()
+            .stdout(r#"{"name":"Ada","tags":["a","b"]}"#.to_owned() + "\n");
+    }
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list", "--compact"]);
+    cmd.assert().success().stdout("[\"user1\"]\n");
+}
+
+#[test]
+fn test_list_cursor_pagination() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    for key in ["a", "b", "c"] {
+        let mut cmd = Command::cargo_bin("rcli").unwrap();
+        cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+            .args(["store", key, "1"]);
+        cmd.assert().success();
+    }
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list", "--limit", "2", "--format", "json"]);
+    cmd.assert().success().stdout("{\"items\":[\"a\",\"b\"],\"next\":\"b\"}\n");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list", "--after", "b", "--limit", "2", "--format", "json"]);
+    cmd.assert().success().stdout("{\"items\":[\"c\"],\"next\":null}\n");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list", "--limit", "1"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("continue with --after a"));
+}
+
+#[test]
+fn test_list_empty() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(&["list"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No stored items found"));
+}
+
+#[test]
+fn test_delete_nonexistent() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(&["delete", "nonexistent_key"]);
+    cmd.assert().failure();
+}
+
+fn store_keys(data_dir: &Path, keys: &[&str]) {
+    for key in keys {
+        rcli(data_dir).args(["store", key, r#"{"value": 1}"#]).assert().success();
+    }
+}
+
+#[test]
+fn test_delete_confirmation() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["keep", "drop"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["--debug-errors", "delete", "keep"])
+        .write_stdin("y\n");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("use --yes"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "keep"]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["delete", "drop", "--yes"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Deleted key: drop"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "drop"]);
+    cmd.assert().failure();
+}
+
+#[test]
+fn test_prune_max_items() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["old_1", "old_2", "old_3", "new_1", "new_2"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["prune", "--max-items", "2", "--yes"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Pruned 3 keys"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("new_1"))
+        .stdout(predicate::str::contains("new_2"))
+        .stdout(predicate::str::contains("old_").not());
+}
+
+#[test]
+fn test_delete_many_prefix() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["user_1", "user_2", "order_1"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["delete-many", "user_", "--prefix", "--yes"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Deleted 2 keys"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("order_1"))
+        .stdout(predicate::str::contains("user_").not());
+}
+
+#[test]
+fn test_delete_many_glob() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["cache_a_1", "cache_b_1", "cache_a_2"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["delete-many", "cache_*_1", "--glob", "--yes"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Deleted 2 keys"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "cache_a_2"]);
+    cmd.assert().success();
+}
+
+#[test]
+fn test_delete_many_no_match() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["user_1"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["delete-many", "missing*", "--glob", "--yes"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No matching keys found"));
+}
+
+#[test]
+fn test_delete_many_requires_yes_without_terminal() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["user_1"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["--debug-errors", "delete-many", "user", "--prefix"])
+        .write_stdin("y\n");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("use --yes"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "user_1"]);
+    cmd.assert().success();
+}
+
+#[test]
+fn test_config_dir_override() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_dir = temp_dir.path().join("rcli");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("config.yaml"),
+        "server:\n  base_url: https://override.example.com\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--config-dir", config_dir.to_str().unwrap(), "config", "show"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("https://override.example.com"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_CONFIG_DIR", config_dir.to_str().unwrap())
+        .args(["config", "path"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("config.yaml (loaded)"));
+}
+
+#[test]
+fn test_env_file_sets_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let env_file = temp_dir.path().join("staging.env");
+    fs::write(&env_file, "RCLI_SERVER__BASE_URL=https://from-env-file.example.com\n").unwrap();
+    fs::write(temp_dir.path().join(".env"), "RCLI_SERVER__BASE_URL=https://dotenv.example.com\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .env_remove("RCLI_SERVER__BASE_URL")
+        .args(["--env-file", env_file.to_str().unwrap(), "config", "show"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("https://from-env-file.example.com"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .env_remove("RCLI_SERVER__BASE_URL")
+        .args(["config", "show"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("https://dotenv.example.com"));
+
+    // Variables already in the environment win over the file.
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .env("RCLI_SERVER__BASE_URL", "https://real-env.example.com")
+        .args(["config", "show"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("https://real-env.example.com"));
+}
+
+#[test]
+fn test_no_config_uses_defaults() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join("config.yaml"),
+        "server:\n  base_url: https://project.example.com\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .env("RCLI_SERVER__TIMEOUT_SECONDS", "99")
+        .args(["--no-config", "config", "show", "--format", "json"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let shown: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(shown["server"]["base_url"], "https://api.example.com");
+    assert_eq!(shown["server"]["timeout_seconds"], 30);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["--no-config", "--base-url", "https://flag.example.com", "config", "show"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("https://flag.example.com"));
+}
+
+#[test]
+fn test_cache_list_and_clear() {
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/report")
+        .with_header("etag", "\"v7\"")
+        .with_body(r#"{"rows": 3}"#)
+        .create();
+    let temp_dir = TempDir::new().unwrap();
+    let cached = |args: &[&str]| {
+        let mut cmd = rcli(&temp_dir.path().join("data"));
+        cmd.env("RCLI_CACHE__DIR", temp_dir.path())
+            .env("RCLI_SERVER__BASE_URL", server.url())
+            .args(args);
+        cmd.assert()
+    };
+    let url = format!("{}/report", server.url());
+
+    cached(&["fetch", "/report", "--cache"]).success();
+    cached(&["cache", "list"])
+        .success()
+        .stdout(predicate::str::contains(url.as_str()))
+        .stdout(predicate::str::contains("\"v7\""))
+        .stdout(predicate::str::contains("11 B"));
+
+    cached(&["cache", "clear", "/report"]).success().stdout(predicate::str::contains(url.as_str()));
+    cached(&["cache", "clear", "/report"]).failure();
+    cached(&["cache", "list"]).success().stdout(predicate::str::contains("No cached responses"));
+}
+
+#[test]
+fn test_retry_only_listed_statuses() {
+    let mut server = mockito::Server::new();
+    let missing = server.mock("GET", "/missing").with_status(404).expect(1).create();
+    let unavailable = server.mock("GET", "/unavailable").with_status(503).expect(2).create();
+    let broken = server.mock("GET", "/broken").with_status(500).expect(1).create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--no-config", "--retries", "3", "fetch", &format!("{}/missing", server.url())]);
+    cmd.assert().failure();
+    missing.assert();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--no-config", "--retries", "2", "fetch", &format!("{}/unavailable", server.url())]);
+    cmd.assert().failure();
+    unavailable.assert();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--no-config", "--retries", "3", "--retry-on-status", "502,503", "fetch"])
+        .arg(format!("{}/broken", server.url()));
+    cmd.assert().failure();
+    broken.assert();
+}
+
+#[test]
+fn test_post_not_retried_by_default() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/items")
+        .with_status(500)
+        .expect(1)
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--retries", "3", "request", "post", &format!("{}/items", server.url())])
+        .args(["--data", r#"{"name": "test"}"#]);
+    cmd.assert().failure();
+
+    mock.assert();
+}
+
+#[test]
+fn test_post_retried_with_opt_in() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/items")
+        .with_status(500)
+        .expect(2)
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--retries", "2", "request", "post", &format!("{}/items", server.url())])
+        .args(["--data", r#"{"name": "test"}"#, "--retry"]);
+    cmd.assert().failure();
+
+    mock.assert();
+}
+
+#[test]
+fn test_get_retried_automatically() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/items")
+        .with_status(503)
+        .expect(2)
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--retries", "2", "fetch", &format!("{}/items", server.url())]);
+    cmd.assert().failure();
+
+    mock.assert();
+}
+
+#[test]
+fn test_fetch_query_parameters() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/search")
+        .match_query(mockito::Matcher::Exact(
+            "page=2&q=rust+%26+cli&tag=a&tag=b%2Fc".to_string(),
+        ))
+        .with_body(r#"{"ok": true}"#)
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["fetch", &format!("{}/search?page=2", server.url())])
+        .args(["--query", "q=rust & cli", "--query", "tag=a", "--query", "tag=b/c"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"ok\": true"));
+
+    mock.assert();
+}
+
+#[test]
+fn test_fetch_output_dir_names_files_after_url() {
+    let mut server = mockito::Server::new();
+    server.mock("GET", "/api/users/1").with_body(r#"{"id": 1}"#).create();
+    server.mock("GET", "/api/users/2.json").with_body(r#"{"id": 2}"#).create();
+    let temp_dir = TempDir::new().unwrap();
+    let out = temp_dir.path().join("out");
+
+    for path in ["/api/users/1", "/api/users/2.json", "/api/users/1"] {
+        Command::cargo_bin("rcli")
+            .unwrap()
+            .args(["--no-config", "fetch", &format!("{}{}", server.url(), path), "--format", "json"])
+            .args(["--output-dir", out.to_str().unwrap()])
+            .assert()
+            .success();
+    }
+
+    let read = |name: &str| std::fs::read_to_string(out.join(name)).unwrap();
+    assert_eq!(read("api_users_1.json"), r#"{"id":1}"#);
+    assert_eq!(read("api_users_2.json"), r#"{"id":2}"#);
+    assert_eq!(read("api_users_1-1.json"), r#"{"id":1}"#);
+    assert_eq!(std::fs::read_dir(&out).unwrap().count(), 3);
+}
+
+#[test]
+fn test_stream_prints_events_in_order() {
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/events")
+        .match_header("accept", "text/event-stream")
+        .with_header("content-type", "text/event-stream")
+        .with_body(": hello\n\ndata: {\"n\": 1}\n\ndata: oops\n\nevent: tick\ndata: {\"n\": 2}\n\ndata: {\"n\": 3}\n\n")
+        .create();
+
+    // With --json, an event that is not JSON is skipped with a warning.
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--no-config", "stream", &format!("{}/events", server.url()), "--json", "--max-events", "3"])
+        .assert()
+        .success()
+        .stdout("{\"n\":1}\n{\"n\":2}\n{\"n\":3}\n")
+        .stderr(predicate::str::contains("Skipping event whose data is not JSON"));
+}
+
+#[test]
+fn test_stream_reconnects_with_last_event_id() {
+    let mut server = mockito::Server::new();
+    let first = server
+        .mock("GET", "/events")
+        .match_header("last-event-id", mockito::Matcher::Missing)
+        .with_header("content-type", "text/event-stream")
+        .with_body("retry: 10\n\nid: 1\ndata: one\n\nid: 2\ndata: two\n\n")
+        .expect(1)
+        .create();
+    let resumed = server
+        .mock("GET", "/events")
+        .match_header("last-event-id", "2")
+        .with_header("content-type", "text/event-stream")
+        .with_body("id: 3\ndata: three\n\n")
+        .expect(1)
+        .create();
+
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--no-config", "stream", &format!("{}/events", server.url()), "--max-events", "3"])
+        .assert()
+        .success()
+        .stdout("one\ntwo\nthree\n");
+    first.assert();
+    resumed.assert();
+}
+
+#[test]
+fn test_stream_gives_up_after_max_reconnects() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/events")
+        .with_header("content-type", "text/event-stream")
+        .with_body("retry: 10\n\n")
+        .expect(3)
+        .create();
+
+    Command::cargo_bin("rcli")
+        .unwrap()
+        .args(["--no-config", "stream", &format!("{}/events", server.url()), "--max-reconnects", "2"])
+        .assert()
+        .success()
+        .stdout("");
+    mock.assert();
+}
+
+#[test]
+fn test_fetch_query_requires_key_value() {
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["fetch", "/search", "--query", "novalue"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("expected KEY=VALUE"));
+}
+
+#[test]
+fn test_debug_errors_prints_error_chain() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let missing = temp_dir.path().join("missing.json");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["--debug-errors", "store", "key", missing.to_str().unwrap(), "--file"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Caused by: No such file or directory"))
+        .stderr(predicate::str::contains("Debug: Io("));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["store", "key", missing.to_str().unwrap(), "--file"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Caused by").not());
+}
+
+#[test]
+fn test_version_check_update_available() {
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/releases/latest")
+        .with_body(r#"{"tag_name": "v99.0.0"}"#)
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_UPDATE__CHECK_URL", format!("{}/releases/latest", server.url()))
+        .args(["version", "--check"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Update available"))
+        .stdout(predicate::str::contains("99.0.0"));
+}
+
+#[test]
+fn test_version_check_up_to_date() {
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/releases/latest")
+        .with_body(format!(r#"{{"tag_name": "v{}"}}"#, env!("CARGO_PKG_VERSION")))
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_UPDATE__CHECK_URL", format!("{}/releases/latest", server.url()))
+        .args(["version", "--check"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("up to date"));
+}
+
+#[test]
+fn test_output_json_not_found_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["--output-json", "get", "missing"]);
+    let output = cmd.assert().failure().get_output().stdout.clone();
+
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(report["status"], "error");
+    assert_eq!(report["code"], "NOT_FOUND");
+    assert!(report["message"].as_str().unwrap().contains("missing"));
+}
+
+#[test]
+fn test_output_json_parse_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["--output-json", "store", "key", "{not json"]);
+    let output = cmd.assert().failure().get_output().stdout.clone();
+
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(report["status"], "error");
+    assert_eq!(report["code"], "JSON_ERROR");
+}
+
+#[test]
+fn test_migrate_old_format_item() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    fs::create_dir_all(&data_dir).unwrap();
+    fs::write(
+        data_dir.join("legacy.json"),
+        r#"{
+  "key": "legacy",
+  "value": {"name": "old"},
+  "created_at": "2024-01-01T00:00:00Z",
+  "updated_at": "2024-01-01T00:00:00Z"
+}"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["migrate"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Migrated 1 items"));
+
+    let migrated: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(data_dir.join("legacy.json")).unwrap()).unwrap();
+    assert_eq!(migrated["schema_version"], 1);
+    assert_eq!(migrated["value"]["name"], "old");
+    assert!(migrated["id"].as_str().is_some_and(|id| !id.is_empty()));
+    assert_eq!(migrated["created_at"], "2024-01-01T00:00:00Z");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["migrate"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Migrated 0 items (1 already current)"));
+}
+
+#[test]
+fn test_base_url_override() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/users/1")
+        .with_body(r#"{"id": 1}"#)
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_SERVER__BASE_URL", "http://127.0.0.1:1")
+        .args(["--base-url", &server.url(), "fetch", "/users/1"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"id\": 1"));
+
+    mock.assert();
+}
+
+#[test]
+fn test_base_url_rejects_invalid_url() {
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--base-url", "not a url", "fetch", "/users/1"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid URL"));
+}
+
+fn write_profiles_config(dir: &std::path::Path) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(
+        dir.join("config.yaml"),
+        r#"server:
+  base_url: https://base.example.com
+  timeout_seconds: 10
+default_profile: dev
+profiles:
+  dev:
+    server:
+      base_url: https://dev.example.com
+  staging:
+    server:
+      base_url: https://staging.example.com
+"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_profile_selection() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_dir = temp_dir.path().join("rcli");
+    write_profiles_config(&config_dir);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_CONFIG_DIR", config_dir.to_str().unwrap())
+        .args(["--profile", "staging", "config", "show"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("base_url: https://staging.example.com"))
+        .stdout(predicate::str::contains("timeout_seconds: 10"));
+}
+
+#[test]
+fn test_profile_falls_back_to_default() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_dir = temp_dir.path().join("rcli");
+    write_profiles_config(&config_dir);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_CONFIG_DIR", config_dir.to_str().unwrap())
+        .args(["config", "show"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("base_url: https://dev.example.com"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_CONFIG_DIR", config_dir.to_str().unwrap())
+        .args(["config", "profiles"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("dev (default, active)"))
+        .stdout(predicate::str::contains("staging"));
+}
+
+#[test]
+fn test_unknown_profile_fails() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_dir = temp_dir.path().join("rcli");
+    write_profiles_config(&config_dir);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_CONFIG_DIR", config_dir.to_str().unwrap())
+        .args(["--output-json", "--profile", "prod", "config", "show"]);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Unknown profile 'prod'"));
+}
+
+#[test]
+fn test_list_format_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["beta", "alpha"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list", "--format", "json"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+
+    let keys: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(keys, serde_json::json!(["alpha", "beta"]));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list", "--format", "json", "--detailed"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+
+    let items: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(items[0]["key"], "alpha");
+    assert!(items[0]["created_at"].is_string());
+    assert!(items[1]["id"].is_string());
+}
+
+#[test]
+fn test_get_max_depth() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["store", "nested", r#"{"a": {"b": {"c": 1}}, "top": true}"#]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "nested", "--max-depth", "2", "--format", "json"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#"{"a":{"b":{"...":"truncated"}},"top":true}"#));
+}
+
+#[test]
+fn test_list_columns() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    fs::create_dir_all(&data_dir).unwrap();
+    fs::write(
+        data_dir.join("report.json"),
+        r#"{
+  "id": "id-1",
+  "key": "report",
+  "value": {"ok": true},
+  "created_at": "2024-03-01T10:00:00Z",
+  "updated_at": "2024-03-02T11:30:00Z",
+  "metadata": {"owner": "alice"}
+}"#,
+    )
+    .unwrap();
+    store_keys(&data_dir, &["plain"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list", "--columns", "key,owner,created"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let lines: Vec<&str> = std::str::from_utf8(&output).unwrap().lines().collect();
+
+    assert_eq!(lines[0].split_whitespace().collect::<Vec<_>>(), ["KEY", "OWNER", "CREATED"]);
+    assert!(lines[1].starts_with("plain "));
+    assert_eq!(lines[1].split_whitespace().nth(1), Some("-"));
+    assert_eq!(
+        lines[2].split_whitespace().collect::<Vec<_>>(),
+        ["report", "alice", "2024-03-01", "10:00:00", "UTC"]
+    );
+}
+
+#[test]
+fn test_post_and_store_response() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/echo")
+        .match_body(mockito::Matcher::JsonString(r#"{"name": "widget"}"#.to_string()))
+        .with_header("content-type", "application/json")
+        .with_body_from_request(|request| request.body().unwrap().clone())
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["post", &format!("{}/echo", server.url()), r#"{"name": "widget"}"#])
+        .args(["--store", "echoed"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Stored response under 'echoed'"));
+    mock.assert();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "echoed", "--format", "json"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#"{"name":"widget"}"#));
+}
+
+#[test]
+fn test_user_agent_from_config() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/items")
+        .match_header("user-agent", "config-agent/1.0")
+        .match_header("x-request-id", mockito::Matcher::Any)
+        .with_body("{}")
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_SERVER__USER_AGENT", "config-agent/1.0")
+        .args(["fetch", &format!("{}/items", server.url())]);
+    cmd.assert().success();
+
+    mock.assert();
+}
+
+fn write_user_schema(dir: &std::path::Path) -> std::path::PathBuf {
+    let path = dir.join("user.schema.json");
+    fs::write(
+        &path,
+        r#"{
+  "type": "object",
+  "required": ["id", "name"],
+  "properties": {
+    "id": {"type": "integer"},
+    "name": {"type": "string"}
+  }
+}"#,
+    )
+    .unwrap();
+    path
+}
+
+#[test]
+fn test_fetch_schema_conforming() {
+    let temp_dir = TempDir::new().unwrap();
+    let schema_path = write_user_schema(temp_dir.path());
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/users/1")
+        .with_body(r#"{"id": 1, "name": "Ada"}"#)
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["fetch", &format!("{}/users/1", server.url())])
+        .args(["--schema", schema_path.to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Ada"));
+}
+
+#[test]
+fn test_fetch_schema_non_conforming() {
+    let temp_dir = TempDir::new().unwrap();
+    let schema_path = write_user_schema(temp_dir.path());
+    let output_path = temp_dir.path().join("out.json");
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/users/1")
+        .with_body(r#"{"id": "one"}"#)
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--output-json", "fetch", &format!("{}/users/1", server.url())])
+        .args(["--schema", schema_path.to_str().unwrap()])
+        .args(["--output", output_path.to_str().unwrap()]);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("VALIDATION_ERROR"))
+        .stdout(predicate::str::contains("/id"))
+        .stdout(predicate::str::contains("name"));
+
+    assert!(!output_path.exists());
+}
+
+#[test]
+fn test_data_dir_env_var_expansion() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_TEST_ROOT", temp_dir.path().to_str().unwrap())
+        .env("RCLI_STORAGE__DATA_DIR", "${RCLI_TEST_ROOT}/data")
+        .args(["store", "expanded", r#"{"ok": true}"#]);
+    cmd.assert().success();
+
+    assert!(temp_dir.path().join("data").join("expanded.json").exists());
+}
+
+#[test]
+fn test_inspect_shows_full_item() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["inspected"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["inspect", "inspected", "--format", "json"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+
+    let item: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(item["key"], "inspected");
+    assert!(item["id"].is_string());
+    assert!(item["created_at"].is_string());
+    assert_eq!(item["value"]["value"], 1);
+}
+
+#[cfg(not(feature = "clipboard"))]
+#[test]
+fn test_get_clipboard_requires_feature() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["user_1"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "user_1", "--clipboard"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("clipboard support not compiled in"));
+}
+
+#[test]
+fn test_get_uses_configured_indent() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["user_1"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .env("RCLI_OUTPUT__INDENT", "tab")
+        .args(["get", "user_1"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("{\n\t\"value\": 1\n}"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .env("RCLI_OUTPUT__INDENT", "4")
+        .args(["get", "user_1"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("{\n    \"value\": 1\n}"));
+}
+
+#[test]
+fn test_ping_healthy_server() {
+    let mut server = mockito::Server::new();
+    server.mock("GET", "/health").with_status(200).create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--base-url", &server.url(), "ping", "/health"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Reachable: HTTP 200 OK"));
+}
+
+#[test]
+fn test_ping_unhealthy_server() {
+    let mut server = mockito::Server::new();
+    server.mock("GET", "/health").with_status(503).create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--base-url", &server.url(), "ping", "/health"]);
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("HTTP 503"));
+}
+
+#[test]
+fn test_bench_json_report() {
+    let mut server = mockito::Server::new();
+    let mock = server.mock("GET", "/health").with_status(200).expect(12).create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--base-url", &server.url(), "bench", "/health", "-n", "12", "--concurrency", "3", "--format", "json"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    assert_eq!(report["requests"], 12);
+    assert_eq!(report["succeeded"], 12);
+    assert_eq!(report["error_rate"], 0.0);
+    assert!(report["latency"]["p99"].as_f64().unwrap() >= report["latency"]["p50"].as_f64().unwrap());
+    mock.assert();
+}
+
+#[test]
+fn test_doctor_reports_unwritable_data_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let blocker = temp_dir.path().join("blocker");
+    fs::write(&blocker, "not a directory").unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", blocker.join("data").to_str().unwrap())
+        .args(["doctor", "--no-ping"]);
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("[fail] data directory"))
+        .stdout(predicate::str::contains("cannot be created"))
+        .stdout(predicate::str::contains("[pass] config values   valid"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", temp_dir.path().join("data").to_str().unwrap())
+        .args(["doctor", "--no-ping", "--format", "json"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let checks: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let data_dir = checks.as_array().unwrap().iter().find(|check| check["name"] == "data directory").unwrap();
+    assert_eq!(data_dir["status"], "warn");
+}
+
+#[test]
+fn test_store_binary_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let original = temp_dir.path().join("image.bin");
+    let bytes: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+    fs::write(&original, &bytes).unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["store", "image", original.to_str().unwrap(), "--file", "--binary"]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "image", "--decode"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+
+    let decoded = temp_dir.path().join("decoded.bin");
+    fs::write(&decoded, output).unwrap();
+    assert_eq!(fs::read(&decoded).unwrap(), fs::read(&original).unwrap());
+}
+
+#[test]
+fn test_get_decode_rejects_json_value() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["user_1"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "user_1", "--decode"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("not binary data"));
+}
+
+#[test]
+fn test_list_since_relative_duration() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["fresh"]);
+    fs::write(
+        data_dir.join("stale.json"),
+        r#"{"key": "stale", "value": 1, "created_at": "2020-01-01T00:00:00Z", "updated_at": "2020-01-01T00:00:00Z"}"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list", "--since", "2h", "--format", "json"]);
+    cmd.assert().success().stdout("[\"fresh\"]\n");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list", "--until", "3d", "--format", "json"]);
+    cmd.assert().success().stdout("[\"stale\"]\n");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["list", "--since", "1h", "--until", "2h", "--format", "json"]);
+    cmd.assert().success().stdout("[]\n");
+}
+
+#[test]
+fn test_list_since_rejects_invalid_bound() {
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["list", "--since", "yesterday-ish"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("expected an RFC 3339 timestamp"));
+}
+
+#[test]
+fn test_dedupe_reports_and_deletes_duplicates() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["first", "second"]);
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["store", "other", r#"{"value": 2}"#]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .arg("dedupe");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Duplicate groups (1):"))
+        .stdout(predicate::str::contains("first, second"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["dedupe", "--delete", "--yes"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Deleted 1 duplicate keys"));
+
+    assert!(data_dir.join("first.json").exists());
+    assert!(!data_dir.join("second.json").exists());
+    assert!(data_dir.join("other.json").exists());
+}
+
+#[test]
+fn test_store_with_registered_schema() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let schema_dir = temp_dir.path().join("schemas");
+    let schema_file = temp_dir.path().join("user.schema.json");
+    fs::write(
+        &schema_file,
+        r#"{"type": "object", "properties": {"age": {"type": "integer"}}, "required": ["name"]}"#,
+    )
+    .unwrap();
+    let with_schemas = |args: &[&str]| {
+        let mut cmd = rcli(&data_dir);
+        cmd.env("RCLI_STORAGE__SCHEMA_DIR", schema_dir.to_str().unwrap())
+            .arg("--debug-errors")
+            .args(args);
+        cmd
+    };
+
+    with_schemas(&["schema", "add", "user", schema_file.to_str().unwrap()]).assert().success();
+    with_schemas(&["schema", "list"]).assert().success().stdout("user\n");
+
+    with_schemas(&["store", "ada", r#"{"name": "Ada", "age": 36}"#, "--schema-name", "user"])
+        .assert()
+        .success();
+    with_schemas(&["store", "bob", r#"{"age": "old"}"#, "--schema-name", "user"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Value does not match schema"))
+        .stderr(predicate::str::contains("/age"));
+    assert!(!data_dir.join("bob.json").exists());
+
+    with_schemas(&["store", "eve", "{}", "--schema-name", "missing"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("schema 'missing'"));
+
+    with_schemas(&["schema", "remove", "user"]).assert().success();
+    with_schemas(&["schema", "list"]).assert().success().stdout("");
+}
+
+#[test]
+fn test_store_from_url() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/users/1")
+        .with_body(r#"{"id": 1, "name": "Ada"}"#)
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["store", "user", "--from-url", &format!("{}/users/1", server.url())]);
+    cmd.assert().success();
+    mock.assert();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "user", "--format", "json"]);
+    cmd.assert()
+        .success()
+        .stdout("{\"id\":1,\"name\":\"Ada\"}\n");
+}
+
+#[test]
+fn test_store_from_url_records_provenance() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/users/1")
+        .with_header("etag", "\"v1\"")
+        .with_body(r#"{"id": 1}"#)
+        .create();
+    let url = format!("{}/users/1", server.url());
+    let metadata = |key: &str| {
+        let output = rcli(&data_dir).args(["meta", key, "get"]).assert().success().get_output().stdout.clone();
+        serde_json::from_slice::<serde_json::Value>(&output).unwrap()
+    };
+
+    rcli(&data_dir).args(["store", "user", "--from-url", &url]).assert().success();
+
+    let recorded = metadata("user");
+    assert_eq!(recorded["source_url"], url);
+    assert_eq!(recorded["status"], "200");
+    assert_eq!(recorded["etag"], "\"v1\"");
+    let fetched_at = recorded["fetched_at"].as_str().unwrap();
+    assert!(chrono::DateTime::parse_from_rfc3339(fetched_at).is_ok(), "{}", fetched_at);
+
+    rcli(&data_dir)
+        .args(["store", "plain", "--from-url", &url, "--no-provenance"])
+        .assert()
+        .success();
+    assert_eq!(metadata("plain"), serde_json::json!({}));
+}
+
+#[test]
+fn test_run_spec_fetches_and_stores() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let mut server = mockito::Server::new();
+    let fetch = server
+        .mock("GET", "/users/1")
+        .with_body(r#"{"id": 1, "name": "Ada"}"#)
+        .expect(1)
+        .create();
+    let create = server
+        .mock("POST", "/items")
+        .match_body(mockito::Matcher::Json(serde_json::json!({"name": "widget"})))
+        .with_status(201)
+        .with_body(r#"{"id": 42}"#)
+        .expect(1)
+        .create();
+    let spec = temp_dir.path().join("spec.json");
+    fs::write(
+        &spec,
+        serde_json::json!([
+            {"url": format!("{}/users/1", server.url()), "store_key": "user"},
+            {"method": "post", "url": format!("{}/items", server.url()), "body": {"name": "widget"}, "store_key": "created"},
+        ])
+        .to_string(),
+    )
+    .unwrap();
+
+    let mut cmd = rcli(&data_dir);
+    cmd.args(["run", spec.to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("[1/2] GET").and(predicate::str::contains("ok, stored as 'user'")))
+        .stdout(predicate::str::contains("[2/2] POST").and(predicate::str::contains("ok, stored as 'created'")));
+    fetch.assert();
+    create.assert();
+
+    let mut cmd = rcli(&data_dir);
+    cmd.args(["get", "created", "--format", "json"]);
+    cmd.assert().success().stdout("{\"id\":42}\n");
+
+    fs::write(&spec, r#"[{"method": "FETCH", "url": "/x"}]"#).unwrap();
+    let mut cmd = rcli(&data_dir);
+    cmd.args(["--debug-errors", "run", spec.to_str().unwrap()]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Step 1: unsupported method 'FETCH'"));
+}
+
+#[test]
+fn test_replay_posts_stored_payload() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/webhook")
+        .match_body(mockito::Matcher::Json(serde_json::json!({"event": "created", "id": 7})))
+        .with_body(r#"{"received": true}"#)
+        .expect(1)
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["store", "hook_1", r#"{"event": "created", "id": 7}"#]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["replay", "hook_1", &format!("{}/webhook", server.url())]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"received\": true"));
+
+    mock.assert();
+}
+
+#[test]
+fn test_replay_prefix_sends_each_payload() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["hook_1", "hook_2", "other"]);
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("PUT", "/webhook")
+        .match_body(mockito::Matcher::Json(serde_json::json!({"value": 1})))
+        .with_body("{}")
+        .expect(2)
+        .create();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["replay", "hook_", &format!("{}/webhook", server.url())])
+        .args(["--prefix", "--method", "put", "--delay-ms", "10"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("==> hook_1"))
+        .stdout(predicate::str::contains("==> hook_2"));
+
+    mock.assert();
+}
+
+#[test]
+fn test_invalid_config_reports_all_issues() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.yaml");
+    fs::write(
+        &config_path,
+        "server:\n  base_url: ftp://example.com\n  timeout_seconds: 0\n  retry_attempts: 1\n\
+         logging:\n  level: info\n\
+         storage:\n  data_dir: ./data\n  max_file_size_mb: 10\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.args(["--config", config_path.to_str().unwrap(), "list"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("server.base_url: unsupported scheme 'ftp'"))
+        .stderr(predicate::str::contains("server.timeout_seconds: must be greater than 0"));
+}
+
+#[test]
+fn test_set_and_get_pointer() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["set", "doc", "/items/0/name", r#""first""#]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("key 'doc'"));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["set", "doc", "/items", r#"[{"name": "first"}]"#, "--create"]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["set", "doc", "/items/0/tags", r#"["a"]"#]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "doc", "--pointer", "/items/0", "--format", "json"]);
+    cmd.assert()
+        .success()
+        .stdout("{\"name\":\"first\",\"tags\":[\"a\"]}\n");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "doc", "--pointer", "/items/3"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("pointer '/items/3'"));
+}
+
+#[test]
+fn test_get_with_template() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    store_keys(&data_dir, &["user_1"]);
+    let template_path = temp_dir.path().join("report.hbs");
+    fs::write(&template_path, "Value is {{value}}").unwrap();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "user_1", "--template", template_path.to_str().unwrap()]);
+    cmd.assert().success().stdout("Value is 1\n");
+}
+
+#[test]
+fn test_audit_command_filters_by_key() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let audit_path = temp_dir.path().join("audit.ndjson");
+    for args in [vec!["store", "a", "1"], vec!["store", "b", "2"], vec!["get", "a"]] {
+        let mut cmd = Command::cargo_bin("rcli").unwrap();
+        cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+            .env("RCLI_STORAGE__AUDIT_LOG", audit_path.to_str().unwrap())
+            .args(args);
+        cmd.assert().success();
+    }
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .env("RCLI_STORAGE__AUDIT_LOG", audit_path.to_str().unwrap())
+        .args(["audit", "a"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+
+    let operations: Vec<String> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["operation"].to_string())
+        .collect();
+    assert_eq!(operations, vec!["\"store\"", "\"get\""]);
+}
+
+#[test]
+fn test_list_parallel_matches_sequential() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let keys: Vec<String> = (0..20).map(|i| format!("item_{:02}", i)).collect();
+    store_keys(&data_dir, &keys.iter().map(String::as_str).collect::<Vec<_>>());
+
+    let list = |extra: &[&str]| {
+        let mut cmd = rcli(&data_dir);
+        cmd.args(["list", "--detailed", "--format", "json"]).args(extra);
+        cmd.assert().success().get_output().stdout.clone()
+    };
+
+    let sequential = list(&[]);
+    assert_eq!(list(&["--parallel"]), sequential);
+    assert_eq!(list(&["--parallel", "3"]), sequential);
+}
+
+#[test]
+fn test_store_and_get_by_path() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let export = temp_dir.path().join("export.json");
+
+    rcli(&data_dir)
+        .args(["store", "shared", r#"{"name": "x"}"#, "--path", export.to_str().unwrap()])
+        .assert()
+        .success();
+    assert!(export.exists());
+    assert!(!data_dir.join("shared.json").exists());
+
+    let output = rcli(&data_dir)
+        .args(["get", "--path", export.to_str().unwrap(), "--format", "json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let value: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(value, serde_json::json!({"name": "x"}));
+
+    rcli(&data_dir)
+        .args(["get", "--path", temp_dir.path().to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is a directory"));
+}
+
+#[test]
+fn test_size_reports_field_breakdown() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    rcli(&data_dir).args(["store", "doc", r#"{"big": "xxxxxxxxxx", "n": 1}"#]).assert().success();
+
+    rcli(&data_dir)
+        .args(["size", "doc"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Total: 26 bytes").and(predicate::str::contains("(overhead)")));
+
+    let output = rcli(&data_dir).args(["size", "doc", "--format", "json"]).assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(report["total_bytes"], 26);
+    assert_eq!(report["parts"][0], serde_json::json!({"name": "big", "bytes": 18}));
+
+    let file = temp_dir.path().join("doc.yaml");
+    fs::write(&file, "big: xxxxxxxxxx\nn: 1\n").unwrap();
+    rcli(&data_dir)
+        .args(["size", file.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Total: 26 bytes"));
+}
+
+#[test]
+fn test_project_config_found_by_alternate_names() {
+    let temp_dir = TempDir::new().unwrap();
+    let empty_user_dir = temp_dir.path().join("user");
+    fs::create_dir_all(&empty_user_dir).unwrap();
+    fs::write(temp_dir.path().join("rcli.toml"), "[server]\nbase_url = \"https://toml.example.com\"\n").unwrap();
+    let in_project = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("rcli").unwrap();
+        cmd.current_dir(temp_dir.path())
+            .env("RCLI_CONFIG_DIR", empty_user_dir.to_str().unwrap())
+            .args(args);
+        cmd
+    };
+
+    in_project(&["config", "show"]).assert().success().stdout(predicate::str::contains("https://toml.example.com"));
+    in_project(&["config", "path"]).assert().success().stdout(predicate::str::contains("rcli.toml (loaded)"));
+
+    // `.rclirc` is read as YAML, and comes after `rcli.*` in the search list.
+    fs::write(temp_dir.path().join(".rclirc"), "server:\n  base_url: https://rc.example.com\n").unwrap();
+    in_project(&["config", "show"]).assert().success().stdout(predicate::str::contains("https://toml.example.com"));
+    fs::remove_file(temp_dir.path().join("rcli.toml")).unwrap();
+    in_project(&["config", "show"]).assert().success().stdout(predicate::str::contains("https://rc.example.com"));
+    in_project(&["config", "path"]).assert().success().stdout(predicate::str::contains(".rclirc (loaded)"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_store_edit_uses_editor_output() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    // The editor command is run by the shell, so a quoted path with a space
+    // works.
+    let scripts = temp_dir.path().join("my editors");
+    std::fs::create_dir(&scripts).unwrap();
+    let script = |name: &str, body: &str| {
+        let path = scripts.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        format!("'{}'", path.display())
+    };
+    let edit = |editor: &str, key: &str| {
+        let mut cmd = rcli(&data_dir);
+        cmd.env_remove("VISUAL").env("EDITOR", editor).args(["store", key, "--edit"]);
+        cmd
+    };
+    let stored = |key: &str| {
+        let output = rcli(&data_dir)
+            .args(["get", key, "--format", "json"])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        serde_json::from_slice::<serde_json::Value>(&output).unwrap()
+    };
+
+    // A new key starts from an empty object, in a file only the owner can read.
+    let write = script(
+        "write.sh",
+        r#"ls -l "$1" | grep -q '^-rw-------' && grep -qx '{}' "$1" && printf '{"count": 1}' > "$1""#,
+    );
+    edit(&write, "counter").assert().success();
+    assert_eq!(stored("counter"), serde_json::json!({"count": 1}));
+
+    // An existing key starts from its stored value.
+    let bump = script("bump.sh", r#"sed 's/1/2/' "$1" > "$1.new" && mv "$1.new" "$1""#);
+    edit(&bump, "counter").assert().success();
+    assert_eq!(stored("counter"), serde_json::json!({"count": 2}));
+
+    let invalid = script("invalid.sh", r#"printf '{"count": ' > "$1""#);
+    edit(&invalid, "counter")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not valid JSON"));
+    let quit = script("quit.sh", "exit 1");
+    edit(&quit, "counter").assert().failure();
+    assert_eq!(stored("counter"), serde_json::json!({"count": 2}));
+}
+
+#[test]
+fn test_store_xml_input_and_get_xml_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["store", "user", "<user><name>Ada</name><langs><item>en</item></langs></user>", "--input-format", "xml"]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "user", "--format", "json"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#"{"langs":["en"],"name":"Ada"}"#));
+
+    let mut cmd = Command::cargo_bin("rcli").unwrap();
+    cmd.env("RCLI_STORAGE__DATA_DIR", data_dir.to_str().unwrap())
+        .args(["get", "user", "--format", "xml"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("<name>Ada</name>"));
+}
@@ -0,0 +1,53 @@
+use rust_advanced_cli::logging;
+use tempfile::TempDir;
+
+#[test]
+fn test_tail_lines_returns_last_lines() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("rcli.log");
+    let contents: String = (1..=20_000).map(|i| format!("line {}\n", i)).collect();
+    std::fs::write(&path, contents).unwrap();
+
+    assert_eq!(
+        logging::tail_lines(&path, 3).unwrap(),
+        vec!["line 19998", "line 19999", "line 20000"]
+    );
+}
+
+#[test]
+fn test_tail_lines_spanning_many_blocks() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("rcli.log");
+    let contents: String = (1..=20_000).map(|i| format!("line {}\n", i)).collect();
+    std::fs::write(&path, contents).unwrap();
+
+    let lines = logging::tail_lines(&path, 5_000).unwrap();
+    assert_eq!(lines.len(), 5_000);
+    assert_eq!(lines[0], "line 15001");
+    assert_eq!(lines[4_999], "line 20000");
+}
+
+#[test]
+fn test_tail_lines_short_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("rcli.log");
+    std::fs::write(&path, "first\nsecond").unwrap();
+
+    assert_eq!(logging::tail_lines(&path, 10).unwrap(), vec!["first", "second"]);
+}
+
+#[cfg(feature = "otlp")]
+#[tokio::test]
+async fn test_otlp_layer_installs_with_endpoint() {
+    use rust_advanced_cli::config::LoggingConfig;
+
+    let config = LoggingConfig {
+        level: "info".to_string(),
+        file_path: None,
+        otlp_endpoint: Some("http://127.0.0.1:4318/v1/traces".to_string()),
+    };
+
+    logging::init_logging(&config, false).unwrap();
+    tracing::info_span!("otlp_test").in_scope(|| tracing::info!("inside span"));
+    logging::shutdown();
+}
@@ -0,0 +1,241 @@
+use chrono::FixedOffset;
+use rust_advanced_cli::config::{Indent, OutputConfig};
+use rust_advanced_cli::output::{
+    canonical_size, canonicalize, file_name_for_url, flatten_value, format_output, format_timestamp, format_timestamp_in,
+    render_table, render_template, size_breakdown, sort_keys, truncate_value, unflatten_value, OutputFormat,
+};
+use serde_json::json;
+
+#[test]
+fn test_truncate_value_keeps_top_level_only() {
+    let value = json!({"name": "root", "child": {"leaf": 1}, "items": [1, 2]});
+
+    assert_eq!(
+        truncate_value(&value, 1),
+        json!({
+            "name": "root",
+            "child": {"...": "truncated"},
+            "items": {"...": "truncated"}
+        })
+    );
+}
+
+#[test]
+fn test_truncate_value_preserves_shallower_structure() {
+    let value = json!({"a": {"b": {"c": {"d": 1}}, "x": [{"y": 2}]}});
+
+    assert_eq!(
+        truncate_value(&value, 2),
+        json!({"a": {"b": {"...": "truncated"}, "x": {"...": "truncated"}}})
+    );
+    assert_eq!(
+        truncate_value(&value, 3),
+        json!({"a": {"b": {"c": {"...": "truncated"}}, "x": [{"...": "truncated"}]}})
+    );
+    assert_eq!(truncate_value(&value, 4), value);
+}
+
+#[test]
+fn test_truncate_value_leaves_scalars_and_empty_containers() {
+    assert_eq!(truncate_value(&json!(42), 1), json!(42));
+    assert_eq!(truncate_value(&json!({"a": {}, "b": []}), 1), json!({"a": {}, "b": []}));
+}
+
+#[test]
+fn test_ndjson_array_prints_one_element_per_line() {
+    let value = json!([{"id": 1, "tags": ["a"]}, {"id": 2}]);
+
+    let output = format_output(&value, OutputFormat::Ndjson, &OutputConfig::default()).unwrap();
+    assert_eq!(output, "{\"id\":1,\"tags\":[\"a\"]}\n{\"id\":2}");
+}
+
+#[test]
+fn test_ndjson_single_object_is_one_line() {
+    let value = json!({"id": 1, "nested": {"ok": true}});
+
+    let output = format_output(&value, OutputFormat::Ndjson, &OutputConfig::default()).unwrap();
+    assert_eq!(output, r#"{"id":1,"nested":{"ok":true}}"#);
+}
+
+#[test]
+fn test_ndjson_rejects_scalars() {
+    let err = format_output(&json!("text"), OutputFormat::Ndjson, &OutputConfig::default()).unwrap_err();
+    assert!(err.to_string().contains("NDJSON output requires"));
+}
+
+#[test]
+fn test_render_table_aligns_columns() {
+    let headers = vec!["key".to_string(), "owner".to_string()];
+    let rows = vec![
+        vec!["a".to_string(), "alice".to_string()],
+        vec!["long_key".to_string(), "-".to_string()],
+    ];
+
+    assert_eq!(
+        render_table(&headers, &rows),
+        "KEY       OWNER\na         alice\nlong_key  -"
+    );
+}
+
+#[test]
+fn test_pretty_default_indent_is_two_spaces() {
+    let output = format_output(&json!({"a": 1}), OutputFormat::Pretty, &OutputConfig::default()).unwrap();
+
+    assert_eq!(output, "{\n  \"a\": 1\n}");
+}
+
+#[test]
+fn test_pretty_configured_indent() {
+    let value = json!({"a": {"b": 1}});
+
+    let four = OutputConfig { indent: Indent::Spaces(4), ..Default::default() };
+    assert_eq!(
+        format_output(&value, OutputFormat::Pretty, &four).unwrap(),
+        "{\n    \"a\": {\n        \"b\": 1\n    }\n}"
+    );
+
+    let tab = OutputConfig { indent: Indent::Tab, ..Default::default() };
+    assert_eq!(
+        format_output(&value, OutputFormat::Pretty, &tab).unwrap(),
+        "{\n\t\"a\": {\n\t\t\"b\": 1\n\t}\n}"
+    );
+}
+
+#[test]
+fn test_format_timestamp_default_and_custom() {
+    let instant = "2024-03-05T14:07:09Z".parse().unwrap();
+
+    assert_eq!(format_timestamp(&instant, &OutputConfig::default()), "2024-03-05 14:07:09 UTC");
+
+    let custom = OutputConfig {
+        time_format: "%d/%m/%Y %H:%M".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(format_timestamp(&instant, &custom), "05/03/2024 14:07");
+}
+
+#[test]
+fn test_format_timestamp_in_timezone() {
+    let instant = "2024-03-05T14:07:09Z".parse().unwrap();
+    let kolkata = FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap();
+
+    assert_eq!(format_timestamp_in(&instant, "%Y-%m-%d %H:%M %:z", &kolkata), "2024-03-05 19:37 +05:30");
+}
+
+#[test]
+fn test_render_template() {
+    let data = json!({"name": "Ada", "langs": ["rust", "ml"]});
+
+    let rendered = render_template("# {{name}}\n{{#each langs}}- {{this}}\n{{/each}}", &data).unwrap();
+
+    assert_eq!(rendered, "# Ada\n- rust\n- ml\n");
+}
+
+#[test]
+fn test_render_template_errors() {
+    let data = json!({"name": "Ada"});
+
+    let missing = render_template("{{email}}", &data).unwrap_err();
+    assert!(missing.to_string().contains("Template error"));
+
+    let syntax = render_template("{{#each}}", &data).unwrap_err();
+    assert!(syntax.to_string().contains("Template error"));
+}
+
+#[test]
+fn test_sort_keys_orders_every_level() {
+    let value = json!({
+        "zeta": {"b": 1, "a": {"y": true, "x": false}},
+        "alpha": [{"d": 1, "c": 2}]
+    });
+
+    let sorted = sort_keys(&value);
+    let text = serde_json::to_string(&sorted).unwrap();
+
+    assert_eq!(
+        text,
+        r#"{"alpha":[{"c":2,"d":1}],"zeta":{"a":{"x":false,"y":true},"b":1}}"#
+    );
+    assert_eq!(sorted, value);
+}
+
+#[test]
+fn test_canonicalize_ignores_formatting_and_key_order() {
+    let a: serde_json::Value = serde_json::from_str("{\n  \"b\": {\"y\": 1, \"x\": [3, {\"d\": 4, \"c\": 5}]},\n  \"a\": true\n}").unwrap();
+    let b: serde_json::Value = serde_json::from_str(r#"{"a":true,"b":{"x":[3,{"c":5,"d":4}],"y":1}}"#).unwrap();
+
+    assert_eq!(canonicalize(&a), canonicalize(&b));
+    assert_eq!(canonicalize(&a), r#"{"a":true,"b":{"x":[3,{"c":5,"d":4}],"y":1}}"#);
+}
+
+#[test]
+fn test_flatten_then_unflatten_round_trips() {
+    let nested = json!({
+        "user": {"name": "Ada", "langs": ["en", {"code": "fr"}]},
+        "empty": {},
+        "none": [],
+        "count": 3
+    });
+
+    let flat = flatten_value(&nested).unwrap();
+
+    assert_eq!(
+        flat,
+        json!({
+            "user.name": "Ada",
+            "user.langs.0": "en",
+            "user.langs.1.code": "fr",
+            "empty": {},
+            "none": [],
+            "count": 3
+        })
+    );
+    assert_eq!(unflatten_value(&flat).unwrap(), nested);
+    assert_eq!(flatten_value(&json!("scalar")).unwrap(), json!("scalar"));
+
+    for empty in [json!({}), json!([])] {
+        let flat = flatten_value(&empty).unwrap();
+        assert_eq!(flat, empty);
+        assert_eq!(unflatten_value(&flat).unwrap(), empty);
+    }
+}
+
+#[test]
+fn test_flatten_and_unflatten_reject_conflicts() {
+    assert!(flatten_value(&json!({"a.b": 1, "a": {"b": 2}})).is_err());
+    assert!(unflatten_value(&json!({"a": 1, "a.b": 2})).is_err());
+    assert_eq!(unflatten_value(&json!({"a.0": 1, "a.2": 2})).unwrap(), json!({"a": {"0": 1, "2": 2}}));
+}
+
+#[test]
+fn test_size_breakdown_parts_sum_to_total() {
+    let value = json!({
+        "name": "Ada \"Countess\" Lovelace",
+        "tags": ["math", "poetry"],
+        "born": 1815,
+        "profile": {"active": true, "notes": null, "ratio": 0.25}
+    });
+
+    let breakdown = size_breakdown(&value);
+
+    assert_eq!(breakdown.total_bytes, canonicalize(&value).len());
+    assert_eq!(canonical_size(&value), breakdown.total_bytes);
+    let bytes: Vec<(&str, usize)> = breakdown.parts.iter().map(|part| (part.name.as_str(), part.bytes)).collect();
+    assert_eq!(bytes, [("profile", 51), ("name", 34), ("tags", 24), ("born", 11)]);
+    // Two braces and three commas.
+    assert_eq!(breakdown.overhead_bytes, 5);
+    assert_eq!(bytes.iter().map(|(_, size)| size).sum::<usize>() + breakdown.overhead_bytes, breakdown.total_bytes);
+
+    let array = size_breakdown(&json!([1, "two", [3]]));
+    assert_eq!(array.total_bytes, 13);
+    assert_eq!(array.parts.iter().map(|part| part.bytes).sum::<usize>() + array.overhead_bytes, 13);
+    assert!(size_breakdown(&json!("text")).parts.is_empty());
+}
+
+#[test]
+fn test_file_name_for_url() {
+    assert_eq!(file_name_for_url("https://example.com/api/users/1.xml?page=2", "json"), "api_users_1.json");
+    assert_eq!(file_name_for_url("/reports/2024 Q1/summary", "yaml"), "reports_2024_Q1_summary.yaml");
+    assert_eq!(file_name_for_url("https://example.com/", "json"), "example.com.json");
+    assert_eq!(file_name_for_url("/..", "json"), "index.json");
+}
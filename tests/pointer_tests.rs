@@ -0,0 +1,33 @@
+use rust_advanced_cli::pointer;
+use serde_json::json;
+
+#[test]
+fn test_set_nested_object_and_array_index() {
+    let mut value = json!({"items": [{"name": "a"}, {"name": "b"}]});
+
+    pointer::set(&mut value, "/items/1/name", json!("renamed")).unwrap();
+    pointer::set(&mut value, "/items/-", json!({"name": "c"})).unwrap();
+
+    assert_eq!(value, json!({"items": [{"name": "a"}, {"name": "renamed"}, {"name": "c"}]}));
+}
+
+#[test]
+fn test_set_creates_intermediate_objects() {
+    let mut value = json!(null);
+
+    pointer::set(&mut value, "/a/b~1c/d", json!(1)).unwrap();
+
+    assert_eq!(value, json!({"a": {"b/c": {"d": 1}}}));
+    assert_eq!(value.pointer("/a/b~1c/d"), Some(&json!(1)));
+}
+
+#[test]
+fn test_set_rejects_invalid_paths() {
+    let mut value = json!({"items": [1], "name": "x"});
+
+    assert!(pointer::set(&mut value, "items", json!(1)).is_err());
+    assert!(pointer::set(&mut value, "/items/5", json!(1)).is_err());
+    assert!(pointer::set(&mut value, "/items/01", json!(1)).is_err());
+    assert!(pointer::set(&mut value, "/name/first", json!(1)).is_err());
+    assert_eq!(value, json!({"items": [1], "name": "x"}));
+}
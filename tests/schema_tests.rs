@@ -0,0 +1,83 @@
+use rust_advanced_cli::schema::{infer_document, infer_schema, validate, SchemaRegistry};
+use rust_advanced_cli::AppError;
+use serde_json::json;
+
+#[test]
+fn test_infer_schema_mixed_object() {
+    let value = json!({
+        "id": 7,
+        "score": 9.5,
+        "name": "octocat",
+        "active": true,
+        "manager": null,
+        "tags": ["a", "b"],
+        "addresses": [
+            {"city": "Paris", "zip": "75001"},
+            {"city": "Lyon", "floor": 2}
+        ]
+    });
+
+    let schema = infer_schema(&value);
+
+    assert_eq!(schema["type"], "object");
+    let properties = &schema["properties"];
+    assert_eq!(properties["id"], json!({"type": "integer"}));
+    assert_eq!(properties["score"], json!({"type": "number"}));
+    assert_eq!(properties["name"], json!({"type": "string"}));
+    assert_eq!(properties["active"], json!({"type": "boolean"}));
+    assert_eq!(properties["manager"], json!({"type": "null"}));
+    assert_eq!(properties["tags"], json!({"type": "array", "items": {"type": "string"}}));
+
+    let address = &properties["addresses"]["items"];
+    assert_eq!(address["type"], "object");
+    assert_eq!(address["properties"]["floor"], json!({"type": "integer"}));
+    assert_eq!(address["required"], json!(["city"]));
+    assert_eq!(schema["required"].as_array().unwrap().len(), 7);
+}
+
+#[test]
+fn test_infer_schema_merges_array_element_types() {
+    assert_eq!(infer_schema(&json!([1, 2.5])), json!({"type": "array", "items": {"type": "number"}}));
+    assert_eq!(
+        infer_schema(&json!([1, "x"])),
+        json!({"type": "array", "items": {"anyOf": [{"type": "integer"}, {"type": "string"}]}})
+    );
+    assert_eq!(infer_schema(&json!([])), json!({"type": "array"}));
+}
+
+#[test]
+fn test_inferred_document_validates_its_source() {
+    let value = json!({"users": [{"id": 1, "email": "a@example.com"}, {"id": 2}], "total": 2});
+
+    let schema = infer_document(&value);
+
+    assert_eq!(schema["$schema"], "https://json-schema.org/draft/2020-12/schema");
+    validate(&schema, &value).unwrap();
+    assert!(validate(&schema, &json!({"users": []})).is_err());
+}
+
+#[test]
+fn test_schema_registry_round_trip() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let registry = SchemaRegistry::new(temp_dir.path().join("schemas"));
+    assert!(registry.list().unwrap().is_empty());
+
+    let file = temp_dir.path().join("user.json");
+    std::fs::write(&file, r#"{"type": "object", "required": ["name"]}"#).unwrap();
+    registry.add("user", &file, false).unwrap();
+    registry.add("user.v2", &file, false).unwrap();
+    assert_eq!(registry.list().unwrap(), ["user", "user.v2"]);
+    assert_eq!(registry.get("user").unwrap()["required"], json!(["name"]));
+
+    let err = registry.add("user", &file, false).unwrap_err();
+    assert!(err.to_string().contains("already exists"), "{}", err);
+    registry.add("user", &file, true).unwrap();
+
+    std::fs::write(&file, r#"{"type": 12}"#).unwrap();
+    assert!(registry.add("broken", &file, false).is_err());
+    assert!(registry.add("../escape", &file, true).is_err());
+
+    registry.remove("user.v2").unwrap();
+    assert_eq!(registry.list().unwrap(), ["user"]);
+    assert!(matches!(registry.get("user.v2"), Err(AppError::NotFound { .. })));
+}
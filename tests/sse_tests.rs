@@ -0,0 +1,42 @@
+use rust_advanced_cli::sse::{SseEvent, SseParser};
+use std::time::Duration;
+
+fn event(name: Option<&str>, id: Option<&str>, data: &str) -> SseEvent {
+    SseEvent {
+        event: name.map(str::to_string),
+        id: id.map(str::to_string),
+        data: data.to_string(),
+    }
+}
+
+#[test]
+fn test_parser_handles_chunks_split_mid_line() {
+    let body = b": keep-alive\nid: 1\nevent: update\ndata: {\"n\":\r\ndata:  1}\n\nretry: 250\n\ndata: plain\n\n";
+    let mut parser = SseParser::default();
+    let mut events = Vec::new();
+    for chunk in body.chunks(3) {
+        events.extend(parser.feed(chunk));
+    }
+
+    assert_eq!(
+        events,
+        vec![
+            event(Some("update"), Some("1"), "{\"n\":\n 1}"),
+            // The id carries over; the event name does not.
+            event(None, Some("1"), "plain"),
+        ]
+    );
+    assert_eq!(parser.last_event_id(), Some("1"));
+    assert_eq!(parser.retry(), Some(Duration::from_millis(250)));
+}
+
+#[test]
+fn test_parser_reset_drops_partial_event() {
+    let mut parser = SseParser::default();
+    assert_eq!(parser.feed(b"id: 7\ndata: complete\n\ndata: cut off").len(), 1);
+    parser.reset();
+
+    assert_eq!(parser.feed(b"\ndata: next\n\n"), vec![event(None, Some("7"), "next")]);
+    assert!(parser.feed(b"id\ndata\n\n")[0].data.is_empty());
+    assert_eq!(parser.last_event_id(), Some(""));
+}
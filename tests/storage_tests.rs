@@ -0,0 +1,643 @@
+use rust_advanced_cli::storage::{decode_binary, manifest_path, ImportOptions, Storage, StoredItem};
+use rust_advanced_cli::AppError;
+use futures::StreamExt;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::Layer;
+
+type SpanLog = Arc<Mutex<Vec<(String, HashMap<String, String>)>>>;
+
+#[derive(Clone, Default)]
+struct SpanCapture {
+    spans: SpanLog,
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for SpanCapture {
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        let mut fields = HashMap::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        self.spans
+            .lock()
+            .unwrap()
+            .push((attrs.metadata().name().to_string(), fields));
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_store_records_span_with_key() {
+    let capture = SpanCapture::default();
+    let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 1).unwrap();
+    storage
+        .store("user_1".to_string(), json!({"secret": "payload"}))
+        .await
+        .unwrap();
+
+    let spans = capture.spans.lock().unwrap();
+    let (_, fields) = spans
+        .iter()
+        .find(|(name, _)| name == "store")
+        .expect("store span recorded");
+    assert_eq!(fields.get("key").map(String::as_str), Some("user_1"));
+    assert!(!fields.contains_key("value"));
+    assert!(!fields.values().any(|v| v.contains("payload")));
+}
+
+#[tokio::test]
+async fn test_store_binary_limits_raw_size() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 1).unwrap();
+
+    // Base64 inflates this past 1 MB, but the raw bytes fit.
+    let fits = vec![0xAB; 900 * 1024];
+    let item = storage.store_binary("blob".to_string(), &fits).await.unwrap();
+    assert_eq!(decode_binary(&item.value).unwrap(), fits);
+
+    let too_large = vec![0xAB; 1024 * 1024 + 1];
+    assert!(storage.store_binary("blob".to_string(), &too_large).await.is_err());
+}
+
+fn write_item_updated_at(dir: &std::path::Path, key: &str, updated_at: &str) {
+    let item = json!({
+        "key": key,
+        "value": 1,
+        "created_at": updated_at,
+        "updated_at": updated_at,
+    });
+    std::fs::write(dir.join(format!("{}.json", key)), item.to_string()).unwrap();
+}
+
+#[tokio::test]
+async fn test_list_in_range_absolute_bounds() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10).unwrap();
+    write_item_updated_at(temp_dir.path(), "old", "2024-01-01T00:00:00Z");
+    write_item_updated_at(temp_dir.path(), "mid", "2024-06-01T00:00:00Z");
+    write_item_updated_at(temp_dir.path(), "new", "2025-01-01T00:00:00Z");
+
+    let since = "2024-03-01T00:00:00Z".parse().unwrap();
+    let until = "2024-12-31T00:00:00Z".parse().unwrap();
+
+    assert_eq!(storage.list_in_range(Some(since), None).await.unwrap(), vec!["mid", "new"]);
+    assert_eq!(storage.list_in_range(None, Some(until)).await.unwrap(), vec!["mid", "old"]);
+    assert_eq!(storage.list_in_range(Some(since), Some(until)).await.unwrap(), vec!["mid"]);
+    assert!(storage.list_in_range(Some(until), Some(since)).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_touch_only_advances_updated_at() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10).unwrap();
+    let stored = storage.store("cached".to_string(), json!({"a": [1, 2]})).await.unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    storage.touch("cached").await.unwrap();
+
+    let touched = storage.get("cached").await.unwrap();
+    assert!(touched.updated_at > stored.updated_at);
+    assert_eq!(touched.created_at, stored.created_at);
+    assert_eq!(touched.id, stored.id);
+    assert_eq!(touched.value, stored.value);
+}
+
+#[tokio::test]
+async fn test_touch_missing_key() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10).unwrap();
+
+    assert!(storage.touch("missing").await.is_err());
+}
+
+#[tokio::test]
+async fn test_metadata_set_and_delete_keep_value() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10).unwrap();
+    let stored = storage.store("report".to_string(), json!({"rows": 3})).await.unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    storage.set_metadata("report", "owner".to_string(), "ops".to_string()).await.unwrap();
+    storage.set_metadata("report", "source".to_string(), "nightly".to_string()).await.unwrap();
+
+    let item = storage.get("report").await.unwrap();
+    assert_eq!(item.metadata.get("owner").map(String::as_str), Some("ops"));
+    assert_eq!(item.metadata.len(), 2);
+    assert_eq!(item.value, stored.value);
+    assert!(item.updated_at > stored.updated_at);
+
+    storage.delete_metadata("report", "owner").await.unwrap();
+    let metadata = storage.get_metadata("report").await.unwrap();
+    assert_eq!(metadata.keys().collect::<Vec<_>>(), vec!["source"]);
+
+    let err = storage.delete_metadata("report", "owner").await.unwrap_err();
+    assert!(matches!(err, AppError::NotFound { .. }), "{}", err);
+    assert!(storage.set_metadata("missing", "a".to_string(), "b".to_string()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_get_many_waits_for_concurrency_permits() {
+    let temp_dir = TempDir::new().unwrap();
+    let limiter = Arc::new(tokio::sync::Semaphore::new(1));
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10)
+        .unwrap()
+        .with_concurrency_limit(Some(limiter.clone()));
+    storage.store("a".to_string(), json!(1)).await.unwrap();
+    let keys = vec!["a".to_string()];
+
+    let held = limiter.acquire().await.unwrap();
+    let blocked = tokio::time::timeout(std::time::Duration::from_millis(100), storage.get_many(&keys, 4)).await;
+    assert!(blocked.is_err(), "read ran without a permit");
+
+    drop(held);
+    let results = storage.get_many(&keys, 4).await;
+    assert_eq!(results[0].as_ref().unwrap().value, json!(1));
+}
+
+#[tokio::test]
+async fn test_path_traversal_keys_are_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().join("data"), None, 10).unwrap();
+
+    for key in ["..", "../escape", "a/../../escape", "..\\..\\escape"] {
+        let err = storage.store(key.to_string(), json!(1)).await.unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }), "{}: {}", key, err);
+        assert!(storage.get(key).await.is_err());
+        assert!(!storage.exists(key).await);
+    }
+    assert!(!temp_dir.path().join("escape.json").exists());
+    assert!(storage.list().await.unwrap().is_empty());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_symlinked_item_outside_data_dir_is_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().join("data"), None, 10).unwrap();
+    let outside = temp_dir.path().join("outside.json");
+    std::fs::write(&outside, "{}").unwrap();
+    std::os::unix::fs::symlink(&outside, temp_dir.path().join("data/victim.json")).unwrap();
+
+    let err = storage.store("victim".to_string(), json!(1)).await.unwrap_err();
+    assert!(err.to_string().contains("outside the data directory"), "{}", err);
+    assert!(storage.get("victim").await.is_err());
+    assert_eq!(std::fs::read_to_string(&outside).unwrap(), "{}");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_dangling_symlinked_item_is_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().join("data"), None, 10).unwrap();
+    let outside = temp_dir.path().join("outside.json");
+    std::os::unix::fs::symlink(&outside, temp_dir.path().join("data/victim.json")).unwrap();
+
+    let err = storage.store("victim".to_string(), json!(1)).await.unwrap_err();
+    assert!(err.to_string().contains("symlink to a missing file"), "{}", err);
+    assert!(!outside.exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_symlinked_data_dir_refused_unless_followed() {
+    let temp_dir = TempDir::new().unwrap();
+    let real = temp_dir.path().join("real");
+    std::fs::create_dir(&real).unwrap();
+    let link = temp_dir.path().join("link");
+    std::os::unix::fs::symlink(&real, &link).unwrap();
+
+    let err = Storage::new(link.clone(), None, 10).unwrap().with_follow_symlinks(false).err().unwrap();
+    assert!(err.to_string().contains("is a symlink"), "{}", err);
+
+    let storage = Storage::new(link, None, 10).unwrap().with_follow_symlinks(true).unwrap();
+    storage.store("ok".to_string(), json!(1)).await.unwrap();
+    assert!(real.join("ok.json").exists());
+}
+
+#[tokio::test]
+async fn test_item_expires_at_absolute_instant() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10).unwrap();
+    storage.store("token".to_string(), json!("abc")).await.unwrap();
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::milliseconds(200);
+    storage.set_expiry("token", Some(expires_at)).await.unwrap();
+    let item = storage.get("token").await.unwrap();
+    assert_eq!(item.expires_at, Some(expires_at));
+    assert!(!item.is_expired());
+
+    tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    let err = storage.get("token").await.unwrap_err();
+    assert!(matches!(err, AppError::NotFound { .. }), "{}", err);
+    assert!(err.to_string().contains("expired at"), "{}", err);
+    assert!(storage.get_many(&["token".to_string()], 1).await[0].is_err());
+
+    // A new value starts without the old expiry.
+    storage.store("token".to_string(), json!("def")).await.unwrap();
+    assert_eq!(storage.get("token").await.unwrap().expires_at, None);
+}
+
+#[tokio::test]
+async fn test_audit_log_records_store_and_get() {
+    let temp_dir = TempDir::new().unwrap();
+    let audit_path = temp_dir.path().join("audit.ndjson");
+    let storage = Storage::new(temp_dir.path().join("data"), None, 10)
+        .unwrap()
+        .with_audit_log(Some(audit_path.clone()));
+
+    storage.store("audited".to_string(), json!({"a": 1})).await.unwrap();
+    storage.get("audited").await.unwrap();
+
+    let records: Vec<serde_json::Value> = std::fs::read_to_string(&audit_path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["operation"], "store");
+    assert_eq!(records[1]["operation"], "get");
+    for record in &records {
+        assert_eq!(record["key"], "audited");
+        assert_eq!(record["result"], "ok");
+        assert!(record["timestamp"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn test_store_with_sets_expiry_in_one_audited_write() {
+    let temp_dir = TempDir::new().unwrap();
+    let audit_path = temp_dir.path().join("audit.ndjson");
+    let storage = Storage::new(temp_dir.path().join("data"), None, 10)
+        .unwrap()
+        .with_audit_log(Some(audit_path.clone()));
+    storage.store("token".to_string(), json!("abc")).await.unwrap();
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::hours(1);
+    let item = storage
+        .store_with("token".to_string(), json!("def"), |item| item.expires_at = Some(expires_at))
+        .await
+        .unwrap();
+    assert_eq!(item.expires_at, Some(expires_at));
+    assert_eq!(storage.get("token").await.unwrap().expires_at, Some(expires_at));
+
+    let operations: Vec<String> = std::fs::read_to_string(&audit_path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["operation"].to_string())
+        .collect();
+    assert_eq!(operations, vec![r#""store""#, r#""store""#, r#""get""#]);
+}
+
+#[tokio::test]
+async fn test_audit_failure_does_not_fail_operation() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().join("data"), None, 10)
+        .unwrap()
+        .with_audit_log(Some(temp_dir.path().join("missing").join("audit.ndjson")));
+
+    storage.store("audited".to_string(), json!(1)).await.unwrap();
+    assert_eq!(storage.get("audited").await.unwrap().value, json!(1));
+}
+
+#[tokio::test]
+async fn test_get_many_preserves_key_order() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10).unwrap();
+    let mut keys: Vec<String> = (0..50).map(|i| format!("key_{:02}", i)).collect();
+    for (i, key) in keys.iter().enumerate() {
+        storage.store(key.clone(), json!(i)).await.unwrap();
+    }
+    keys.insert(10, "missing".to_string());
+
+    let values = |results: Vec<_>| -> Vec<Option<serde_json::Value>> {
+        results.into_iter().map(|result: rust_advanced_cli::Result<StoredItem>| result.ok().map(|item| item.value)).collect()
+    };
+    let sequential = values(storage.get_many(&keys, 1).await);
+    assert_eq!(values(storage.get_many(&keys, 8).await), sequential);
+    assert_eq!(sequential[0], Some(json!(0)));
+    assert_eq!(sequential[10], None);
+    assert_eq!(sequential[50], Some(json!(49)));
+}
+
+#[tokio::test]
+async fn test_namespaces_are_isolated() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().to_path_buf();
+    let proj1 = Storage::new(root.clone(), Some("proj1"), 10).unwrap();
+    let proj2 = Storage::new(root.clone(), Some("proj2"), 10).unwrap();
+    let default = Storage::new(root.clone(), None, 10).unwrap();
+
+    proj1.store("k".to_string(), json!(1)).await.unwrap();
+    proj2.store("k".to_string(), json!(2)).await.unwrap();
+
+    assert_eq!(proj1.get("k").await.unwrap().value, json!(1));
+    assert_eq!(proj2.get("k").await.unwrap().value, json!(2));
+    assert_eq!(proj1.list().await.unwrap(), vec!["k"]);
+    assert!(default.list().await.unwrap().is_empty());
+    assert_eq!(default.namespaces().unwrap(), vec!["proj1", "proj2"]);
+
+    assert!(Storage::new(root, Some("../escape"), 10).is_err());
+}
+
+#[tokio::test]
+async fn test_corrupt_file_reports_path_and_is_not_listed() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10).unwrap();
+    storage.store("good".to_string(), json!(1)).await.unwrap();
+    let corrupt = temp_dir.path().join("broken.json");
+    std::fs::write(&corrupt, "not json {").unwrap();
+    std::fs::write(temp_dir.path().join("other.json"), r#"{"unrelated": true}"#).unwrap();
+
+    let err = storage.get("broken").await.unwrap_err();
+    assert_eq!(err.code(), "CORRUPT_ITEM");
+    assert!(err.to_string().contains(&corrupt.display().to_string()));
+    assert!(err.to_string().contains("rcli migrate"));
+
+    assert_eq!(storage.list().await.unwrap(), vec!["good"]);
+}
+
+#[tokio::test]
+async fn test_import_resumes_after_interruption() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let audit_path = temp_dir.path().join("audit.ndjson");
+    let storage = Storage::new(data_dir.clone(), None, 10)
+        .unwrap()
+        .with_audit_log(Some(audit_path.clone()));
+
+    let archive = temp_dir.path().join("items.ndjson");
+    let lines: String = (0..20).map(|i| format!("{{\"key\": \"item_{:02}\", \"value\": {}}}\n", i, i)).collect();
+    std::fs::write(&archive, lines).unwrap();
+    let options = ImportOptions { overwrite: true, resume: false, concurrency: 4 };
+
+    // A directory in place of one item's file makes that write fail, which
+    // stops the import short of the full set.
+    let blocker = data_dir.join("item_07.json");
+    std::fs::create_dir(&blocker).unwrap();
+    let report = storage.import(&archive, &options).await.unwrap();
+    assert_eq!(report.imported, 19);
+    assert_eq!(report.failed.len(), 1);
+    assert!(manifest_path(&archive).exists());
+
+    // Starting over without --resume is refused while the manifest exists.
+    assert!(storage.import(&archive, &options).await.is_err());
+
+    std::fs::remove_dir(&blocker).unwrap();
+    let resume = ImportOptions { resume: true, ..options };
+    let report = storage.import(&archive, &resume).await.unwrap();
+    assert_eq!(report.imported, 1);
+    assert_eq!(report.already_done, 19);
+    assert!(report.failed.is_empty());
+    assert!(!manifest_path(&archive).exists());
+
+    let mut stores: HashMap<String, usize> = HashMap::new();
+    for line in std::fs::read_to_string(&audit_path).unwrap().lines() {
+        let record: serde_json::Value = serde_json::from_str(line).unwrap();
+        if record["operation"] == "store" && record["result"] == "ok" {
+            *stores.entry(record["key"].as_str().unwrap().to_string()).or_default() += 1;
+        }
+    }
+    assert_eq!(stores.len(), 20);
+    assert!(stores.values().all(|&count| count == 1));
+    assert_eq!(storage.get("item_07").await.unwrap().value, json!(7));
+}
+
+#[tokio::test]
+async fn test_prune_to_removes_oldest() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10).unwrap();
+    // `c` and `b` share a timestamp, so the key decides which goes first.
+    for (key, created_at) in [
+        ("e", "2024-01-01T00:00:00Z"),
+        ("d", "2024-01-02T00:00:00Z"),
+        ("c", "2024-01-03T00:00:00Z"),
+        ("b", "2024-01-03T00:00:00Z"),
+        ("a", "2024-01-04T00:00:00Z"),
+        ("z", "2024-01-05T00:00:00Z"),
+        ("y", "2024-01-06T00:00:00Z"),
+        ("x", "2024-01-07T00:00:00Z"),
+    ] {
+        write_item_updated_at(temp_dir.path(), key, created_at);
+    }
+
+    let removed = storage.prune_to(5).await.unwrap();
+
+    assert_eq!(removed, vec!["e", "d", "b"]);
+    assert_eq!(storage.list().await.unwrap(), vec!["a", "c", "x", "y", "z"]);
+    assert!(storage.prune_to(5).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_max_items_enforced_on_store() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10)
+        .unwrap()
+        .with_max_items(Some(2));
+
+    for key in ["first", "second", "third"] {
+        storage.store(key.to_string(), json!(1)).await.unwrap();
+    }
+
+    assert_eq!(storage.list().await.unwrap(), vec!["second", "third"]);
+}
+
+#[tokio::test]
+async fn test_builder_applies_settings() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::builder()
+        .data_dir(temp_dir.path())
+        .namespace("staging")
+        .max_items(2)
+        .indexed_fields(vec!["status".to_string()])
+        .build()
+        .unwrap();
+
+    for key in ["a", "b", "c"] {
+        storage.store(key.to_string(), json!({"status": "open"})).await.unwrap();
+    }
+
+    assert_eq!(storage.list().await.unwrap(), vec!["b", "c"]);
+    assert_eq!(storage.query_index("status", &json!("open")).await.unwrap(), vec!["b", "c"]);
+    assert!(temp_dir.path().join("staging").join("c.json").exists());
+    assert!(Storage::builder().data_dir(temp_dir.path()).namespace("../x").build().is_err());
+}
+
+#[tokio::test]
+async fn test_index_stays_consistent_across_updates_and_deletes() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10)
+        .unwrap()
+        .with_indexed_fields(vec!["status".to_string(), "priority".to_string()]);
+    // Stored before the index exists, so the first query has to build it.
+    storage.store("a".to_string(), json!({"status": "open", "priority": 1})).await.unwrap();
+    storage.store("b".to_string(), json!({"status": "open", "priority": 2})).await.unwrap();
+    assert_eq!(storage.query_index("status", &json!("open")).await.unwrap(), vec!["a", "b"]);
+
+    storage.store("b".to_string(), json!({"status": "closed", "priority": 2})).await.unwrap();
+    storage.store("c".to_string(), json!({"status": "open"})).await.unwrap();
+    storage.set_pointer("a".to_string(), "/priority", json!(2), false).await.unwrap();
+    storage.delete("c").await.unwrap();
+
+    assert_eq!(storage.query_index("status", &json!("open")).await.unwrap(), vec!["a"]);
+    assert_eq!(storage.query_index("status", &json!("closed")).await.unwrap(), vec!["b"]);
+    assert_eq!(storage.query_index("priority", &json!(2)).await.unwrap(), vec!["a", "b"]);
+    assert!(storage.query_index("priority", &json!(1)).await.unwrap().is_empty());
+    assert!(storage.query_index("priority", &json!("2")).await.unwrap().is_empty());
+
+    let err = storage.query_index("owner", &json!("x")).await.unwrap_err();
+    assert!(err.to_string().contains("not indexed"));
+    assert_eq!(storage.list().await.unwrap(), vec!["a", "b"]);
+}
+
+#[tokio::test]
+async fn test_list_after_pages_without_gaps_or_overlaps() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10).unwrap();
+    for i in 0..11 {
+        storage.store(format!("key-{:02}", i), json!(i)).await.unwrap();
+    }
+    std::fs::write(temp_dir.path().join("key-05x.json"), "not an item").unwrap();
+
+    let mut seen = Vec::new();
+    let mut cursor: Option<String> = None;
+    let mut pages = 0;
+    loop {
+        let page = storage.list_after(cursor.as_deref(), 4).await.unwrap();
+        assert!(page.keys.len() <= 4);
+        seen.extend(page.keys.clone());
+        pages += 1;
+        match page.next {
+            Some(next) => {
+                assert_eq!(page.keys.last(), Some(&next));
+                cursor = Some(next);
+            }
+            None => break,
+        }
+    }
+
+    assert_eq!(pages, 3);
+    assert_eq!(seen, storage.list().await.unwrap());
+    assert_eq!(seen.len(), 11);
+
+    let last = storage.list_after(Some("key-10"), 4).await.unwrap();
+    assert!(last.keys.is_empty() && last.next.is_none());
+    let exact = storage.list_after(Some("key-06"), 4).await.unwrap();
+    assert_eq!(exact.keys, ["key-07", "key-08", "key-09", "key-10"]);
+    assert_eq!(exact.next, None);
+}
+
+#[tokio::test]
+async fn test_store_at_path_round_trips_outside_data_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().join("data"), None, 1).unwrap();
+    let export = temp_dir.path().join("shared.json");
+
+    let first = storage.store_at_path(&export, "shared".to_string(), json!({"v": 1})).await.unwrap();
+    let second = storage.store_at_path(&export, "shared".to_string(), json!({"v": 2})).await.unwrap();
+    assert_eq!(first.id, second.id);
+    assert!(storage.list().await.unwrap().is_empty());
+
+    let item = storage.get_at_path(&export).await.unwrap();
+    assert_eq!(item.key, "shared");
+    assert_eq!(item.value, json!({"v": 2}));
+    let leftovers: Vec<_> = std::fs::read_dir(temp_dir.path())
+        .unwrap()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty());
+
+    let too_large = json!("x".repeat(1024 * 1024 + 1));
+    assert!(storage.store_at_path(&export, "shared".to_string(), too_large).await.is_err());
+    assert_eq!(storage.get_at_path(&export).await.unwrap().value, json!({"v": 2}));
+
+    let dir = temp_dir.path().join("data");
+    let err = storage.store_at_path(&dir, "x".to_string(), json!(1)).await.unwrap_err();
+    assert!(err.to_string().contains("is a directory"));
+    assert!(storage.get_at_path(&dir).await.unwrap_err().to_string().contains("is a directory"));
+    assert!(matches!(
+        storage.get_at_path(&temp_dir.path().join("missing.json")).await,
+        Err(AppError::NotFound { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_key_pattern_rejects_non_matching_keys() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10)
+        .unwrap()
+        .with_key_pattern(Some("[a-z]+(/[a-z0-9-]+)*"))
+        .unwrap();
+
+    storage.store("users/alice-1".to_string(), json!(1)).await.unwrap();
+    // The pattern has to match the whole key, not just a part of it.
+    for key in ["Users/alice", "users/alice!", "/users"] {
+        match storage.store(key.to_string(), json!(1)).await {
+            Err(AppError::Validation { message }) => assert!(message.contains("[a-z]+(/[a-z0-9-]+)*"), "{}", message),
+            other => panic!("expected a validation error for '{}', got {:?}", key, other),
+        }
+    }
+    assert_eq!(storage.list().await.unwrap().len(), 1);
+    assert!(Storage::new(temp_dir.path().to_path_buf(), None, 10).unwrap().with_key_pattern(Some("(")).is_err());
+}
+
+#[tokio::test]
+async fn test_normalized_key_is_stored_under_normalized_form() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::builder()
+        .data_dir(temp_dir.path())
+        .key_pattern("[a-z/]+")
+        .normalize_keys(true)
+        .indexed_fields(vec!["v".to_string()])
+        .build()
+        .unwrap();
+
+    let item = storage.store("  Users/Alice ".to_string(), json!({"v": 1})).await.unwrap();
+    assert_eq!(item.key, "users/alice");
+    assert_eq!(storage.list().await.unwrap().len(), 1);
+    assert_eq!(storage.get("USERS/ALICE").await.unwrap().value, json!({"v": 1}));
+
+    storage.delete("USERS/ALICE").await.unwrap();
+    assert!(storage.query_index("v", &json!(1)).await.unwrap().is_empty());
+
+    let outside = temp_dir.path().join("outside.json");
+    let item = storage.store_at_path(&outside, "Users/Bob".to_string(), json!(2)).await.unwrap();
+    assert_eq!(item.key, "users/bob");
+    assert!(storage.store_at_path(&outside, "users/bob-2".to_string(), json!(2)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_stream_list_yields_same_keys_as_list() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf(), None, 10).unwrap();
+    for i in 0..25 {
+        storage.store(format!("key{:02}", i), json!({"n": i})).await.unwrap();
+    }
+    std::fs::write(temp_dir.path().join("broken.json"), "not an item").unwrap();
+    std::fs::write(temp_dir.path().join("notes.txt"), "ignored").unwrap();
+    std::fs::create_dir(temp_dir.path().join("sub.json")).unwrap();
+
+    let mut stream = std::pin::pin!(storage.stream_list());
+    let mut streamed = Vec::new();
+    while let Some(key) = stream.next().await {
+        streamed.push(key.unwrap());
+    }
+    streamed.sort();
+
+    let listed = storage.list().await.unwrap();
+    assert_eq!(listed.len(), 25);
+    assert_eq!(streamed, listed);
+}
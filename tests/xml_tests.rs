@@ -0,0 +1,45 @@
+use rust_advanced_cli::xml::{from_xml, to_xml};
+use rust_advanced_cli::AppError;
+use serde_json::json;
+
+#[test]
+fn test_xml_round_trip_simple_object() {
+    let value = json!({
+        "name": "octocat",
+        "address": {"city": "Paris", "zip": "75001"},
+        "tags": ["a", "b"],
+        "nickname": null
+    });
+
+    let xml = to_xml(&value).unwrap();
+    assert!(xml.contains("<name>octocat</name>"));
+    assert!(xml.contains("<item>a</item>"));
+
+    assert_eq!(from_xml(&xml).unwrap(), value);
+}
+
+#[test]
+fn test_xml_escapes_text() {
+    let value = json!({"expr": "a < b & c"});
+
+    let xml = to_xml(&value).unwrap();
+    assert!(xml.contains("a &lt; b &amp; c"));
+    assert_eq!(from_xml(&xml).unwrap(), value);
+}
+
+#[test]
+fn test_to_xml_rejects_unmappable_key() {
+    let err = to_xml(&json!({"first name": "Ada"})).unwrap_err();
+
+    assert!(matches!(err, AppError::Validation { .. }));
+    assert!(err.to_string().contains("Cannot map key 'first name'"));
+}
+
+#[test]
+fn test_from_xml_rejects_attributes_and_mixed_content() {
+    let err = from_xml(r#"<root><user id="1">Ada</user></root>"#).unwrap_err();
+    assert!(err.to_string().contains("Cannot map XML attributes on <user>"));
+
+    let err = from_xml("<root>hello <b>world</b></root>").unwrap_err();
+    assert!(err.to_string().contains("mixed text and element content in <root>"));
+}